@@ -1,27 +1,56 @@
+use crate::track::AudioQuality;
 use poise::CreateReply;
 use songbird::tracks::PlayMode;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::Duration;
 
 pub struct Data {
-    pub session: tokio::sync::Mutex<crate::session::Session>,
+    pub tidal: std::sync::Arc<crate::session_pool::SessionPool>,
     pub spool_read_ahead_bytes: u64,
     pub collection_track_fetch_concurrency: usize,
     pub command_prefix: String,
     pub repeat_modes:
         std::sync::Arc<tokio::sync::Mutex<HashMap<serenity::model::id::GuildId, RepeatMode>>>,
+    pub default_audio_quality: AudioQuality,
+    pub audio_qualities:
+        std::sync::Arc<tokio::sync::Mutex<HashMap<serenity::model::id::GuildId, AudioQuality>>>,
     pub playback_status: std::sync::Arc<tokio::sync::Mutex<PlaybackStatusState>>,
+    pub autoplay:
+        std::sync::Arc<tokio::sync::Mutex<HashMap<serenity::model::id::GuildId, AutoplayState>>>,
+    pub track_autocomplete: std::sync::Arc<tokio::sync::Mutex<TrackAutocompleteState>>,
+    pub queue_persistence: std::sync::Arc<crate::queue_state::QueuePersistence>,
 }
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
 
 const QUEUE_PAGE_SIZE: usize = 10;
 const SEARCH_RESULT_LIMIT: u32 = 50;
+// Per section, so an `all` search still fits in a few pages
+const SEARCH_ALL_RESULT_LIMIT: u32 = 10;
 const SEARCH_SELECTION_TIMEOUT: Duration = Duration::from_secs(120);
+/// Most linked tracks offered in the "did you mean" menu for one query; later uncertain matches
+/// are reported as not found instead.
+const MATCH_SUGGESTION_LIMIT: usize = 3;
 const QUEUE_PAGINATION_TIMEOUT: Duration = Duration::from_secs(120);
 const TRACK_INFO_TIMEOUT: Duration = Duration::from_secs(2);
 const ACTIVITY_NAME_MAX_CHARS: usize = 128;
 const DEFAULT_AUDIO_BITRATE: u32 = 64_000;
+const AUTOPLAY_HISTORY_LEN: usize = 50;
+const AUTOPLAY_SEED_COUNT: usize = 3;
+const AUTOPLAY_BATCH_SIZE: usize = 5;
+const AUTOCOMPLETE_RESULT_LIMIT: u32 = 10;
+const AUTOCOMPLETE_MIN_QUERY_CHARS: usize = 2;
+// Discord sends a request per keystroke; only the last one after a short pause hits Tidal
+const AUTOCOMPLETE_DEBOUNCE: Duration = Duration::from_millis(300);
+const AUTOCOMPLETE_CACHE_TTL: Duration = Duration::from_secs(300);
+const AUTOCOMPLETE_CACHE_LEN: usize = 200;
+const AUTOCOMPLETE_CHOICE_MAX_CHARS: usize = 100;
+const LYRICS_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+const LYRICS_PAGINATION_TIMEOUT: Duration = Duration::from_secs(120);
+// Extra time a synced lyrics message keeps following a track, so pauses do not cut it short
+const LYRICS_FOLLOW_GRACE: Duration = Duration::from_secs(600);
+// Discord stops accepting edits to a slash command's reply after this long
+const INTERACTION_TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum PlaybackStatusKind {
@@ -40,7 +69,127 @@ pub struct PlaybackStatusState {
     current: Option<PlaybackStatus>,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, poise::ChoiceParameter)]
+/// Recent autocomplete results and the newest pending request per user.
+#[derive(Default, Debug)]
+pub struct TrackAutocompleteState {
+    cache: HashMap<String, (std::time::Instant, Vec<crate::track::TrackSummary>)>,
+    latest_requests: HashMap<serenity::model::id::UserId, u64>,
+    next_request: u64,
+}
+
+impl TrackAutocompleteState {
+    fn cached(
+        &self,
+        query: &str,
+        now: std::time::Instant,
+    ) -> Option<Vec<crate::track::TrackSummary>> {
+        self.cache
+            .get(query)
+            .filter(|(stored_at, _)| now.duration_since(*stored_at) < AUTOCOMPLETE_CACHE_TTL)
+            .map(|(_, tracks)| tracks.clone())
+    }
+
+    fn store(
+        &mut self,
+        query: String,
+        tracks: Vec<crate::track::TrackSummary>,
+        now: std::time::Instant,
+    ) {
+        self.cache
+            .retain(|_, (stored_at, _)| now.duration_since(*stored_at) < AUTOCOMPLETE_CACHE_TTL);
+        if self.cache.len() >= AUTOCOMPLETE_CACHE_LEN
+            && let Some(oldest) = self
+                .cache
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(query, _)| query.clone())
+        {
+            self.cache.remove(&oldest);
+        }
+
+        self.cache.insert(query, (now, tracks));
+    }
+
+    fn begin_request(&mut self, user_id: serenity::model::id::UserId) -> u64 {
+        self.next_request += 1;
+        self.latest_requests.insert(user_id, self.next_request);
+        self.next_request
+    }
+
+    fn is_latest_request(&self, user_id: serenity::model::id::UserId, request: u64) -> bool {
+        self.latest_requests.get(&user_id) == Some(&request)
+    }
+
+    /// Forgets the user once their latest request is done, so users are not tracked forever.
+    fn finish_request(&mut self, user_id: serenity::model::id::UserId, request: u64) {
+        if self.is_latest_request(user_id, request) {
+            self.latest_requests.remove(&user_id);
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct AutoplayState {
+    enabled: bool,
+    recent_track_ids: VecDeque<String>, // Oldest first
+}
+
+impl AutoplayState {
+    fn record_played(&mut self, track_id: &str) {
+        self.recent_track_ids.retain(|id| id != track_id);
+        self.recent_track_ids.push_back(track_id.to_string());
+        if self.recent_track_ids.len() > AUTOPLAY_HISTORY_LEN {
+            self.recent_track_ids.pop_front();
+        }
+    }
+
+    /// The most recently played tracks, newest first.
+    fn seed_track_ids(&self) -> Vec<String> {
+        self.recent_track_ids
+            .iter()
+            .rev()
+            .take(AUTOPLAY_SEED_COUNT)
+            .cloned()
+            .collect()
+    }
+
+    /// Takes recommendations from each seed in turn, skipping anything played recently.
+    fn pick_recommendations(
+        &self,
+        recommendations: Vec<Vec<crate::track::Track>>,
+        limit: usize,
+    ) -> Vec<crate::track::Track> {
+        let mut seen = self
+            .recent_track_ids
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        let mut sources = recommendations
+            .into_iter()
+            .map(Vec::into_iter)
+            .collect::<Vec<_>>();
+        let mut picked = Vec::new();
+
+        while picked.len() < limit && !sources.is_empty() {
+            sources.retain_mut(|source| match source.next() {
+                Some(track) => {
+                    if picked.len() < limit && seen.insert(track.id.clone()) {
+                        picked.push(track);
+                    }
+                    true
+                }
+                None => false,
+            });
+        }
+
+        picked
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, poise::ChoiceParameter, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum RepeatMode {
     #[name = "off"]
     Off,
@@ -50,6 +199,75 @@ pub enum RepeatMode {
     Queue,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, poise::ChoiceParameter)]
+pub enum ArtistView {
+    #[name = "top_tracks"]
+    TopTracks,
+    #[name = "albums"]
+    Albums,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, poise::ChoiceParameter)]
+pub enum SearchType {
+    #[name = "tracks"]
+    Tracks,
+    #[name = "albums"]
+    Albums,
+    #[name = "playlists"]
+    Playlists,
+    #[name = "artists"]
+    Artists,
+    #[name = "all"]
+    All,
+}
+
+impl SearchType {
+    fn api_types(self) -> &'static str {
+        match self {
+            Self::Tracks => "tracks",
+            Self::Albums => "albums",
+            Self::Playlists => "playlists",
+            Self::Artists => "artists",
+            Self::All => "tracks,albums,playlists,artists",
+        }
+    }
+
+    fn result_limit(self) -> u32 {
+        match self {
+            Self::All => SEARCH_ALL_RESULT_LIMIT,
+            _ => SEARCH_RESULT_LIMIT,
+        }
+    }
+
+    fn item_name(self) -> &'static str {
+        match self {
+            Self::Tracks => "a track",
+            Self::Albums => "an album",
+            Self::Playlists => "a playlist",
+            Self::Artists => "an artist",
+            Self::All => "a result",
+        }
+    }
+
+    fn not_found_message(self) -> &'static str {
+        match self {
+            Self::Tracks => "No track was found on Tidal.",
+            Self::Albums => "No album was found on Tidal.",
+            Self::Playlists => "No playlist was found on Tidal.",
+            Self::Artists => "No artist was found on Tidal.",
+            Self::All => "Nothing was found on Tidal.",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, poise::ChoiceParameter)]
+pub enum FavoritesKind {
+    #[name = "tracks"]
+    Tracks,
+    #[name = "albums"]
+    Albums,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, poise::ChoiceParameter)]
 pub enum RepeatCommandMode {
     #[name = "off"]
@@ -82,16 +300,140 @@ impl songbird::events::EventHandler for TrackErrorNotifier {
     }
 }
 
+#[derive(Clone)]
 struct RepeatModeNotifier {
     handler_lock: std::sync::Arc<tokio::sync::Mutex<songbird::Call>>,
+    tidal: std::sync::Arc<crate::session_pool::SessionPool>,
     repeat_modes:
         std::sync::Arc<tokio::sync::Mutex<HashMap<serenity::model::id::GuildId, RepeatMode>>>,
     serenity_context: serenity::client::Context,
     playback_status: std::sync::Arc<tokio::sync::Mutex<PlaybackStatusState>>,
+    autoplay:
+        std::sync::Arc<tokio::sync::Mutex<HashMap<serenity::model::id::GuildId, AutoplayState>>>,
+    default_audio_quality: AudioQuality,
+    audio_qualities:
+        std::sync::Arc<tokio::sync::Mutex<HashMap<serenity::model::id::GuildId, AudioQuality>>>,
     guild_id: serenity::model::id::GuildId,
     spool_read_ahead_bytes: u64,
 }
 
+impl RepeatModeNotifier {
+    async fn record_played(&self, track: &crate::track::Track) {
+        self.autoplay
+            .lock()
+            .await
+            .entry(self.guild_id)
+            .or_default()
+            .record_played(&track.id);
+    }
+
+    /// Queues recommendations based on the last few played tracks once the queue is empty.
+    async fn autoplay_if_queue_empty(&self) {
+        let seed_track_ids = match self.autoplay.lock().await.get(&self.guild_id) {
+            Some(state) if state.enabled => state.seed_track_ids(),
+            _ => return,
+        };
+        if seed_track_ids.is_empty() || !self.handler_lock.lock().await.queue().is_empty() {
+            return;
+        }
+
+        let audio_quality = self
+            .audio_qualities
+            .lock()
+            .await
+            .get(&self.guild_id)
+            .copied()
+            .unwrap_or(self.default_audio_quality);
+        let mut radios = tokio::task::JoinSet::new();
+        for (index, track_id) in seed_track_ids.into_iter().enumerate() {
+            let session = self.tidal.session(Some(self.guild_id));
+            radios.spawn(async move {
+                let result = session
+                    .find_track_radio(&track_id, crate::session::TRACK_RADIO_LIMIT, audio_quality)
+                    .await;
+                (index, track_id, result)
+            });
+        }
+
+        let mut radio_results = Vec::new();
+        while let Some(result) = radios.join_next().await {
+            match result {
+                Ok(radio) => radio_results.push(radio),
+                Err(error) => tracing::error!(%error, "Autoplay recommendation task failed"),
+            }
+        }
+        radio_results.sort_by_key(|(index, _, _)| *index);
+
+        let mut recommendations = Vec::new();
+        for (_, track_id, result) in radio_results {
+            match result {
+                Ok(tracks) => recommendations.push(tracks),
+                Err(error) => {
+                    tracing::warn!(
+                        guild_id = %self.guild_id,
+                        track_id = %track_id,
+                        %error,
+                        "Failed to fetch autoplay recommendations"
+                    );
+                }
+            }
+        }
+
+        let tracks = match self.autoplay.lock().await.get(&self.guild_id) {
+            Some(state) if state.enabled => {
+                state.pick_recommendations(recommendations, AUTOPLAY_BATCH_SIZE)
+            }
+            _ => return,
+        };
+        if tracks.is_empty() {
+            tracing::info!(guild_id = %self.guild_id, "No autoplay recommendations found");
+            return;
+        }
+
+        let mut handler = self.handler_lock.lock().await;
+        // Someone may have queued a track while the recommendations were loading
+        if !handler.queue().is_empty() {
+            return;
+        }
+
+        let audio_bitrate =
+            current_voice_channel_bitrate(&self.serenity_context, self.guild_id, &handler);
+        for (index, track) in tracks.iter().enumerate() {
+            match enqueue_track_with_spool(
+                &mut handler,
+                &self.tidal,
+                self.guild_id,
+                track,
+                Duration::ZERO,
+                self.spool_read_ahead_bytes,
+                audio_bitrate,
+            )
+            .await
+            {
+                Ok(new_handle) if index == 0 => {
+                    begin_playback_status(
+                        &self.serenity_context,
+                        self.playback_status.clone(),
+                        self.guild_id,
+                        new_handle,
+                    )
+                    .await;
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    tracing::warn!(%error, "Failed to queue autoplay track");
+                }
+            }
+        }
+
+        tracing::info!(
+            guild_id = %self.guild_id,
+            track_count = tracks.len(),
+            "Queued autoplay recommendations"
+        );
+    }
+}
+
 #[serenity::async_trait]
 impl songbird::events::EventHandler for RepeatModeNotifier {
     async fn act(
@@ -105,12 +447,14 @@ impl songbird::events::EventHandler for RepeatModeNotifier {
                 .copied()
                 .unwrap_or(RepeatMode::Off)
         };
-        if repeat_mode == RepeatMode::Off {
-            return None;
-        }
 
         if let songbird::events::EventContext::Track(track_list) = ctx {
             for (state, handle) in *track_list {
+                if matches!(state.playing, PlayMode::End | PlayMode::Stop) {
+                    self.record_played(&handle.data::<crate::track::Track>())
+                        .await;
+                }
+
                 match (repeat_mode, &state.playing) {
                     (RepeatMode::Track, PlayMode::Play) => {
                         if let Err(error) = handle.enable_loop() {
@@ -127,6 +471,8 @@ impl songbird::events::EventHandler for RepeatModeNotifier {
                         );
                         match enqueue_track_with_spool(
                             &mut handler,
+                            &self.tidal,
+                            self.guild_id,
                             &track,
                             Duration::ZERO,
                             self.spool_read_ahead_bytes,
@@ -148,6 +494,12 @@ impl songbird::events::EventHandler for RepeatModeNotifier {
                             }
                         }
                     }
+                    // Only natural ends trigger autoplay, so `stop` and `skip` stay silent. It
+                    // runs on its own task so this event does not hold up the call.
+                    (RepeatMode::Off, PlayMode::End) => {
+                        let notifier = self.clone();
+                        tokio::spawn(async move { notifier.autoplay_if_queue_empty().await });
+                    }
                     _ => {}
                 }
             }
@@ -265,26 +617,135 @@ fn help_message(prefix: &str) -> String {
             "`/ping` or `{0}ping` - Check whether the bot is responding.\n",
             "`/join` or `{0}join` (`{0}j`, `{0}summon`, `{0}connect`) - Join your current voice channel.\n",
             "`/volume [0-200]` or `{0}volume [0-200]` (`{0}vol`) - Show or set the playback volume.\n",
-            "`/play <query-or-url>` or `{0}play <query-or-url>` (`{0}p`) - Queue a song, album, playlist, Tidal URL, or supported YouTube URL.\n",
-            "`/search <query>` or `{0}search <query>` - Search Tidal and choose which result to queue.\n",
+            "`/play <query-or-url>` or `{0}play <query-or-url>` (`{0}p`) - Queue a song, album, playlist, or a Tidal, Spotify, YouTube, Apple Music, Deezer, SoundCloud, or Bandcamp link.\n",
+            "`/search <query> [tracks|albums|playlists|artists|all]` or `{0}search [tracks|albums|playlists|artists|all] <query>` - Search Tidal and choose which result to queue. Albums and playlists queue every track; artists queue their top tracks.\n",
+            "`/artist <name-or-url> [top_tracks|albums]` or `{0}artist [top_tracks|albums] <name-or-url>` - Queue an artist's top tracks, or browse their albums and choose one to queue.\n",
+            "`/radio [query-or-url]` or `{0}radio [query-or-url]` - Queue tracks similar to the current track, or to a song you search for.\n",
             "`/pause` or `{0}pause` (`{0}wait`) - Pause the current track.\n",
             "`/resume` or `{0}resume` (`{0}unpause`, `{0}continue`) - Resume playback.\n",
             "`/seek <position>` or `{0}seek <position>` (`{0}seekto`, `{0}jump`, `{0}jumpto`, `{0}goto`) - Seek the current track to `seconds`, `mm:ss`, or `hh:mm:ss`.\n",
             "`/skip` or `{0}skip` (`{0}s`, `{0}next`) - Skip the current track.\n",
-            "`/playnext <query-or-url>` or `{0}playnext <query-or-url>` (`{0}pn`) - Insert a song, album, playlist, Tidal URL, or supported YouTube URL right after the current track.\n",
+            "`/favorites [tracks|albums]` or `{0}favorites [tracks|albums]` (`{0}favs`) - Browse the linked Tidal account's favorites and choose one to queue.\n",
+            "`/myplaylists` or `{0}myplaylists` - Browse the linked Tidal account's playlists and choose one to queue.\n",
+            "`/playlist save <name>` or `{0}playlist save <name>` - Save the current queue as a new playlist on the linked Tidal account.\n",
+            "`/playlist add [name-or-url]` or `{0}playlist add [name-or-url]` - Add the current track to one of the linked Tidal account's playlists.\n",
+            "`/playnext <query-or-url>` or `{0}playnext <query-or-url>` (`{0}pn`) - Insert a song, album, playlist, or a Tidal, Spotify, YouTube, Apple Music, Deezer, SoundCloud, or Bandcamp link right after the current track.\n",
+            "`/quality [low|high|lossless|hi_res]` or `{0}quality [low|high|lossless|hi_res]` - Show or set the Tidal audio quality used for newly queued tracks.\n",
             "`/repeat [track|all|off]` or `{0}repeat [track|all|off]` (`{0}loop`) - Repeat the current track, all tracks, or turn repeat off.\n",
+            "`/autoplay [true|false]` or `{0}autoplay [true|false]` - Toggle queueing similar tracks when the queue runs out.\n",
             "`/shuffle` or `{0}shuffle` - Shuffle the queued tracks.\n",
             "`/remove <position>` or `{0}remove <position>` (`{0}delete <position>`) - Remove a queued track by its position in `queue`. Position `1` is the next track.\n",
             "`/clear` or `{0}clear` - Clear queued tracks without stopping the current track.\n",
             "`/stop` or `{0}stop` - Stop playback and clear the queue.\n",
             "`/current` or `{0}current` (`{0}currentplaying`, `{0}now`, `{0}nowplaying`, `{0}playing`, `{0}np`) - Show the current track.\n",
+            "`/lyrics` or `{0}lyrics` - Show lyrics for the current track. Synced lyrics follow along as the song plays.\n",
             "`/leave` or `{0}leave` (`{0}disconnect`) - Disconnect from voice.\n",
+            "`/tidal login|logout [account]` or `{0}tidal login|logout [account]` - Bot owner only. Log a Tidal account in by DM or log it out; defaults to the account this server uses.\n`/tidal status` or `{0}tidal status` - Bot owner only. Show every Tidal account and which one this server uses.\n",
             "`/queue` or `{0}queue` (`{0}q`, `{0}list`, `{0}l`) - Show the current queue.\n",
         ),
         prefix
     )
 }
 
+fn tidal_track_url(track_id: &str) -> String {
+    format!("https://tidal.com/browse/track/{}", track_id)
+}
+
+fn tidal_playlist_url(playlist_id: &str) -> String {
+    format!("https://tidal.com/browse/playlist/{}", playlist_id)
+}
+
+/// Moves required options ahead of optional ones, as Discord requires. Prefix commands can
+/// take an optional keyword before the text, like `search albums <query>`; slash options are
+/// read by name, so only the order shown in Discord changes.
+pub fn required_options_first(
+    mut commands: Vec<poise::Command<Data, Error>>,
+) -> Vec<poise::Command<Data, Error>> {
+    for command in &mut commands {
+        command
+            .parameters
+            .sort_by_key(|parameter| !parameter.required);
+        command.subcommands = required_options_first(std::mem::take(&mut command.subcommands));
+    }
+    commands
+}
+
+/// Returns the track ID when `query` is a Tidal track URL, such as an autocomplete choice.
+fn parse_tidal_track_url(query: &str) -> Option<String> {
+    let url = url::Url::parse(query.trim()).ok()?;
+    match crate::url_handler::parse_tidal_resource(&url)? {
+        (crate::url_handler::TidalResource::Track, id) => Some(id),
+        _ => None,
+    }
+}
+
+fn autocomplete_choices(
+    tracks: &[crate::track::TrackSummary],
+) -> Vec<serenity::all::AutocompleteChoice> {
+    tracks
+        .iter()
+        .map(|track| {
+            serenity::all::AutocompleteChoice::new(
+                truncate_chars(
+                    &get_formatted_track_summary(track),
+                    AUTOCOMPLETE_CHOICE_MAX_CHARS,
+                ),
+                tidal_track_url(&track.id),
+            )
+        })
+        .collect()
+}
+
+/// Suggests Tidal tracks while a query is typed. Each choice's value is the track's Tidal URL,
+/// so picking one queues exactly that track.
+async fn autocomplete_track(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<serenity::all::AutocompleteChoice> {
+    let query = partial.trim();
+    if query.chars().count() < AUTOCOMPLETE_MIN_QUERY_CHARS || url::Url::parse(query).is_ok() {
+        return Vec::new();
+    }
+
+    let cache_key = query.to_lowercase();
+    let user_id = ctx.author().id;
+    let request = {
+        let mut state = ctx.data().track_autocomplete.lock().await;
+        if let Some(tracks) = state.cached(&cache_key, std::time::Instant::now()) {
+            return autocomplete_choices(&tracks);
+        }
+        state.begin_request(user_id)
+    };
+
+    tokio::time::sleep(AUTOCOMPLETE_DEBOUNCE).await;
+    if !ctx
+        .data()
+        .track_autocomplete
+        .lock()
+        .await
+        .is_latest_request(user_id, request)
+    {
+        return Vec::new();
+    }
+
+    let tracks = tidal_session(&ctx)
+        .search_track_summaries(query, AUTOCOMPLETE_RESULT_LIMIT)
+        .await;
+    let mut state = ctx.data().track_autocomplete.lock().await;
+    state.finish_request(user_id, request);
+    let tracks = match tracks {
+        Ok(tracks) => tracks,
+        Err(error) => {
+            tracing::warn!(%error, query, "Failed to autocomplete track search");
+            return Vec::new();
+        }
+    };
+
+    state.store(cache_key, tracks.clone(), std::time::Instant::now());
+
+    autocomplete_choices(&tracks)
+}
+
 fn format_duration_seconds(total_seconds: u64) -> String {
     let hours = total_seconds / 3600;
     let minutes = (total_seconds % 3600) / 60;
@@ -437,6 +898,22 @@ async fn current_repeat_mode(ctx: Context<'_>) -> Result<Option<RepeatMode>, Err
     Ok(Some(repeat_mode))
 }
 
+async fn guild_audio_quality(data: &Data, guild_id: serenity::model::id::GuildId) -> AudioQuality {
+    data.audio_qualities
+        .lock()
+        .await
+        .get(&guild_id)
+        .copied()
+        .unwrap_or(data.default_audio_quality)
+}
+
+async fn current_audio_quality(ctx: &Context<'_>) -> AudioQuality {
+    match ctx.guild_id() {
+        Some(guild_id) => guild_audio_quality(ctx.data(), guild_id).await,
+        None => ctx.data().default_audio_quality,
+    }
+}
+
 fn parse_seek_position(position: &str) -> Result<Duration, String> {
     let position = position.trim();
     if position.is_empty() {
@@ -501,11 +978,14 @@ mod tests {
 
     fn track(title: &str, featured_artists: Vec<&str>, duration: u32) -> crate::track::Track {
         crate::track::Track {
+            id: "123456789".to_string(),
             title: title.to_string(),
             artist: "Main Artist".to_string(),
             featured_artists: featured_artists.into_iter().map(String::from).collect(),
             duration,
-            stream_url: "https://example.com/stream".to_string(),
+            audio_quality: AudioQuality::High,
+            isrc: None,
+            served_quality: Default::default(),
         }
     }
 
@@ -566,6 +1046,157 @@ mod tests {
         assert!(status.ends_with("..."));
     }
 
+    #[test]
+    fn formats_album_search_choice() {
+        let album = crate::track::AlbumSummary {
+            id: "55555".to_string(),
+            title: "Album Title".to_string(),
+            artist: "Main Artist".to_string(),
+            track_count: 1,
+            release_year: Some("2013".to_string()),
+        };
+        let choice = SearchChoice::album(&album);
+
+        assert_eq!(choice.line, "Main Artist - Album Title (2013 · 1 track)");
+        assert_eq!(choice.label, "Album Title");
+        assert_eq!(choice.description, "2013 · 1 track");
+    }
+
+    #[test]
+    fn labels_mixed_search_results_with_their_kind() {
+        let results = SearchResult::from_results(crate::session::SearchResults {
+            playlists: vec![crate::track::PlaylistSummary {
+                id: "abc".to_string(),
+                title: "Road Trip".to_string(),
+                track_count: 2,
+            }],
+            artists: vec![crate::track::ArtistSummary {
+                id: "7".to_string(),
+                name: "Main Artist".to_string(),
+            }],
+            ..Default::default()
+        });
+
+        let playlist = results[0].choice(true);
+        assert_eq!(playlist.line, "Playlist: Road Trip (2 tracks)");
+        assert_eq!(playlist.description, "Playlist · 2 tracks");
+        let artist = results[1].choice(false);
+        assert_eq!(artist.line, "Main Artist");
+        assert_eq!(artist.description, "Queue top tracks");
+    }
+
+    #[test]
+    fn expires_and_evicts_autocomplete_cache_entries() {
+        let mut state = TrackAutocompleteState::default();
+        let start = std::time::Instant::now();
+
+        state.store("first".to_string(), Vec::new(), start);
+        assert!(state.cached("first", start).is_some());
+        assert!(
+            state
+                .cached("first", start + AUTOCOMPLETE_CACHE_TTL)
+                .is_none()
+        );
+
+        for index in 1..=AUTOCOMPLETE_CACHE_LEN {
+            state.store(
+                format!("query {index}"),
+                Vec::new(),
+                start + Duration::from_millis(index as u64),
+            );
+        }
+        assert_eq!(state.cache.len(), AUTOCOMPLETE_CACHE_LEN);
+        assert!(!state.cache.contains_key("first"));
+    }
+
+    #[test]
+    fn only_latest_autocomplete_request_per_user_runs() {
+        let mut state = TrackAutocompleteState::default();
+        let user = serenity::model::id::UserId::new(1);
+        let other_user = serenity::model::id::UserId::new(2);
+
+        let stale = state.begin_request(user);
+        let other = state.begin_request(other_user);
+        let latest = state.begin_request(user);
+
+        assert!(!state.is_latest_request(user, stale));
+        assert!(state.is_latest_request(user, latest));
+        assert!(state.is_latest_request(other_user, other));
+
+        state.finish_request(user, stale);
+        assert!(state.is_latest_request(user, latest));
+        state.finish_request(user, latest);
+        state.finish_request(other_user, other);
+        assert!(state.latest_requests.is_empty());
+    }
+
+    #[test]
+    fn parses_tidal_track_urls_from_autocomplete() {
+        assert_eq!(
+            parse_tidal_track_url(&tidal_track_url("12345")),
+            Some("12345".to_string())
+        );
+        assert_eq!(
+            parse_tidal_track_url("https://tidal.com/browse/album/1"),
+            None
+        );
+        assert_eq!(parse_tidal_track_url("some song"), None);
+    }
+
+    #[test]
+    fn lists_required_slash_options_first() {
+        let commands = required_options_first(vec![search(), artist()]);
+        let option_names = |command: &poise::Command<Data, Error>| {
+            command
+                .parameters
+                .iter()
+                .map(|parameter| parameter.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(option_names(&commands[0]), ["query", "type"]);
+        assert_eq!(option_names(&commands[1]), ["name_or_url", "view"]);
+    }
+
+    fn track_with_id(id: &str) -> crate::track::Track {
+        crate::track::Track {
+            id: id.to_string(),
+            ..track("Song Title", Vec::new(), 185)
+        }
+    }
+
+    #[test]
+    fn autoplay_seeds_from_most_recent_tracks() {
+        let mut state = AutoplayState::default();
+        for id in ["1", "2", "3", "4", "2"] {
+            state.record_played(id);
+        }
+
+        assert_eq!(state.seed_track_ids(), vec!["2", "4", "3"]);
+    }
+
+    #[test]
+    fn autoplay_skips_recent_and_duplicate_recommendations() {
+        let mut state = AutoplayState::default();
+        state.record_played("1");
+
+        let picked = state.pick_recommendations(
+            vec![
+                vec![track_with_id("1"), track_with_id("2"), track_with_id("3")],
+                vec![track_with_id("2"), track_with_id("4")],
+            ],
+            3,
+        );
+
+        assert_eq!(
+            picked
+                .iter()
+                .map(|track| track.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["2", "4", "3"]
+        );
+    }
+
     #[test]
     fn maps_remove_position_to_queue_index() {
         assert_eq!(queue_remove_index(4, 1), Ok(1));
@@ -714,6 +1345,63 @@ mod tests {
             Ok("**Repeat:** all\n\nThe queue is currently empty.".to_string())
         );
     }
+
+    #[test]
+    fn finds_owned_playlist_by_url_or_title() {
+        let playlists = vec![
+            crate::track::PlaylistSummary {
+                id: "05f75c8a-a5be-4f4c-8d53".to_string(),
+                title: "Road Trip".to_string(),
+                track_count: 12,
+            },
+            crate::track::PlaylistSummary {
+                id: "9b1e".to_string(),
+                title: "Focus".to_string(),
+                track_count: 3,
+            },
+        ];
+
+        let by_url = find_owned_playlist(
+            &playlists,
+            "https://tidal.com/browse/playlist/05f75c8a-a5be-4f4c-8d53",
+        );
+        assert_eq!(
+            by_url.map(|playlist| playlist.title.as_str()),
+            Some("Road Trip")
+        );
+        let by_title = find_owned_playlist(&playlists, " focus ");
+        assert_eq!(by_title.map(|playlist| playlist.id.as_str()), Some("9b1e"));
+        assert!(
+            find_owned_playlist(&playlists, "https://tidal.com/browse/playlist/other").is_none()
+        );
+        assert!(find_owned_playlist(&playlists, "Workout").is_none());
+    }
+
+    #[test]
+    fn formats_tidal_login_status() {
+        let status = |expires_in| crate::session::SessionStatus {
+            user_id: 42,
+            country_code: "US".to_string(),
+            expires_in,
+        };
+
+        assert_eq!(format_tidal_status(None), "Not logged in to Tidal.");
+        assert_eq!(
+            format_tidal_status(Some(&status(Some(Duration::from_secs(3_540))))),
+            "Logged in to Tidal as user `42` (country `US`). Access token expires in 59:00."
+        );
+        assert!(format_tidal_status(Some(&status(None))).ends_with("Access token expiry unknown."));
+
+        assert_eq!(
+            format_tidal_account("backup", None, false, false),
+            "`backup`: Not logged in to Tidal."
+        );
+        assert_eq!(
+            format_tidal_account("main", Some(&status(None)), false, true),
+            "`main`: Logged in to Tidal as user `42` (country `US`). Access token expiry unknown. \
+             Token refresh is failing; requests use another account. (used by this server)"
+        );
+    }
 }
 
 /// Check whether the bot is responding.
@@ -730,33 +1418,248 @@ pub async fn help(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
-async fn guild_id(ctx: Context<'_>) -> Result<Option<serenity::model::id::GuildId>, Error> {
-    match ctx.guild_id() {
-        Some(guild_id) => Ok(Some(guild_id)),
-        None => {
-            ctx.say("This command can only be used in a guild.").await?;
-            Ok(None)
-        }
-    }
-}
-
-async fn voice_manager(ctx: Context<'_>) -> std::sync::Arc<songbird::Songbird> {
-    songbird::get(ctx.serenity_context())
-        .await
-        .expect("Songbird voice manager not found")
+/// Manage the bot's Tidal login. Only the bot owner can use these.
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("tidal_login", "tidal_status", "tidal_logout"),
+    subcommand_required,
+    owners_only
+)]
+pub async fn tidal(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
 }
 
-async fn guild_voice_manager(
+/// DM yourself a link that logs one of the bot's Tidal accounts in.
+#[poise::command(slash_command, prefix_command, rename = "login", owners_only)]
+pub async fn tidal_login(
     ctx: Context<'_>,
-) -> Result<
-    Option<(
-        serenity::model::id::GuildId,
-        std::sync::Arc<songbird::Songbird>,
-    )>,
-    Error,
-> {
-    let Some(guild_id) = guild_id(ctx).await? else {
-        return Ok(None);
+    #[description = "Account to log in; defaults to the one this server uses"] account: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let account = match tidal_account(&ctx, account.as_deref()) {
+        Ok(account) => account,
+        Err(error) => {
+            ctx.say(error.to_string()).await?;
+            return Ok(());
+        }
+    };
+    let account_name = account.name.clone();
+    let session = account.session.clone();
+    let device_login = match session.start_device_login().await {
+        Ok(device_login) => device_login,
+        Err(error) => {
+            ctx.say(format!("Could not start a Tidal login: {error}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let link_message = format!(
+        "Open {} to log the bot's `{}` account in to Tidal. The link expires in {}.",
+        device_login.verification_url(),
+        account_name,
+        format_duration_seconds(device_login.expires_in().as_secs())
+    );
+    if let Err(error) = ctx
+        .author()
+        .direct_message(
+            ctx,
+            serenity::all::CreateMessage::new().content(link_message),
+        )
+        .await
+    {
+        tracing::warn!(%error, "Failed to DM the Tidal login link");
+        ctx.say("I could not DM you the login link. Allow direct messages from this server and try again.")
+            .await?;
+        return Ok(());
+    }
+
+    ctx.send(
+        CreateReply::default()
+            .content("Sent you a DM with the Tidal login link.")
+            .ephemeral(true),
+    )
+    .await?;
+
+    let http = ctx.serenity_context().http.clone();
+    let owner = ctx.author().clone();
+    tokio::spawn(async move {
+        let message = match session.finish_device_login(device_login).await {
+            Ok(()) => format!(
+                "Logged in to Tidal.\n{}",
+                format_tidal_account(&account_name, session.status().as_ref(), true, false)
+            ),
+            Err(error) => format!("Tidal login for `{account_name}` failed: {error}"),
+        };
+
+        if let Err(error) = owner
+            .direct_message(&http, serenity::all::CreateMessage::new().content(message))
+            .await
+        {
+            tracing::warn!(%error, "Failed to DM the Tidal login result");
+        }
+    });
+
+    Ok(())
+}
+
+/// Show each Tidal account the bot uses and when its token expires.
+#[poise::command(slash_command, prefix_command, rename = "status", owners_only)]
+pub async fn tidal_status(ctx: Context<'_>) -> Result<(), Error> {
+    let tidal = &ctx.data().tidal;
+    let current = &tidal.for_guild(ctx.guild_id()).name;
+    let content = tidal
+        .accounts()
+        .iter()
+        .map(|account| {
+            format_tidal_account(
+                &account.name,
+                account.session.status().as_ref(),
+                account.session.is_healthy(),
+                ctx.guild_id().is_some() && &account.name == current,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Log one of the bot's Tidal accounts out and delete its saved token.
+#[poise::command(slash_command, prefix_command, rename = "logout", owners_only)]
+pub async fn tidal_logout(
+    ctx: Context<'_>,
+    #[description = "Account to log out; defaults to the one this server uses"] account: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let account = match tidal_account(&ctx, account.as_deref()) {
+        Ok(account) => account,
+        Err(error) => {
+            ctx.say(error.to_string()).await?;
+            return Ok(());
+        }
+    };
+
+    account.session.logout().await?;
+    ctx.say(format!(
+        "Logged `{}` out of Tidal. Use `/tidal login` to log in again.",
+        account.name
+    ))
+    .await?;
+    Ok(())
+}
+
+/// The account named by an owner, or the one this guild is assigned when no name is given.
+fn tidal_account<'a>(
+    ctx: &'a Context<'_>,
+    name: Option<&str>,
+) -> Result<&'a crate::session_pool::TidalAccount, Error> {
+    let tidal = &ctx.data().tidal;
+    let Some(name) = name else {
+        return Ok(tidal.assigned(ctx.guild_id()));
+    };
+
+    tidal.account(name).ok_or_else(|| {
+        let names = tidal
+            .accounts()
+            .iter()
+            .map(|account| format!("`{}`", account.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("There is no Tidal account named `{name}`. Accounts: {names}.").into()
+    })
+}
+
+fn format_tidal_account(
+    name: &str,
+    status: Option<&crate::session::SessionStatus>,
+    healthy: bool,
+    current: bool,
+) -> String {
+    let mut line = format!("`{name}`: {}", format_tidal_status(status));
+    if status.is_some() && !healthy {
+        line.push_str(" Token refresh is failing; requests use another account.");
+    }
+    if current {
+        line.push_str(" (used by this server)");
+    }
+    line
+}
+
+fn format_tidal_status(status: Option<&crate::session::SessionStatus>) -> String {
+    let Some(status) = status else {
+        return "Not logged in to Tidal.".to_string();
+    };
+
+    let expiry = match status.expires_in {
+        Some(expires_in) if expires_in.is_zero() => "expired, refreshing".to_string(),
+        Some(expires_in) => format!(
+            "expires in {}",
+            format_duration_seconds(expires_in.as_secs())
+        ),
+        None => "expiry unknown".to_string(),
+    };
+
+    format!(
+        "Logged in to Tidal as user `{}` (country `{}`). Access token {}.",
+        status.user_id, status.country_code, expiry
+    )
+}
+
+fn tidal_session(ctx: &Context<'_>) -> std::sync::Arc<crate::session::Session> {
+    ctx.data().tidal.session(ctx.guild_id())
+}
+
+/// The guild's own Tidal account, for commands that read or change its favorites and
+/// playlists. These never fail over, since another account's library belongs to someone else.
+async fn linked_account_session(
+    ctx: &Context<'_>,
+) -> Result<Option<std::sync::Arc<crate::session::Session>>, Error> {
+    let account = ctx.data().tidal.assigned(ctx.guild_id());
+    if account.session.status().is_none() {
+        ctx.say(format!(
+            "The linked Tidal account **{}** is logged out. The bot owner can log it in with `/tidal login {}`.",
+            account.name, account.name
+        ))
+        .await?;
+        return Ok(None);
+    }
+
+    Ok(Some(account.session.clone()))
+}
+
+async fn guild_id(ctx: Context<'_>) -> Result<Option<serenity::model::id::GuildId>, Error> {
+    match ctx.guild_id() {
+        Some(guild_id) => Ok(Some(guild_id)),
+        None => {
+            ctx.say("This command can only be used in a guild.").await?;
+            Ok(None)
+        }
+    }
+}
+
+async fn voice_manager(ctx: Context<'_>) -> std::sync::Arc<songbird::Songbird> {
+    songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird voice manager not found")
+}
+
+async fn guild_voice_manager(
+    ctx: Context<'_>,
+) -> Result<
+    Option<(
+        serenity::model::id::GuildId,
+        std::sync::Arc<songbird::Songbird>,
+    )>,
+    Error,
+> {
+    let Some(guild_id) = guild_id(ctx).await? else {
+        return Ok(None);
     };
 
     Ok(Some((guild_id, voice_manager(ctx).await)))
@@ -820,56 +1723,183 @@ async fn try_join_voice_channel(ctx: Context<'_>) -> Result<Option<JoinVoiceChan
 
     // Join (or move to) the voice channel
     if let Ok(handler_lock) = manager.join(guild_id, channel_id).await {
-        let repeat_handler_lock = handler_lock.clone();
-        let serenity_context = ctx.serenity_context().clone();
-        let playback_status = ctx.data().playback_status.clone();
-        let mut handler = handler_lock.lock().await;
-        handler.add_global_event(
-            songbird::events::TrackEvent::Error.into(),
-            TrackErrorNotifier,
-        );
+        register_voice_events(ctx.serenity_context(), ctx.data(), guild_id, &handler_lock).await;
+        Ok(Some(JoinVoiceChannelState::Joined))
+    } else {
+        ctx.say("Failed to join the voice channel.").await?;
+        Ok(None)
+    }
+}
+
+async fn register_voice_events(
+    serenity_context: &serenity::client::Context,
+    data: &Data,
+    guild_id: serenity::model::id::GuildId,
+    handler_lock: &std::sync::Arc<tokio::sync::Mutex<songbird::Call>>,
+) {
+    let mut handler = handler_lock.lock().await;
+    handler.add_global_event(
+        songbird::events::TrackEvent::Error.into(),
+        TrackErrorNotifier,
+    );
+    handler.add_global_event(
+        songbird::events::TrackEvent::End.into(),
+        RepeatModeNotifier {
+            handler_lock: handler_lock.clone(),
+            tidal: data.tidal.clone(),
+            repeat_modes: data.repeat_modes.clone(),
+            serenity_context: serenity_context.clone(),
+            playback_status: data.playback_status.clone(),
+            autoplay: data.autoplay.clone(),
+            default_audio_quality: data.default_audio_quality,
+            audio_qualities: data.audio_qualities.clone(),
+            guild_id,
+            spool_read_ahead_bytes: data.spool_read_ahead_bytes,
+        },
+    );
+    handler.add_global_event(
+        songbird::events::TrackEvent::Play.into(),
+        RepeatModeNotifier {
+            handler_lock: handler_lock.clone(),
+            tidal: data.tidal.clone(),
+            repeat_modes: data.repeat_modes.clone(),
+            serenity_context: serenity_context.clone(),
+            playback_status: data.playback_status.clone(),
+            autoplay: data.autoplay.clone(),
+            default_audio_quality: data.default_audio_quality,
+            audio_qualities: data.audio_qualities.clone(),
+            guild_id,
+            spool_read_ahead_bytes: data.spool_read_ahead_bytes,
+        },
+    );
+    for event in [
+        songbird::events::TrackEvent::Play,
+        songbird::events::TrackEvent::Pause,
+        songbird::events::TrackEvent::End,
+        songbird::events::TrackEvent::Error,
+    ] {
         handler.add_global_event(
-            songbird::events::TrackEvent::End.into(),
-            RepeatModeNotifier {
-                handler_lock: repeat_handler_lock.clone(),
-                repeat_modes: ctx.data().repeat_modes.clone(),
+            event.into(),
+            PlaybackStatusNotifier {
                 serenity_context: serenity_context.clone(),
-                playback_status: playback_status.clone(),
+                playback_status: data.playback_status.clone(),
                 guild_id,
-                spool_read_ahead_bytes: ctx.data().spool_read_ahead_bytes,
             },
         );
-        handler.add_global_event(
-            songbird::events::TrackEvent::Play.into(),
-            RepeatModeNotifier {
-                handler_lock: repeat_handler_lock,
-                repeat_modes: ctx.data().repeat_modes.clone(),
-                serenity_context: serenity_context.clone(),
-                playback_status: playback_status.clone(),
+    }
+}
+
+/// Rejoin voice channels and requeue the tracks saved before the last shutdown.
+pub async fn restore_guild_queues(serenity_context: &serenity::client::Context, data: &Data) {
+    let snapshot = match data.queue_persistence.take_snapshot_for_restore() {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return,
+        Err(error) => {
+            tracing::warn!(%error, "Failed to read saved guild queues");
+            data.queue_persistence.finish_restore();
+            return;
+        }
+    };
+
+    let Some(manager) = songbird::get(serenity_context).await else {
+        tracing::warn!("Songbird voice manager not found; skipping queue restore");
+        data.queue_persistence.finish_restore();
+        return;
+    };
+
+    for guild_snapshot in snapshot.guilds {
+        let guild_id = guild_snapshot.guild_id;
+        if let Err(error) =
+            restore_guild_queue(serenity_context, data, &manager, guild_snapshot).await
+        {
+            tracing::warn!(guild_id = %guild_id, %error, "Failed to restore guild queue");
+        }
+    }
+
+    data.queue_persistence.finish_restore();
+}
+
+async fn restore_guild_queue(
+    serenity_context: &serenity::client::Context,
+    data: &Data,
+    manager: &songbird::Songbird,
+    guild_snapshot: crate::queue_state::GuildQueueSnapshot,
+) -> Result<(), Error> {
+    let guild_id = guild_snapshot.guild_id;
+    let position = guild_snapshot.position();
+    if let Some(audio_quality) = guild_snapshot.audio_quality {
+        data.audio_qualities
+            .lock()
+            .await
+            .insert(guild_id, audio_quality);
+    }
+    // Stream URLs are resolved at play time, so the saved metadata can be queued as is
+    let tracks = guild_snapshot.tracks;
+    if tracks.is_empty() {
+        return Err("Saved queue is empty".into());
+    }
+
+    let handler_lock = manager
+        .join(guild_id, guild_snapshot.channel_id)
+        .await
+        .map_err(|error| format!("Failed to rejoin voice channel: {error}"))?;
+    register_voice_events(serenity_context, data, guild_id, &handler_lock).await;
+
+    if guild_snapshot.repeat_mode != RepeatMode::Off {
+        data.repeat_modes
+            .lock()
+            .await
+            .insert(guild_id, guild_snapshot.repeat_mode);
+    }
+
+    let mut handler = handler_lock.lock().await;
+    let audio_bitrate = current_voice_channel_bitrate(serenity_context, guild_id, &handler);
+    let mut queued = 0;
+    for (index, track) in tracks.iter().enumerate() {
+        let start_position = if index == 0 { position } else { Duration::ZERO };
+        let handle = match enqueue_track_with_spool(
+            &mut handler,
+            &data.tidal,
+            guild_id,
+            track,
+            start_position,
+            data.spool_read_ahead_bytes,
+            audio_bitrate,
+        )
+        .await
+        {
+            Ok(handle) => handle,
+            Err(error) => {
+                tracing::warn!(guild_id = %guild_id, track_id = %track.id, %error, "Failed to restore queued track");
+                continue;
+            }
+        };
+
+        if queued == 0 {
+            begin_playback_status(
+                serenity_context,
+                data.playback_status.clone(),
                 guild_id,
-                spool_read_ahead_bytes: ctx.data().spool_read_ahead_bytes,
-            },
-        );
-        for event in [
-            songbird::events::TrackEvent::Play,
-            songbird::events::TrackEvent::Pause,
-            songbird::events::TrackEvent::End,
-            songbird::events::TrackEvent::Error,
-        ] {
-            handler.add_global_event(
-                event.into(),
-                PlaybackStatusNotifier {
-                    serenity_context: serenity_context.clone(),
-                    playback_status: playback_status.clone(),
-                    guild_id,
-                },
-            );
+                handle,
+            )
+            .await;
         }
-        Ok(Some(JoinVoiceChannelState::Joined))
-    } else {
-        ctx.say("Failed to join the voice channel.").await?;
-        Ok(None)
+        queued += 1;
+    }
+
+    if queued == 0 {
+        return Err("None of the saved tracks could be queued".into());
     }
+
+    tracing::info!(
+        guild_id = %guild_id,
+        track_count = queued,
+        skipped = tracks.len() - queued,
+        position_seconds = position.as_secs(),
+        "Restored guild queue"
+    );
+
+    Ok(())
 }
 
 async fn pause_playback_message(handler: &songbird::Call) -> Result<&'static str, Error> {
@@ -1159,28 +2189,156 @@ fn search_component_prefix(ctx_id: u64) -> String {
     format!("search:{ctx_id}:")
 }
 
+struct SearchChoice {
+    line: String,
+    label: String,
+    description: String,
+}
+
+impl SearchChoice {
+    fn track(track: &crate::track::TrackSummary) -> Self {
+        Self {
+            line: get_formatted_track_summary(track),
+            label: format!("{} - {}", track.artist, track.title),
+            description: format!(
+                "Duration {}",
+                format_duration_seconds(track.duration as u64)
+            ),
+        }
+    }
+
+    fn playlist(playlist: &crate::track::PlaylistSummary) -> Self {
+        let track_count = format!(
+            "{} track{}",
+            playlist.track_count,
+            if playlist.track_count == 1 { "" } else { "s" }
+        );
+
+        Self {
+            line: format!("{} ({})", playlist.title, track_count),
+            label: playlist.title.clone(),
+            description: track_count,
+        }
+    }
+
+    fn album(album: &crate::track::AlbumSummary) -> Self {
+        Self {
+            line: get_formatted_album_summary(album),
+            label: album.title.clone(),
+            description: format_album_details(album),
+        }
+    }
+
+    fn artist(artist: &crate::track::ArtistSummary) -> Self {
+        Self {
+            line: artist.name.clone(),
+            label: artist.name.clone(),
+            description: "Queue top tracks".to_string(),
+        }
+    }
+
+    /// Labels the choice with its kind, for result lists that mix tracks, albums, and so on.
+    fn with_kind(mut self, kind: &str) -> Self {
+        self.line = format!("{kind}: {}", self.line);
+        self.description = format!("{kind} · {}", self.description);
+        self
+    }
+}
+
+enum SearchResult {
+    Track(crate::track::TrackSummary),
+    Album(crate::track::AlbumSummary),
+    Playlist(crate::track::PlaylistSummary),
+    Artist(crate::track::ArtistSummary),
+}
+
+impl SearchResult {
+    fn from_results(results: crate::session::SearchResults) -> Vec<Self> {
+        results
+            .tracks
+            .into_iter()
+            .map(Self::Track)
+            .chain(results.albums.into_iter().map(Self::Album))
+            .chain(results.playlists.into_iter().map(Self::Playlist))
+            .chain(results.artists.into_iter().map(Self::Artist))
+            .collect()
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Track(_) => "Track",
+            Self::Album(_) => "Album",
+            Self::Playlist(_) => "Playlist",
+            Self::Artist(_) => "Artist",
+        }
+    }
+
+    fn choice(&self, show_kind: bool) -> SearchChoice {
+        let choice = match self {
+            Self::Track(track) => SearchChoice::track(track),
+            Self::Album(album) => SearchChoice::album(album),
+            Self::Playlist(playlist) => SearchChoice::playlist(playlist),
+            Self::Artist(artist) => SearchChoice::artist(artist),
+        };
+
+        if show_kind {
+            choice.with_kind(self.kind())
+        } else {
+            choice
+        }
+    }
+
+    fn display_name(&self) -> String {
+        match self {
+            Self::Track(track) => get_formatted_track_summary(track),
+            Self::Album(album) => get_formatted_album_summary(album),
+            Self::Playlist(playlist) => playlist.title.clone(),
+            Self::Artist(artist) => artist.name.clone(),
+        }
+    }
+}
+
+fn format_album_details(album: &crate::track::AlbumSummary) -> String {
+    let track_count = format!(
+        "{} track{}",
+        album.track_count,
+        if album.track_count == 1 { "" } else { "s" }
+    );
+
+    match &album.release_year {
+        Some(year) => format!("{year} · {track_count}"),
+        None => track_count,
+    }
+}
+
+fn get_formatted_album_summary(album: &crate::track::AlbumSummary) -> String {
+    format!(
+        "{} - {} ({})",
+        album.artist,
+        album.title,
+        format_album_details(album)
+    )
+}
+
 fn format_search_message(
     query: &str,
-    tracks: &[crate::track::TrackSummary],
+    choices: &[SearchChoice],
     page: usize,
+    item_name: &str,
 ) -> Result<String, String> {
-    let (start, end, total_pages) = queue_page_bounds(tracks.len(), page)?;
+    let (start, end, total_pages) = queue_page_bounds(choices.len(), page)?;
     let mut message = format!(
         "**Search Results for:** {}\n**Page {page}/{total_pages}**\n\n",
         truncate_chars(query, 120)
     );
 
-    for (offset, track) in tracks.iter().skip(start).take(end - start).enumerate() {
-        let line = format!(
-            "{}. {}",
-            start + offset + 1,
-            get_formatted_track_summary(track)
-        );
+    for (offset, choice) in choices.iter().skip(start).take(end - start).enumerate() {
+        let line = format!("{}. {}", start + offset + 1, choice.line);
         message.push_str(&truncate_chars(&line, 180));
         message.push('\n');
     }
 
-    message.push_str("\nChoose a track from the menu below.");
+    message.push_str(&format!("\nChoose {item_name} from the menu below."));
     if total_pages > 1 {
         message.push_str(" Use Previous and Next to change pages.");
     }
@@ -1190,30 +2348,22 @@ fn format_search_message(
 
 fn search_result_components(
     ctx_id: u64,
-    tracks: &[crate::track::TrackSummary],
+    choices: &[SearchChoice],
     page: usize,
+    item_name: &str,
 ) -> Result<Vec<serenity::all::CreateActionRow>, String> {
-    let (start, end, total_pages) = queue_page_bounds(tracks.len(), page)?;
-    let options = tracks
+    let (start, end, total_pages) = queue_page_bounds(choices.len(), page)?;
+    let options = choices
         .iter()
         .enumerate()
         .skip(start)
         .take(end - start)
-        .map(|(index, track)| {
+        .map(|(index, choice)| {
             serenity::all::CreateSelectMenuOption::new(
-                truncate_chars(
-                    &format!("{}. {} - {}", index + 1, track.artist, track.title),
-                    100,
-                ),
+                truncate_chars(&format!("{}. {}", index + 1, choice.label), 100),
                 index.to_string(),
             )
-            .description(truncate_chars(
-                &format!(
-                    "Duration {}",
-                    format_duration_seconds(track.duration as u64)
-                ),
-                100,
-            ))
+            .description(truncate_chars(&choice.description, 100))
         })
         .collect::<Vec<_>>();
 
@@ -1221,7 +2371,7 @@ fn search_result_components(
         search_select_id(ctx_id),
         serenity::all::CreateSelectMenuKind::String { options },
     )
-    .placeholder("Choose a track")
+    .placeholder(format!("Choose {item_name}"))
     .min_values(1)
     .max_values(1);
 
@@ -1252,40 +2402,405 @@ fn search_result_components(
     Ok(components)
 }
 
-async fn find_search_summaries(
+/// Shows paged results with a select menu and waits for the author to pick one. The returned
+/// interaction has not been responded to yet.
+async fn choose_search_result(
     ctx: &Context<'_>,
     query: &str,
-) -> Result<Vec<crate::track::TrackSummary>, Error> {
-    let mut session = ctx.data().session.lock().await;
-    session
-        .search_track_summaries(query, SEARCH_RESULT_LIMIT)
-        .await
-}
+    choices: &[SearchChoice],
+    item_name: &str,
+) -> Result<Option<(usize, serenity::all::ComponentInteraction)>, Error> {
+    let ctx_id = ctx.id();
+    let mut page = 1;
+    let select_id = search_select_id(ctx_id);
+    let prev_id = search_prev_id(ctx_id);
+    let next_id = search_next_id(ctx_id);
+    let cancel_id = search_cancel_id(ctx_id);
+    let component_prefix = search_component_prefix(ctx_id);
 
-async fn find_track_for_search_summary(
-    ctx: &Context<'_>,
-    track: &crate::track::TrackSummary,
-) -> Result<crate::track::Track, Error> {
-    let mut session = ctx.data().session.lock().await;
-    session.find_track_by_id(&track.id).await
-}
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .content(
+                    format_search_message(query, choices, page, item_name).map_err(Error::from)?,
+                )
+                .components(
+                    search_result_components(ctx_id, choices, page, item_name)
+                        .map_err(Error::from)?,
+                ),
+        )
+        .await?;
 
-async fn enqueue_selected_track(
-    ctx: &Context<'_>,
-    manager: &std::sync::Arc<songbird::Songbird>,
-    guild_id: serenity::model::id::GuildId,
-    track: &crate::track::Track,
+    let message_id = {
+        let message = reply.message().await?;
+        message.id
+    };
+
+    while let Some(press) = serenity::all::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .message_id(message_id)
+        .timeout(SEARCH_SELECTION_TIMEOUT)
+        .filter({
+            let component_prefix = component_prefix.clone();
+            move |press| press.data.custom_id.starts_with(&component_prefix)
+        })
+        .await
+    {
+        let custom_id = press.data.custom_id.as_str();
+
+        if custom_id == cancel_id {
+            update_search_selection(ctx, &press, "Search cancelled.".to_string()).await?;
+            return Ok(None);
+        }
+
+        if custom_id == prev_id || custom_id == next_id {
+            let (_, _, total_pages) =
+                queue_page_bounds(choices.len(), page).map_err(Error::from)?;
+            if custom_id == prev_id && page > 1 {
+                page -= 1;
+            } else if custom_id == next_id && page < total_pages {
+                page += 1;
+            }
+
+            press
+                .create_response(
+                    ctx.serenity_context(),
+                    serenity::all::CreateInteractionResponse::UpdateMessage(
+                        serenity::all::CreateInteractionResponseMessage::new()
+                            .content(
+                                format_search_message(query, choices, page, item_name)
+                                    .map_err(Error::from)?,
+                            )
+                            .components(
+                                search_result_components(ctx_id, choices, page, item_name)
+                                    .map_err(Error::from)?,
+                            ),
+                    ),
+                )
+                .await?;
+            continue;
+        }
+
+        if custom_id != select_id {
+            continue;
+        }
+
+        let selected_index = match &press.data.kind {
+            serenity::all::ComponentInteractionDataKind::StringSelect { values } => values
+                .first()
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|index| *index < choices.len()),
+            _ => None,
+        };
+
+        let Some(selected_index) = selected_index else {
+            update_search_selection(
+                ctx,
+                &press,
+                "That search selection is no longer available.".to_string(),
+            )
+            .await?;
+            return Ok(None);
+        };
+
+        return Ok(Some((selected_index, press)));
+    }
+
+    reply
+        .edit(
+            *ctx,
+            CreateReply::default()
+                .content("Search timed out.")
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(None)
+}
+
+async fn update_search_selection(
+    ctx: &Context<'_>,
+    press: &serenity::all::ComponentInteraction,
+    content: String,
+) -> Result<(), Error> {
+    press
+        .create_response(
+            ctx.serenity_context(),
+            serenity::all::CreateInteractionResponse::UpdateMessage(
+                serenity::all::CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn finish_search_selection(
+    ctx: &Context<'_>,
+    press: &serenity::all::ComponentInteraction,
+    content: String,
+) -> Result<(), Error> {
+    press
+        .edit_response(
+            ctx.serenity_context(),
+            serenity::all::EditInteractionResponse::new()
+                .content(content)
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn find_search_results(
+    ctx: &Context<'_>,
+    query: &str,
+    search_type: SearchType,
+) -> Result<Vec<SearchResult>, Error> {
+    let results = tidal_session(ctx)
+        .search_catalog(query, search_type.result_limit(), search_type.api_types())
+        .await?;
+
+    Ok(SearchResult::from_results(results))
+}
+
+async fn find_track_for_search_summary(
+    ctx: &Context<'_>,
+    track: &crate::track::TrackSummary,
+) -> Result<crate::track::Track, Error> {
+    let audio_quality = current_audio_quality(ctx).await;
+    tidal_session(ctx)
+        .find_track_by_id(&track.id, audio_quality)
+        .await
+}
+
+async fn enqueue_selected_track(
+    ctx: &Context<'_>,
+    manager: &std::sync::Arc<songbird::Songbird>,
+    guild_id: serenity::model::id::GuildId,
+    track: &crate::track::Track,
+) -> Result<(), Error> {
+    enqueue_selected_tracks(ctx, manager, guild_id, std::slice::from_ref(track)).await
+}
+
+async fn enqueue_selected_tracks(
+    ctx: &Context<'_>,
+    manager: &std::sync::Arc<songbird::Songbird>,
+    guild_id: serenity::model::id::GuildId,
+    tracks: &[crate::track::Track],
 ) -> Result<(), Error> {
     let Some(handler_lock) = manager.get(guild_id) else {
         return Err("Not connected to a voice channel.".into());
     };
 
     let mut handler = handler_lock.lock().await;
-    let _ = enqueue_track(ctx, &mut handler, track).await?;
+    for track in tracks {
+        let _ = enqueue_track(ctx, &mut handler, track).await?;
+    }
 
     Ok(())
 }
 
+async fn find_artist(
+    ctx: &Context<'_>,
+    name_or_url: &str,
+) -> Result<Option<crate::track::ArtistSummary>, Error> {
+    let session = &tidal_session(ctx);
+
+    if let Ok(url) = url::Url::parse(name_or_url) {
+        return match crate::url_handler::parse_tidal_resource(&url) {
+            Some((crate::url_handler::TidalResource::Artist, id)) => {
+                Ok(Some(session.find_artist_by_id(&id).await?))
+            }
+            _ => Ok(None),
+        };
+    }
+
+    session.find_artist_by_name(name_or_url).await
+}
+
+/// Queues a chosen search result and returns the reply for the user.
+async fn enqueue_chosen_track(
+    ctx: &Context<'_>,
+    manager: &std::sync::Arc<songbird::Songbird>,
+    guild_id: serenity::model::id::GuildId,
+    track: &crate::track::TrackSummary,
+) -> String {
+    match find_track_for_search_summary(ctx, track).await {
+        Ok(track) => match enqueue_selected_track(ctx, manager, guild_id, &track).await {
+            Ok(()) => format!(
+                "{} added **{}** to the queue.",
+                ctx.author().name,
+                get_formatted_track(&track)
+            ),
+            Err(error) => {
+                tracing::warn!(%error, "Failed to enqueue selected search track");
+                "Failed to add that track to the queue.".to_string()
+            }
+        },
+        Err(error) => {
+            tracing::warn!(%error, track_id = %track.id, "Failed to fetch selected search track");
+            "Failed to load that track from Tidal.".to_string()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CollectionKind {
+    Album,
+    Playlist,
+}
+
+impl CollectionKind {
+    fn api_name(self) -> &'static str {
+        match self {
+            Self::Album => "albums",
+            Self::Playlist => "playlists",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Album => "album",
+            Self::Playlist => "playlist",
+        }
+    }
+}
+
+/// Queues every track of a chosen album or playlist and returns the reply for the user.
+async fn enqueue_chosen_collection(
+    ctx: &Context<'_>,
+    manager: &std::sync::Arc<songbird::Songbird>,
+    guild_id: serenity::model::id::GuildId,
+    kind: CollectionKind,
+    collection_id: &str,
+    title: &str,
+) -> String {
+    let audio_quality = current_audio_quality(ctx).await;
+    let tracks = tidal_session(ctx)
+        .find_collection_tracks(
+            kind.api_name(),
+            collection_id,
+            ctx.data().collection_track_fetch_concurrency,
+            audio_quality,
+        )
+        .await;
+
+    match tracks {
+        Ok(found) if found.tracks.is_empty() => with_skip_report(
+            format!("No playable tracks were found on that {}.", kind.name()),
+            &found.skipped,
+        ),
+        Ok(found) => match enqueue_selected_tracks(ctx, manager, guild_id, &found.tracks).await {
+            Ok(()) => with_skip_report(
+                format!(
+                    "{} added **{} tracks** from **{}** to the queue.",
+                    ctx.author().name,
+                    found.tracks.len(),
+                    title
+                ),
+                &found.skipped,
+            ),
+            Err(error) => {
+                tracing::warn!(%error, collection = kind.name(), "Failed to enqueue selected collection");
+                format!("Failed to add that {} to the queue.", kind.name())
+            }
+        },
+        Err(error) => {
+            tracing::warn!(
+                %error,
+                collection = kind.name(),
+                collection_id,
+                "Failed to fetch selected collection"
+            );
+            format!("Failed to load that {} from Tidal.", kind.name())
+        }
+    }
+}
+
+/// Queues an artist's top tracks and returns the reply for the user.
+async fn enqueue_artist_top_tracks(
+    ctx: &Context<'_>,
+    manager: &std::sync::Arc<songbird::Songbird>,
+    guild_id: serenity::model::id::GuildId,
+    artist: &crate::track::ArtistSummary,
+) -> Result<String, Error> {
+    let audio_quality = current_audio_quality(ctx).await;
+    let tracks = tidal_session(ctx)
+        .find_artist_top_tracks(
+            &artist.id,
+            crate::session::ARTIST_TOP_TRACK_LIMIT,
+            audio_quality,
+        )
+        .await?;
+    if tracks.is_empty() {
+        return Ok(format!("No tracks were found for **{}**.", artist.name));
+    }
+
+    enqueue_selected_tracks(ctx, manager, guild_id, &tracks).await?;
+    tracing::info!(
+        guild_id = %guild_id,
+        user_id = %ctx.author().id,
+        user = %ctx.author().name,
+        artist_id = %artist.id,
+        track_count = tracks.len(),
+        "Queued artist top tracks"
+    );
+
+    Ok(format!(
+        "{} added **{} tracks** by **{}** to the queue.",
+        ctx.author().name,
+        tracks.len(),
+        artist.name
+    ))
+}
+
+/// Queues a chosen search result of any kind and returns the reply for the user.
+async fn enqueue_search_result(
+    ctx: &Context<'_>,
+    manager: &std::sync::Arc<songbird::Songbird>,
+    guild_id: serenity::model::id::GuildId,
+    result: &SearchResult,
+) -> String {
+    match result {
+        SearchResult::Track(track) => enqueue_chosen_track(ctx, manager, guild_id, track).await,
+        SearchResult::Album(album) => {
+            enqueue_chosen_collection(
+                ctx,
+                manager,
+                guild_id,
+                CollectionKind::Album,
+                &album.id,
+                &album.title,
+            )
+            .await
+        }
+        SearchResult::Playlist(playlist) => {
+            enqueue_chosen_collection(
+                ctx,
+                manager,
+                guild_id,
+                CollectionKind::Playlist,
+                &playlist.id,
+                &playlist.title,
+            )
+            .await
+        }
+        SearchResult::Artist(artist) => {
+            match enqueue_artist_top_tracks(ctx, manager, guild_id, artist).await {
+                Ok(response) => response,
+                Err(error) => {
+                    tracing::warn!(%error, artist_id = %artist.id, "Failed to enqueue artist top tracks");
+                    "Failed to load that artist's top tracks from Tidal.".to_string()
+                }
+            }
+        }
+    }
+}
+
 /// Join the voice channel you are currently in.
 #[poise::command(
     slash_command,
@@ -1356,13 +2871,12 @@ async fn enqueue_track_at(
     start_position: Duration,
 ) -> Result<songbird::tracks::TrackHandle, Error> {
     let was_queue_empty = handler.queue().is_empty();
-    let audio_bitrate = current_voice_channel_bitrate(
-        ctx.serenity_context(),
-        ctx.guild_id().ok_or("Expected a guild ID")?,
-        handler,
-    );
+    let guild_id = ctx.guild_id().ok_or("Expected a guild ID")?;
+    let audio_bitrate = current_voice_channel_bitrate(ctx.serenity_context(), guild_id, handler);
     let handle = enqueue_track_with_spool(
         handler,
+        &ctx.data().tidal,
+        guild_id,
         track,
         start_position,
         ctx.data().spool_read_ahead_bytes,
@@ -1370,7 +2884,7 @@ async fn enqueue_track_at(
     )
     .await?;
 
-    if was_queue_empty && let Some(guild_id) = ctx.guild_id() {
+    if was_queue_empty {
         begin_playback_status(
             ctx.serenity_context(),
             ctx.data().playback_status.clone(),
@@ -1385,6 +2899,8 @@ async fn enqueue_track_at(
 
 async fn enqueue_track_with_spool(
     handler: &mut songbird::Call,
+    tidal: &std::sync::Arc<crate::session_pool::SessionPool>,
+    guild_id: serenity::model::id::GuildId,
     track: &crate::track::Track,
     start_position: Duration,
     spool_read_ahead_bytes: u64,
@@ -1392,15 +2908,17 @@ async fn enqueue_track_with_spool(
 ) -> Result<songbird::tracks::TrackHandle, Error> {
     handler.set_bitrate(songbird::driver::Bitrate::Bits(audio_bitrate as i32));
 
+    // The stream URL is resolved when the track starts, so it cannot expire while queued
+    let source = Box::new(crate::track::TidalStreamSource::new(
+        tidal.clone(),
+        guild_id,
+        track.clone(),
+    ));
     let ffmpeg_stream = if start_position.is_zero() {
-        crate::ffmpeg_spool::FfmpegStream::new(
-            &track.stream_url,
-            spool_read_ahead_bytes,
-            audio_bitrate,
-        )
+        crate::ffmpeg_spool::FfmpegStream::new(source, spool_read_ahead_bytes, audio_bitrate)
     } else {
         crate::ffmpeg_spool::FfmpegStream::new_at(
-            &track.stream_url,
+            source,
             spool_read_ahead_bytes,
             start_position,
             audio_bitrate,
@@ -1437,35 +2955,129 @@ fn current_voice_channel_bitrate(
         .unwrap_or(DEFAULT_AUDIO_BITRATE)
 }
 
+/// Appends the skipped tracks report, if any, on its own line.
+fn with_skip_report(message: String, skipped: &crate::track::SkippedTracks) -> String {
+    match skipped.report() {
+        Some(report) => format!("{message}\n{report}"),
+        None => message,
+    }
+}
+
+/// Keeps the suggestions to offer for one query and reports the rest as not found.
+fn take_match_suggestions(
+    mut suggestions: Vec<crate::track::MatchSuggestion>,
+    skipped: &mut crate::track::SkippedTracks,
+) -> Vec<crate::track::MatchSuggestion> {
+    if suggestions.len() > MATCH_SUGGESTION_LIMIT {
+        skipped.not_found.extend(
+            suggestions
+                .drain(MATCH_SUGGESTION_LIMIT..)
+                .map(|suggestion| suggestion.source),
+        );
+    }
+    suggestions
+}
+
+fn no_match_message(suggestions: &[crate::track::MatchSuggestion]) -> &'static str {
+    if suggestions.is_empty() {
+        "No track was found on Tidal."
+    } else {
+        "No exact match was found on Tidal."
+    }
+}
+
+/// Asks "did you mean" for the linked tracks without a confident match, in one search
+/// selection menu so the command waits on a single choice, and queues the pick.
+async fn offer_match_suggestions(
+    ctx: &Context<'_>,
+    manager: &std::sync::Arc<songbird::Songbird>,
+    guild_id: serenity::model::id::GuildId,
+    suggestions: &[crate::track::MatchSuggestion],
+) -> Result<(), Error> {
+    let candidates = suggestions
+        .iter()
+        .flat_map(|suggestion| {
+            suggestion
+                .candidates
+                .iter()
+                .map(move |track| (suggestion.source.as_str(), track))
+        })
+        .collect::<Vec<_>>();
+    let choices = candidates
+        .iter()
+        .map(|(source, track)| suggestion_choice(source, track, suggestions.len()))
+        .collect::<Vec<_>>();
+    let title = match suggestions {
+        [suggestion] => format!("{} (did you mean?)", suggestion.source),
+        _ => "Linked tracks (did you mean?)".to_string(),
+    };
+
+    let Some((selected_index, press)) =
+        choose_search_result(ctx, &title, &choices, "a track").await?
+    else {
+        return Ok(());
+    };
+
+    let (_, track) = candidates[selected_index];
+    update_search_selection(
+        ctx,
+        &press,
+        format!("Adding **{}** to the queue...", get_formatted_track(track)),
+    )
+    .await?;
+
+    let response = match enqueue_selected_track(ctx, manager, guild_id, track).await {
+        Ok(()) => format!(
+            "{} added **{}** to the queue.",
+            ctx.author().name,
+            get_formatted_track(track)
+        ),
+        Err(error) => {
+            tracing::warn!(%error, "Failed to enqueue suggested track");
+            "Failed to add that track to the queue.".to_string()
+        }
+    };
+    finish_search_selection(ctx, &press, response).await
+}
+
+/// A suggested track, naming the linked track it stands in for when several are offered.
+fn suggestion_choice(
+    source: &str,
+    track: &crate::track::Track,
+    suggestion_count: usize,
+) -> SearchChoice {
+    let mut choice = SearchChoice::track(&track.summary());
+    if suggestion_count > 1 {
+        choice.line = format!("{} (for {})", choice.line, source);
+        choice.description = format!("For {} · {}", source, choice.description);
+    }
+    choice
+}
+
 async fn find_tracks_for_query(
     ctx: &Context<'_>,
     query: &str,
-) -> Result<Vec<crate::track::Track>, Error> {
-    let mut session = ctx.data().session.lock().await;
+) -> Result<crate::track::FoundTracks, Error> {
+    let audio_quality = current_audio_quality(ctx).await;
+    let session = &tidal_session(ctx);
 
-    let mut tracks = crate::url_handler::handle_url(
-        &mut session,
+    let mut found = crate::url_handler::handle_url(
+        session,
         query,
         ctx.data().collection_track_fetch_concurrency,
+        audio_quality,
     )
     .await?;
 
-    if tracks.is_empty() {
-        tracks = {
-            let tracks = session
-                .find_tracks(query, 1)
-                .await
-                .map_err(|e| Error::from(e.to_string()))?;
-
-            if tracks.is_empty() {
-                return Ok(Vec::new());
-            }
-
-            tracks
-        };
+    // Only search when nothing was found, not when everything found was skipped or uncertain
+    if found.tracks.is_empty() && found.skipped.total() == 0 && found.suggestions.is_empty() {
+        found = session
+            .find_first_track(query, audio_quality)
+            .await
+            .map_err(|e| Error::from(e.to_string()))?;
     }
 
-    Ok(tracks)
+    Ok(found)
 }
 
 /// Queue a track from a search query or supported URL.
@@ -1473,6 +3085,7 @@ async fn find_tracks_for_query(
 pub async fn play(
     ctx: Context<'_>,
     #[description = "Provide the query or url of a song"]
+    #[autocomplete = "autocomplete_track"]
     #[rest]
     query_or_url: Option<String>,
 ) -> Result<(), Error> {
@@ -1507,9 +3120,19 @@ pub async fn play(
 
     let _ = ctx.defer().await;
 
-    let tracks = find_tracks_for_query(&ctx, &query).await?;
+    let crate::track::FoundTracks {
+        tracks,
+        mut skipped,
+        suggestions,
+    } = find_tracks_for_query(&ctx, &query).await?;
+    let suggestions = take_match_suggestions(suggestions, &mut skipped);
     if tracks.is_empty() {
-        ctx.say("No track was found on Tidal.").await?;
+        ctx.say(with_skip_report(
+            no_match_message(&suggestions).to_string(),
+            &skipped,
+        ))
+        .await?;
+        offer_match_suggestions(&ctx, &manager, guild_id, &suggestions).await?;
         return Ok(());
     }
 
@@ -1530,10 +3153,13 @@ pub async fn play(
                 duration_seconds = tracks[0].duration,
                 "Queued track"
             );
-            ctx.say(format!(
-                "{} added **{}** to the queue.",
-                ctx.author().name,
-                get_formatted_track(&tracks[0])
+            ctx.say(with_skip_report(
+                format!(
+                    "{} added **{}** to the queue.",
+                    ctx.author().name,
+                    get_formatted_track(&tracks[0])
+                ),
+                &skipped,
             ))
             .await?;
         } else {
@@ -1545,10 +3171,13 @@ pub async fn play(
                 query = %query,
                 "Queued multiple tracks"
             );
-            ctx.say(format!(
-                "{} added **{} tracks** to the queue.",
-                ctx.author().name,
-                tracks.len()
+            ctx.say(with_skip_report(
+                format!(
+                    "{} added **{} tracks** to the queue.",
+                    ctx.author().name,
+                    tracks.len()
+                ),
+                &skipped,
             ))
             .await?;
         }
@@ -1557,17 +3186,30 @@ pub async fn play(
         return Ok(());
     }
 
-    Ok(())
+    offer_match_suggestions(&ctx, &manager, guild_id, &suggestions).await
 }
 
 /// Search Tidal and choose which result to queue.
 #[poise::command(slash_command, prefix_command, guild_only)]
 pub async fn search(
     ctx: Context<'_>,
+    // Comes first so prefix commands can write `search albums <query>`. A type keyword with
+    // nothing after it is searched for instead. Slash commands list the query first; see
+    // `required_options_first`.
+    #[description = "What to search for: tracks (default), albums, playlists, artists, or all"]
+    #[rename = "type"]
+    search_type: Option<SearchType>,
     #[description = "Search query"]
+    #[autocomplete = "autocomplete_track"]
     #[rest]
     query: String,
 ) -> Result<(), Error> {
+    let search_type = search_type.unwrap_or(SearchType::Tracks);
+    if query.trim().is_empty() {
+        ctx.say("Provide something to search for.").await?;
+        return Ok(());
+    }
+
     if try_join_voice_channel(ctx).await?.is_none() {
         return Ok(());
     }
@@ -1581,19 +3223,18 @@ pub async fn search(
         user_id = %ctx.author().id,
         user = %ctx.author().name,
         query = %query,
+        search_type = search_type.api_types(),
         "User interactive search"
     );
 
     let _ = ctx.defer().await;
 
-    let tracks = find_search_summaries(&ctx, &query).await?;
-    if tracks.is_empty() {
-        ctx.say("No track was found on Tidal.").await?;
-        return Ok(());
-    }
-
-    if tracks.len() == 1 {
-        let track = find_track_for_search_summary(&ctx, &tracks[0]).await?;
+    // Picking an autocomplete suggestion fills in the track's URL
+    if let Some(track_id) = parse_tidal_track_url(&query) {
+        let audio_quality = current_audio_quality(&ctx).await;
+        let track = tidal_session(&ctx)
+            .find_track_by_id(&track_id, audio_quality)
+            .await?;
         enqueue_selected_track(&ctx, &manager, guild_id, &track).await?;
         ctx.say(format!(
             "{} added **{}** to the queue.",
@@ -1604,160 +3245,555 @@ pub async fn search(
         return Ok(());
     }
 
-    let ctx_id = ctx.id();
-    let mut page = 1;
-    let select_id = search_select_id(ctx_id);
-    let prev_id = search_prev_id(ctx_id);
-    let next_id = search_next_id(ctx_id);
-    let cancel_id = search_cancel_id(ctx_id);
-    let component_prefix = search_component_prefix(ctx_id);
+    let results = find_search_results(&ctx, &query, search_type).await?;
+    if results.is_empty() {
+        ctx.say(search_type.not_found_message()).await?;
+        return Ok(());
+    }
 
-    let reply = ctx
-        .send(
-            CreateReply::default()
-                .content(format_search_message(&query, &tracks, page).map_err(Error::from)?)
-                .components(search_result_components(ctx_id, &tracks, page).map_err(Error::from)?),
-        )
-        .await?;
+    if results.len() == 1 {
+        let response = enqueue_search_result(&ctx, &manager, guild_id, &results[0]).await;
+        ctx.say(response).await?;
+        return Ok(());
+    }
 
-    let message_id = {
-        let message = reply.message().await?;
-        message.id
+    let show_kind = search_type == SearchType::All;
+    let choices = results
+        .iter()
+        .map(|result| result.choice(show_kind))
+        .collect::<Vec<_>>();
+    let Some((selected_index, press)) =
+        choose_search_result(&ctx, &query, &choices, search_type.item_name()).await?
+    else {
+        return Ok(());
     };
 
-    while let Some(press) = serenity::all::ComponentInteractionCollector::new(ctx)
-        .author_id(ctx.author().id)
-        .channel_id(ctx.channel_id())
-        .message_id(message_id)
-        .timeout(SEARCH_SELECTION_TIMEOUT)
-        .filter({
-            let component_prefix = component_prefix.clone();
-            move |press| press.data.custom_id.starts_with(&component_prefix)
-        })
-        .await
-    {
-        let custom_id = press.data.custom_id.as_str();
+    let selected = &results[selected_index];
+    update_search_selection(
+        &ctx,
+        &press,
+        format!("Adding **{}** to the queue...", selected.display_name()),
+    )
+    .await?;
 
-        if custom_id == cancel_id {
-            press
-                .create_response(
-                    ctx.serenity_context(),
-                    serenity::all::CreateInteractionResponse::UpdateMessage(
-                        serenity::all::CreateInteractionResponseMessage::new()
-                            .content("Search cancelled.")
-                            .components(vec![]),
-                    ),
-                )
-                .await?;
-            return Ok(());
+    let response = enqueue_search_result(&ctx, &manager, guild_id, selected).await;
+
+    finish_search_selection(&ctx, &press, response).await
+}
+
+/// Queue an artist's top tracks or browse their albums.
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn artist(
+    ctx: Context<'_>,
+    // Comes first so prefix commands can write `artist albums <name>`, as in `search`
+    #[description = "What to show: top_tracks (default) or albums"] view: Option<ArtistView>,
+    #[description = "Artist name or Tidal artist URL"]
+    #[rest]
+    name_or_url: String,
+) -> Result<(), Error> {
+    if name_or_url.trim().is_empty() {
+        ctx.say("Provide an artist name or Tidal artist URL.")
+            .await?;
+        return Ok(());
+    }
+
+    if try_join_voice_channel(ctx).await?.is_none() {
+        return Ok(());
+    }
+
+    let Some((guild_id, manager)) = guild_voice_manager(ctx).await? else {
+        return Ok(());
+    };
+
+    tracing::info!(
+        guild_id = %guild_id,
+        user_id = %ctx.author().id,
+        user = %ctx.author().name,
+        query = %name_or_url,
+        "User artist lookup"
+    );
+
+    let _ = ctx.defer().await;
+
+    let Some(artist) = find_artist(&ctx, &name_or_url).await? else {
+        ctx.say("No artist was found on Tidal.").await?;
+        return Ok(());
+    };
+
+    match view.unwrap_or(ArtistView::TopTracks) {
+        ArtistView::TopTracks => {
+            let response = enqueue_artist_top_tracks(&ctx, &manager, guild_id, &artist).await?;
+            ctx.say(response).await?;
         }
+        ArtistView::Albums => {
+            let albums = tidal_session(&ctx).find_artist_albums(&artist.id).await?;
+            if albums.is_empty() {
+                ctx.say(format!("No albums were found for **{}**.", artist.name))
+                    .await?;
+                return Ok(());
+            }
 
-        if custom_id == prev_id || custom_id == next_id {
-            let (_, _, total_pages) = queue_page_bounds(tracks.len(), page).map_err(Error::from)?;
-            if custom_id == prev_id && page > 1 {
-                page -= 1;
-            } else if custom_id == next_id && page < total_pages {
-                page += 1;
+            let choices = albums.iter().map(SearchChoice::album).collect::<Vec<_>>();
+            let Some((selected_index, press)) =
+                choose_search_result(&ctx, &artist.name, &choices, "an album").await?
+            else {
+                return Ok(());
+            };
+
+            let album = &albums[selected_index];
+            update_search_selection(
+                &ctx,
+                &press,
+                format!(
+                    "Adding **{}** to the queue...",
+                    get_formatted_album_summary(album)
+                ),
+            )
+            .await?;
+
+            let response = enqueue_chosen_collection(
+                &ctx,
+                &manager,
+                guild_id,
+                CollectionKind::Album,
+                &album.id,
+                &album.title,
+            )
+            .await;
+
+            finish_search_selection(&ctx, &press, response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Browse the linked Tidal account's favorite tracks or albums and choose one to queue.
+#[poise::command(slash_command, prefix_command, aliases("favs"), guild_only)]
+pub async fn favorites(
+    ctx: Context<'_>,
+    #[description = "What to browse: tracks (default) or albums"] kind: Option<FavoritesKind>,
+) -> Result<(), Error> {
+    if try_join_voice_channel(ctx).await?.is_none() {
+        return Ok(());
+    }
+
+    let Some((guild_id, manager)) = guild_voice_manager(ctx).await? else {
+        return Ok(());
+    };
+
+    let _ = ctx.defer().await;
+
+    let Some(session) = linked_account_session(&ctx).await? else {
+        return Ok(());
+    };
+
+    match kind.unwrap_or(FavoritesKind::Tracks) {
+        FavoritesKind::Tracks => {
+            let tracks = session.find_favorite_tracks().await?;
+            if tracks.is_empty() {
+                ctx.say("The linked Tidal account has no favorite tracks.")
+                    .await?;
+                return Ok(());
             }
 
-            press
-                .create_response(
-                    ctx.serenity_context(),
-                    serenity::all::CreateInteractionResponse::UpdateMessage(
-                        serenity::all::CreateInteractionResponseMessage::new()
-                            .content(
-                                format_search_message(&query, &tracks, page)
-                                    .map_err(Error::from)?,
-                            )
-                            .components(
-                                search_result_components(ctx_id, &tracks, page)
-                                    .map_err(Error::from)?,
-                            ),
-                    ),
-                )
-                .await?;
-            continue;
+            let choices = tracks.iter().map(SearchChoice::track).collect::<Vec<_>>();
+            let Some((selected_index, press)) =
+                choose_search_result(&ctx, "Favorite tracks", &choices, "a track").await?
+            else {
+                return Ok(());
+            };
+
+            let selected_track = &tracks[selected_index];
+            update_search_selection(
+                &ctx,
+                &press,
+                format!(
+                    "Adding **{}** to the queue...",
+                    get_formatted_track_summary(selected_track)
+                ),
+            )
+            .await?;
+
+            let response = enqueue_chosen_track(&ctx, &manager, guild_id, selected_track).await;
+
+            finish_search_selection(&ctx, &press, response).await?;
         }
+        FavoritesKind::Albums => {
+            let albums = session.find_favorite_albums().await?;
+            if albums.is_empty() {
+                ctx.say("The linked Tidal account has no favorite albums.")
+                    .await?;
+                return Ok(());
+            }
 
-        if custom_id != select_id {
-            continue;
+            let choices = albums.iter().map(SearchChoice::album).collect::<Vec<_>>();
+            let Some((selected_index, press)) =
+                choose_search_result(&ctx, "Favorite albums", &choices, "an album").await?
+            else {
+                return Ok(());
+            };
+
+            let album = &albums[selected_index];
+            update_search_selection(
+                &ctx,
+                &press,
+                format!(
+                    "Adding **{}** to the queue...",
+                    get_formatted_album_summary(album)
+                ),
+            )
+            .await?;
+
+            let response = enqueue_chosen_collection(
+                &ctx,
+                &manager,
+                guild_id,
+                CollectionKind::Album,
+                &album.id,
+                &album.title,
+            )
+            .await;
+
+            finish_search_selection(&ctx, &press, response).await?;
         }
+    }
 
-        let selected_index = match &press.data.kind {
-            serenity::all::ComponentInteractionDataKind::StringSelect { values } => values
-                .first()
-                .and_then(|value| value.parse::<usize>().ok())
-                .filter(|index| *index < tracks.len()),
+    Ok(())
+}
+
+/// Browse the linked Tidal account's playlists and choose one to queue.
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn myplaylists(ctx: Context<'_>) -> Result<(), Error> {
+    if try_join_voice_channel(ctx).await?.is_none() {
+        return Ok(());
+    }
+
+    let Some((guild_id, manager)) = guild_voice_manager(ctx).await? else {
+        return Ok(());
+    };
+
+    let _ = ctx.defer().await;
+
+    let Some(session) = linked_account_session(&ctx).await? else {
+        return Ok(());
+    };
+    let playlists = session.find_user_playlists().await?;
+    if playlists.is_empty() {
+        ctx.say("The linked Tidal account has no playlists.")
+            .await?;
+        return Ok(());
+    }
+
+    let choices = playlists
+        .iter()
+        .map(SearchChoice::playlist)
+        .collect::<Vec<_>>();
+    let Some((selected_index, press)) =
+        choose_search_result(&ctx, "Tidal playlists", &choices, "a playlist").await?
+    else {
+        return Ok(());
+    };
+
+    let playlist = &playlists[selected_index];
+    update_search_selection(
+        &ctx,
+        &press,
+        format!("Adding **{}** to the queue...", playlist.title),
+    )
+    .await?;
+
+    let response = enqueue_chosen_collection(
+        &ctx,
+        &manager,
+        guild_id,
+        CollectionKind::Playlist,
+        &playlist.id,
+        &playlist.title,
+    )
+    .await;
+
+    finish_search_selection(&ctx, &press, response).await
+}
+
+/// Finds one of the linked account's playlists by its Tidal URL or exact title.
+fn find_owned_playlist<'a>(
+    playlists: &'a [crate::track::PlaylistSummary],
+    name_or_url: &str,
+) -> Option<&'a crate::track::PlaylistSummary> {
+    if let Ok(url) = url::Url::parse(name_or_url) {
+        return match crate::url_handler::parse_tidal_resource(&url) {
+            Some((crate::url_handler::TidalResource::Playlist, id)) => {
+                playlists.iter().find(|playlist| playlist.id == id)
+            }
             _ => None,
         };
+    }
 
-        let Some(selected_index) = selected_index else {
-            press
-                .create_response(
-                    ctx.serenity_context(),
-                    serenity::all::CreateInteractionResponse::UpdateMessage(
-                        serenity::all::CreateInteractionResponseMessage::new()
-                            .content("That search selection is no longer available.")
-                            .components(vec![]),
-                    ),
-                )
-                .await?;
-            return Ok(());
-        };
+    playlists
+        .iter()
+        .find(|playlist| playlist.title.eq_ignore_ascii_case(name_or_url.trim()))
+}
 
-        let selected_track = tracks[selected_index].clone();
-        press
-            .create_response(
-                ctx.serenity_context(),
-                serenity::all::CreateInteractionResponse::UpdateMessage(
-                    serenity::all::CreateInteractionResponseMessage::new()
-                        .content(format!(
-                            "Adding **{}** to the queue...",
-                            get_formatted_track_summary(&selected_track)
-                        ))
-                        .components(vec![]),
-                ),
+/// Save the queue to, or add the current track to, a playlist on the linked Tidal account.
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("playlist_save", "playlist_add"),
+    subcommand_required,
+    guild_only
+)]
+pub async fn playlist(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Save the current queue as a new playlist on the linked Tidal account.
+#[poise::command(slash_command, prefix_command, rename = "save", guild_only)]
+pub async fn playlist_save(
+    ctx: Context<'_>,
+    #[description = "Name for the new playlist"]
+    #[rest]
+    name: String,
+) -> Result<(), Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        ctx.say("Please provide a name for the playlist.").await?;
+        return Ok(());
+    }
+
+    let Some(handler_lock) = voice_call(ctx, "Not connected to a voice channel.").await? else {
+        return Ok(());
+    };
+    let track_ids = handler_lock
+        .lock()
+        .await
+        .queue()
+        .current_queue()
+        .iter()
+        .map(|track_handle| track_handle.data::<crate::track::Track>().id.clone())
+        .collect::<Vec<_>>();
+
+    if track_ids.is_empty() {
+        ctx.say("The queue is empty.").await?;
+        return Ok(());
+    }
+
+    let _ = ctx.defer().await;
+
+    let Some(session) = linked_account_session(&ctx).await? else {
+        return Ok(());
+    };
+    let playlist = session
+        .create_playlist(name, "Saved from Discord by TidalCordRS.")
+        .await?;
+    session
+        .add_tracks_to_playlist(&playlist.id, &track_ids)
+        .await?;
+
+    tracing::info!(
+        playlist_id = %playlist.id,
+        track_count = track_ids.len(),
+        "Saved queue as Tidal playlist"
+    );
+    ctx.say(format!(
+        "Saved {} track(s) to **{}**: {}",
+        track_ids.len(),
+        playlist.title,
+        tidal_playlist_url(&playlist.id)
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Add the current track to one of the linked Tidal account's playlists.
+#[poise::command(slash_command, prefix_command, rename = "add", guild_only)]
+pub async fn playlist_add(
+    ctx: Context<'_>,
+    #[description = "Playlist name or Tidal URL; choose from a list when omitted"]
+    #[rest]
+    playlist: Option<String>,
+) -> Result<(), Error> {
+    let Some(handler_lock) = voice_call(ctx, "Not connected to a voice channel.").await? else {
+        return Ok(());
+    };
+    let Some(track) = handler_lock
+        .lock()
+        .await
+        .queue()
+        .current()
+        .map(|track_handle| track_handle.data::<crate::track::Track>().as_ref().clone())
+    else {
+        ctx.say("No track is currently playing.").await?;
+        return Ok(());
+    };
+
+    let _ = ctx.defer().await;
+
+    let Some(session) = linked_account_session(&ctx).await? else {
+        return Ok(());
+    };
+    let playlists = session.find_user_playlists().await?;
+    if playlists.is_empty() {
+        ctx.say("The linked Tidal account has no playlists.")
+            .await?;
+        return Ok(());
+    }
+
+    let (target, press) = match playlist.as_deref() {
+        Some(name_or_url) => {
+            let Some(target) = find_owned_playlist(&playlists, name_or_url) else {
+                ctx.say(format!(
+                    "No playlist named **{}** was found on the linked Tidal account.",
+                    name_or_url
+                ))
+                .await?;
+                return Ok(());
+            };
+            (target, None)
+        }
+        None => {
+            let choices = playlists
+                .iter()
+                .map(SearchChoice::playlist)
+                .collect::<Vec<_>>();
+            let Some((selected_index, press)) =
+                choose_search_result(&ctx, "Tidal playlists", &choices, "a playlist").await?
+            else {
+                return Ok(());
+            };
+            let target = &playlists[selected_index];
+            update_search_selection(
+                &ctx,
+                &press,
+                format!("Adding the current track to **{}**...", target.title),
             )
             .await?;
+            (target, Some(press))
+        }
+    };
 
-        let response = match find_track_for_search_summary(&ctx, &selected_track).await {
-            Ok(track) => match enqueue_selected_track(&ctx, &manager, guild_id, &track).await {
-                Ok(()) => format!(
-                    "{} added **{}** to the queue.",
-                    ctx.author().name,
-                    get_formatted_track(&track)
+    let result = session
+        .add_tracks_to_playlist(&target.id, std::slice::from_ref(&track.id))
+        .await;
+    let response = match result {
+        Ok(()) => format!(
+            "Added **{}** to **{}**: {}",
+            get_formatted_track(&track),
+            target.title,
+            tidal_playlist_url(&target.id)
+        ),
+        Err(error) => {
+            tracing::warn!(playlist_id = %target.id, error = %error, "Failed to add track to playlist");
+            format!("Could not add the track to **{}**.", target.title)
+        }
+    };
+
+    match press {
+        Some(press) => finish_search_selection(&ctx, &press, response).await,
+        None => {
+            ctx.say(response).await?;
+            Ok(())
+        }
+    }
+}
+
+/// Start a radio of similar tracks from the current track or a search.
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn radio(
+    ctx: Context<'_>,
+    #[description = "Song to start the radio from; defaults to the current track"]
+    #[rest]
+    query: Option<String>,
+) -> Result<(), Error> {
+    if try_join_voice_channel(ctx).await?.is_none() {
+        return Ok(());
+    }
+
+    let Some((guild_id, manager)) = guild_voice_manager(ctx).await? else {
+        return Ok(());
+    };
+
+    let query = query.filter(|query| !query.trim().is_empty());
+    tracing::info!(
+        guild_id = %guild_id,
+        user_id = %ctx.author().id,
+        user = %ctx.author().name,
+        query = query.as_deref().unwrap_or_default(),
+        "User radio request"
+    );
+
+    let _ = ctx.defer().await;
+
+    // A searched seed is queued ahead of its radio; the current track is already playing
+    let (seed, queue_seed) = match &query {
+        Some(query) => match find_tracks_for_query(&ctx, query)
+            .await?
+            .tracks
+            .into_iter()
+            .next()
+        {
+            Some(track) => (track, true),
+            None => {
+                ctx.say("No track was found on Tidal.").await?;
+                return Ok(());
+            }
+        },
+        None => {
+            let current = match manager.get(guild_id) {
+                Some(handler_lock) => handler_lock.lock().await.queue().current(),
+                None => None,
+            };
+            match current {
+                Some(track_handle) => (
+                    track_handle.data::<crate::track::Track>().as_ref().clone(),
+                    false,
                 ),
-                Err(error) => {
-                    tracing::warn!(%error, "Failed to enqueue selected search track");
-                    "Failed to add that track to the queue.".to_string()
+                None => {
+                    ctx.say(
+                        "No track is currently playing. Provide a song to start the radio from.",
+                    )
+                    .await?;
+                    return Ok(());
                 }
-            },
-            Err(error) => {
-                tracing::warn!(%error, track_id = %selected_track.id, "Failed to fetch selected search track");
-                "Failed to load that track from Tidal.".to_string()
             }
-        };
-
-        press
-            .edit_response(
-                ctx.serenity_context(),
-                serenity::all::EditInteractionResponse::new()
-                    .content(response)
-                    .components(vec![]),
-            )
-            .await?;
+        }
+    };
 
+    let audio_quality = current_audio_quality(&ctx).await;
+    let mut tracks = tidal_session(&ctx)
+        .find_track_radio(&seed.id, crate::session::TRACK_RADIO_LIMIT, audio_quality)
+        .await?;
+    if tracks.is_empty() {
+        ctx.say(format!(
+            "No radio tracks were found for **{}**.",
+            get_formatted_track(&seed)
+        ))
+        .await?;
         return Ok(());
     }
 
-    reply
-        .edit(
-            ctx,
-            CreateReply::default()
-                .content("Search timed out.")
-                .components(vec![]),
-        )
-        .await?;
+    let radio_track_count = tracks.len();
+    if queue_seed {
+        tracks.insert(0, seed.clone());
+    }
+    enqueue_selected_tracks(&ctx, &manager, guild_id, &tracks).await?;
+
+    tracing::info!(
+        guild_id = %guild_id,
+        user_id = %ctx.author().id,
+        user = %ctx.author().name,
+        seed_track_id = %seed.id,
+        track_count = tracks.len(),
+        "Queued track radio"
+    );
+    ctx.say(format!(
+        "{} started a radio from **{}** and added **{} tracks** to the queue.",
+        ctx.author().name,
+        get_formatted_track(&seed),
+        radio_track_count
+    ))
+    .await?;
 
     Ok(())
 }
@@ -1789,9 +3825,19 @@ pub async fn playnext(
 
     let _ = ctx.defer().await;
 
-    let tracks = find_tracks_for_query(&ctx, &query).await?;
+    let crate::track::FoundTracks {
+        tracks,
+        mut skipped,
+        suggestions,
+    } = find_tracks_for_query(&ctx, &query).await?;
+    let suggestions = take_match_suggestions(suggestions, &mut skipped);
     if tracks.is_empty() {
-        ctx.say("No track was found on Tidal.").await?;
+        ctx.say(with_skip_report(
+            no_match_message(&suggestions).to_string(),
+            &skipped,
+        ))
+        .await?;
+        offer_match_suggestions(&ctx, &manager, guild_id, &suggestions).await?;
         return Ok(());
     }
 
@@ -1820,10 +3866,13 @@ pub async fn playnext(
                     duration_seconds = tracks[0].duration,
                     "Queued track to play next"
                 );
-                ctx.say(format!(
-                    "{} added **{}** to play next.",
-                    ctx.author().name,
-                    get_formatted_track(&tracks[0])
+                ctx.say(with_skip_report(
+                    format!(
+                        "{} added **{}** to play next.",
+                        ctx.author().name,
+                        get_formatted_track(&tracks[0])
+                    ),
+                    &skipped,
                 ))
                 .await?;
             } else {
@@ -1835,25 +3884,34 @@ pub async fn playnext(
                     query = %query,
                     "Queued multiple tracks to play next"
                 );
-                ctx.say(format!(
-                    "{} added **{} tracks** to play next.",
-                    ctx.author().name,
-                    tracks.len()
+                ctx.say(with_skip_report(
+                    format!(
+                        "{} added **{} tracks** to play next.",
+                        ctx.author().name,
+                        tracks.len()
+                    ),
+                    &skipped,
                 ))
                 .await?;
             }
         } else if tracks.len() == 1 {
-            ctx.say(format!(
-                "{} started playing **{}**.",
-                ctx.author().name,
-                get_formatted_track(&tracks[0])
+            ctx.say(with_skip_report(
+                format!(
+                    "{} started playing **{}**.",
+                    ctx.author().name,
+                    get_formatted_track(&tracks[0])
+                ),
+                &skipped,
             ))
             .await?;
         } else {
-            ctx.say(format!(
-                "{} added **{} tracks** to the queue.",
-                ctx.author().name,
-                tracks.len()
+            ctx.say(with_skip_report(
+                format!(
+                    "{} added **{} tracks** to the queue.",
+                    ctx.author().name,
+                    tracks.len()
+                ),
+                &skipped,
             ))
             .await?;
         }
@@ -1862,7 +3920,7 @@ pub async fn playnext(
         return Ok(());
     }
 
-    Ok(())
+    offer_match_suggestions(&ctx, &manager, guild_id, &suggestions).await
 }
 
 /// Set repeat mode to the current track, all tracks, or off.
@@ -1881,6 +3939,76 @@ pub async fn repeat(
     set_repeat_mode(ctx, mode).await
 }
 
+/// Turn autoplay on or off. Autoplay queues similar tracks when the queue runs out.
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn autoplay(
+    ctx: Context<'_>,
+    #[description = "Turn autoplay on or off; toggles when omitted"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    let Some(guild_id) = guild_id(ctx).await? else {
+        return Ok(());
+    };
+
+    let enabled = {
+        let mut autoplay = ctx.data().autoplay.lock().await;
+        let state = autoplay.entry(guild_id).or_default();
+        state.enabled = enabled.unwrap_or(!state.enabled);
+        state.enabled
+    };
+
+    tracing::info!(
+        guild_id = %guild_id,
+        user_id = %ctx.author().id,
+        enabled,
+        "Autoplay changed"
+    );
+    ctx.say(if enabled {
+        "Autoplay turned on. Similar tracks will be queued when the queue runs out."
+    } else {
+        "Autoplay turned off."
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Show or set the Tidal audio quality used for newly queued tracks.
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn quality(
+    ctx: Context<'_>,
+    #[description = "Audio quality: low, high, lossless, or hi_res"] quality: Option<AudioQuality>,
+) -> Result<(), Error> {
+    let Some(guild_id) = guild_id(ctx).await? else {
+        return Ok(());
+    };
+
+    let Some(quality) = quality else {
+        let quality = guild_audio_quality(ctx.data(), guild_id).await;
+        ctx.say(format!("Audio quality is set to **{}**.", quality.label()))
+            .await?;
+        return Ok(());
+    };
+
+    ctx.data()
+        .audio_qualities
+        .lock()
+        .await
+        .insert(guild_id, quality);
+    tracing::info!(
+        guild_id = %guild_id,
+        user_id = %ctx.author().id,
+        quality = quality.api_name(),
+        "Audio quality changed"
+    );
+    ctx.say(format!(
+        "Audio quality set to **{}**. Newly queued tracks will use it, stepping down when a track is not available at that quality.",
+        quality.label()
+    ))
+    .await?;
+
+    Ok(())
+}
+
 /// Pause the current playback.
 #[poise::command(slash_command, prefix_command, aliases("wait"), guild_only)]
 pub async fn pause(ctx: Context<'_>) -> Result<(), Error> {
@@ -2224,8 +4352,9 @@ pub async fn current(ctx: Context<'_>) -> Result<(), Error> {
     if let Some(track_handle) = handler.queue().current() {
         let track = track_handle.data::<crate::track::Track>().clone();
         ctx.say(format!(
-            "Current track: **{}**",
-            get_formatted_track(&track)
+            "Current track: **{}** ({} quality)",
+            get_formatted_track(&track),
+            track.playback_quality().label()
         ))
         .await?;
     } else {
@@ -2235,6 +4364,209 @@ pub async fn current(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+fn lyrics_prev_id(ctx_id: u64) -> String {
+    format!("lyrics:{ctx_id}:prev")
+}
+
+fn lyrics_next_id(ctx_id: u64) -> String {
+    format!("lyrics:{ctx_id}:next")
+}
+
+fn lyrics_components(
+    ctx_id: u64,
+    page: usize,
+    total_pages: usize,
+) -> Vec<serenity::all::CreateActionRow> {
+    if total_pages <= 1 {
+        return vec![];
+    }
+
+    vec![serenity::all::CreateActionRow::Buttons(vec![
+        serenity::all::CreateButton::new(lyrics_prev_id(ctx_id))
+            .label("Previous")
+            .style(serenity::all::ButtonStyle::Secondary)
+            .disabled(page <= 1),
+        serenity::all::CreateButton::new(lyrics_next_id(ctx_id))
+            .label("Next")
+            .style(serenity::all::ButtonStyle::Secondary)
+            .disabled(page >= total_pages),
+    ])]
+}
+
+fn format_lyrics_page(header: &str, pages: &[String], page: usize) -> String {
+    if pages.len() <= 1 {
+        return format!("{}\n\n{}", header, pages[page - 1]);
+    }
+
+    format!(
+        "{}\n\n{}\n\nPage {}/{}",
+        header,
+        pages[page - 1],
+        page,
+        pages.len()
+    )
+}
+
+/// Posts synced lyrics and edits the message whenever the playing line changes,
+/// until the track finishes or is removed from the queue.
+async fn follow_synced_lyrics(
+    ctx: Context<'_>,
+    track_handle: &songbird::tracks::TrackHandle,
+    header: &str,
+    lyrics: &crate::lyrics::Lyrics,
+    track_duration: Duration,
+) -> Result<(), Error> {
+    let position = match tokio::time::timeout(TRACK_INFO_TIMEOUT, track_handle.get_info()).await {
+        Ok(Ok(track_info)) => track_info.position,
+        _ => Duration::ZERO,
+    };
+    let mut current = lyrics.current_line(position);
+
+    let reply = ctx
+        .say(format!("{}\n\n{}", header, lyrics.format_synced(current)))
+        .await?;
+
+    let mut deadline = tokio::time::Instant::now() + track_duration + LYRICS_FOLLOW_GRACE;
+    if matches!(ctx, poise::Context::Application(_)) {
+        deadline = deadline.min(tokio::time::Instant::now() + INTERACTION_TOKEN_LIFETIME);
+    }
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(LYRICS_UPDATE_INTERVAL).await;
+
+        let track_info =
+            match tokio::time::timeout(TRACK_INFO_TIMEOUT, track_handle.get_info()).await {
+                Ok(Ok(track_info)) => track_info,
+                Ok(Err(_)) => break,
+                Err(_) => continue,
+            };
+        if track_info.playing.is_done() {
+            break;
+        }
+
+        let line = lyrics.current_line(track_info.position);
+        if line == current {
+            continue;
+        }
+        current = line;
+
+        // The message may have been deleted; stop following instead of failing the command
+        if let Err(error) = reply
+            .edit(
+                ctx,
+                CreateReply::default().content(format!(
+                    "{}\n\n{}",
+                    header,
+                    lyrics.format_synced(current)
+                )),
+            )
+            .await
+        {
+            tracing::debug!(%error, "Stopped following synced lyrics");
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+async fn show_lyrics_pages(ctx: Context<'_>, header: &str, pages: &[String]) -> Result<(), Error> {
+    let ctx_id = ctx.id();
+    let mut page = 1;
+
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .content(format_lyrics_page(header, pages, page))
+                .components(lyrics_components(ctx_id, page, pages.len())),
+        )
+        .await?;
+    if pages.len() <= 1 {
+        return Ok(());
+    }
+
+    let message_id = reply.message().await?.id;
+    let prev_id = lyrics_prev_id(ctx_id);
+    let next_id = lyrics_next_id(ctx_id);
+
+    while let Some(press) = serenity::all::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .message_id(message_id)
+        .timeout(LYRICS_PAGINATION_TIMEOUT)
+        .await
+    {
+        let custom_id = press.data.custom_id.as_str();
+        if custom_id == prev_id && page > 1 {
+            page -= 1;
+        } else if custom_id == next_id && page < pages.len() {
+            page += 1;
+        } else {
+            continue;
+        }
+
+        press
+            .create_response(
+                ctx.serenity_context(),
+                serenity::all::CreateInteractionResponse::UpdateMessage(
+                    serenity::all::CreateInteractionResponseMessage::new()
+                        .content(format_lyrics_page(header, pages, page))
+                        .components(lyrics_components(ctx_id, page, pages.len())),
+                ),
+            )
+            .await?;
+    }
+
+    reply
+        .edit(
+            ctx,
+            CreateReply::default()
+                .content(format_lyrics_page(header, pages, page))
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Show lyrics for the current track, following along when they are time-synced.
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn lyrics(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(handler_lock) = voice_call(ctx, "Not connected to a voice channel.").await? else {
+        return Ok(());
+    };
+    let Some(track_handle) = handler_lock.lock().await.queue().current() else {
+        ctx.say("No track is currently playing.").await?;
+        return Ok(());
+    };
+    let track = track_handle.data::<crate::track::Track>().as_ref().clone();
+
+    let _ = ctx.defer().await;
+
+    let lyrics = tidal_session(&ctx).find_lyrics(&track.id).await?;
+    let Some(lyrics) = lyrics else {
+        ctx.say(format!(
+            "No lyrics were found for **{}**.",
+            get_formatted_track(&track)
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let header = format!("Lyrics for **{}**", get_formatted_track(&track));
+    if !lyrics.synced.is_empty() {
+        return follow_synced_lyrics(
+            ctx,
+            &track_handle,
+            &header,
+            &lyrics,
+            Duration::from_secs(track.duration.into()),
+        )
+        .await;
+    }
+
+    show_lyrics_pages(ctx, &header, &lyrics.pages()).await
+}
+
 /// Disconnect the bot from the voice channel.
 #[poise::command(slash_command, prefix_command, aliases("disconnect"), guild_only)]
 pub async fn leave(ctx: Context<'_>) -> Result<(), Error> {
@@ -1,9 +1,14 @@
 use crate::commands;
+use crate::track::AudioQuality;
 use std::path::{Path, PathBuf};
 
 const DEFAULT_COMMAND_PREFIX: &str = "!";
 const DEFAULT_BOT_NAME: &str = "TidalCordRS";
 const DEFAULT_BOT_PROFILE_STATE_PATH: &str = "data/bot_profile_state.json";
+const DEFAULT_QUEUE_STATE_PATH: &str = "data/queue_state.json";
+const DEFAULT_METADATA_CACHE_PATH: &str = "data/metadata_cache.json";
+const DEFAULT_METADATA_CACHE_MAX_ENTRIES: usize = 20_000;
+const DEFAULT_METADATA_CACHE_TTL_MINUTES: u64 = 24 * 60;
 const DEFAULT_SPOOL_READ_AHEAD_MIB: u64 = 16;
 const DEFAULT_COLLECTION_TRACK_FETCH_CONCURRENCY: usize = 8;
 const DEFAULT_TIDAL_TOKEN_SESSION_PATH: &str = "data/tidal_token.json";
@@ -14,12 +19,17 @@ const DEFAULT_OAUTH_DEVICE_AUTH_URL: &str = "https://auth.tidal.com/v1/oauth2/de
 const DEFAULT_OAUTH_TOKEN_URL: &str = "https://auth.tidal.com/v1/oauth2/token";
 const DEFAULT_SESSIONS_URL: &str = "https://api.tidal.com/v1/sessions";
 const DEFAULT_SEARCH_URL: &str = "https://api.tidal.com/v1/search";
+const DEFAULT_API_BASE_URL: &str = "https://api.tidal.com/v1";
+const DEFAULT_OPENAPI_BASE_URL: &str = "https://openapi.tidal.com/v2";
+pub const DEFAULT_TIDAL_ACCOUNT_NAME: &str = "default";
 
 #[derive(Clone, Debug)]
 pub struct AppConfig {
     pub discord_token: String,
     pub command_prefix: String,
     pub bot_profile: BotProfileConfig,
+    pub queue_persistence: QueuePersistenceConfig,
+    pub metadata_cache: MetadataCacheConfig,
     pub technical: TechnicalConfig,
     pub tidal: TidalConfig,
 }
@@ -32,6 +42,26 @@ pub struct BotProfileConfig {
     pub state_path: PathBuf,
 }
 
+#[derive(Clone, Debug)]
+pub struct QueuePersistenceConfig {
+    pub enabled: bool,
+    pub state_path: PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub struct MetadataCacheConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    pub max_entries: usize,
+    pub ttl_minutes: u64,
+}
+
+impl MetadataCacheConfig {
+    pub fn ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.ttl_minutes.saturating_mul(60))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TechnicalConfig {
     pub spool_read_ahead_mib: u64,
@@ -56,6 +86,49 @@ pub struct TidalConfig {
     pub oauth_token_url: String,
     pub sessions_url: String,
     pub search_url: String,
+    /// Base of the v1 API used for tracks, streams, artists, and the user's collection.
+    pub api_base_url: String,
+    /// Base of the v2 API used to list playlist and album items.
+    pub openapi_base_url: String,
+    pub audio_quality: AudioQuality,
+    /// Print a device login link to the console for accounts without a saved token, instead of
+    /// waiting for the owner to run `/tidal login`.
+    pub console_login: bool,
+    /// Extra accounts to spread guilds across. When empty, the bot uses a single account whose
+    /// token lives at `path_to_session`.
+    pub accounts: Vec<TidalAccountConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TidalAccountConfig {
+    pub name: String,
+    pub path_to_session: String,
+    /// Guilds that always use this account while it is healthy.
+    pub guild_ids: Vec<u64>,
+}
+
+impl TidalConfig {
+    /// Every configured account, or the single default account when none are listed.
+    pub fn account_configs(&self) -> Vec<TidalAccountConfig> {
+        if !self.accounts.is_empty() {
+            return self.accounts.clone();
+        }
+
+        vec![TidalAccountConfig {
+            name: DEFAULT_TIDAL_ACCOUNT_NAME.to_string(),
+            path_to_session: self.path_to_session.clone(),
+            guild_ids: Vec::new(),
+        }]
+    }
+
+    /// This config with the token stored at the account's session path.
+    pub fn for_account(&self, account: &TidalAccountConfig) -> Self {
+        Self {
+            path_to_session: account.path_to_session.clone(),
+            accounts: Vec::new(),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Default, serde::Deserialize)]
@@ -63,6 +136,8 @@ pub struct TidalConfig {
 struct FileConfig {
     command_prefix: Option<String>,
     bot_profile: FileBotProfileConfig,
+    queue_persistence: FileQueuePersistenceConfig,
+    metadata_cache: FileMetadataCacheConfig,
     technical: FileTechnicalConfig,
     tidal: FileTidalConfig,
 }
@@ -76,6 +151,22 @@ struct FileBotProfileConfig {
     state_path: Option<String>,
 }
 
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileQueuePersistenceConfig {
+    enabled: Option<bool>,
+    state_path: Option<String>,
+}
+
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileMetadataCacheConfig {
+    enabled: Option<bool>,
+    path: Option<String>,
+    max_entries: Option<usize>,
+    ttl_minutes: Option<u64>,
+}
+
 #[derive(Default, serde::Deserialize)]
 #[serde(default, deny_unknown_fields)]
 struct FileTechnicalConfig {
@@ -94,6 +185,20 @@ struct FileTidalConfig {
     oauth_token_url: Option<String>,
     sessions_url: Option<String>,
     search_url: Option<String>,
+    api_base_url: Option<String>,
+    openapi_base_url: Option<String>,
+    audio_quality: Option<String>,
+    console_login: Option<bool>,
+    accounts: Option<Vec<FileTidalAccountConfig>>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileTidalAccountConfig {
+    name: String,
+    token_session_path: String,
+    #[serde(default)]
+    guilds: Vec<u64>,
 }
 
 impl AppConfig {
@@ -118,6 +223,16 @@ impl AppConfig {
                 avatar_path: None,
                 state_path: PathBuf::from(DEFAULT_BOT_PROFILE_STATE_PATH),
             },
+            queue_persistence: QueuePersistenceConfig {
+                enabled: true,
+                state_path: PathBuf::from(DEFAULT_QUEUE_STATE_PATH),
+            },
+            metadata_cache: MetadataCacheConfig {
+                enabled: true,
+                path: PathBuf::from(DEFAULT_METADATA_CACHE_PATH),
+                max_entries: DEFAULT_METADATA_CACHE_MAX_ENTRIES,
+                ttl_minutes: DEFAULT_METADATA_CACHE_TTL_MINUTES,
+            },
             technical: TechnicalConfig {
                 spool_read_ahead_mib: DEFAULT_SPOOL_READ_AHEAD_MIB,
                 collection_track_fetch_concurrency: DEFAULT_COLLECTION_TRACK_FETCH_CONCURRENCY,
@@ -131,6 +246,11 @@ impl AppConfig {
                 oauth_token_url: DEFAULT_OAUTH_TOKEN_URL.to_string(),
                 sessions_url: DEFAULT_SESSIONS_URL.to_string(),
                 search_url: DEFAULT_SEARCH_URL.to_string(),
+                api_base_url: DEFAULT_API_BASE_URL.to_string(),
+                openapi_base_url: DEFAULT_OPENAPI_BASE_URL.to_string(),
+                audio_quality: AudioQuality::High,
+                console_login: false,
+                accounts: Vec::new(),
             },
         }
     }
@@ -156,6 +276,24 @@ impl AppConfig {
         if let Some(state_path) = file_config.bot_profile.state_path {
             self.bot_profile.state_path = PathBuf::from(state_path);
         }
+        if let Some(enabled) = file_config.queue_persistence.enabled {
+            self.queue_persistence.enabled = enabled;
+        }
+        if let Some(state_path) = file_config.queue_persistence.state_path {
+            self.queue_persistence.state_path = PathBuf::from(state_path);
+        }
+        if let Some(enabled) = file_config.metadata_cache.enabled {
+            self.metadata_cache.enabled = enabled;
+        }
+        if let Some(path) = file_config.metadata_cache.path {
+            self.metadata_cache.path = PathBuf::from(path);
+        }
+        if let Some(max_entries) = file_config.metadata_cache.max_entries {
+            self.metadata_cache.max_entries = max_entries;
+        }
+        if let Some(ttl_minutes) = file_config.metadata_cache.ttl_minutes {
+            self.metadata_cache.ttl_minutes = ttl_minutes;
+        }
         if let Some(spool_read_ahead_mib) = file_config.technical.spool_read_ahead_mib {
             self.technical.spool_read_ahead_mib = spool_read_ahead_mib;
         }
@@ -188,6 +326,30 @@ impl AppConfig {
         if let Some(search_url) = file_config.tidal.search_url {
             self.tidal.search_url = search_url;
         }
+        if let Some(api_base_url) = file_config.tidal.api_base_url {
+            self.tidal.api_base_url = api_base_url;
+        }
+        if let Some(openapi_base_url) = file_config.tidal.openapi_base_url {
+            self.tidal.openapi_base_url = openapi_base_url;
+        }
+        if let Some(audio_quality) = file_config.tidal.audio_quality {
+            self.tidal.audio_quality = audio_quality
+                .parse()
+                .map_err(|error| format!("Failed to parse tidal.audio_quality: {error}"))?;
+        }
+        if let Some(console_login) = file_config.tidal.console_login {
+            self.tidal.console_login = console_login;
+        }
+        if let Some(accounts) = file_config.tidal.accounts {
+            self.tidal.accounts = accounts
+                .into_iter()
+                .map(|account| TidalAccountConfig {
+                    name: account.name,
+                    path_to_session: account.token_session_path,
+                    guild_ids: account.guilds,
+                })
+                .collect();
+        }
 
         Ok(())
     }
@@ -219,6 +381,30 @@ impl AppConfig {
                 "BOT_PROFILE_STATE_PATH",
             ],
         )?;
+        apply_bool(
+            &mut self.queue_persistence.enabled,
+            &["TIDALCORDRS_QUEUE_PERSISTENCE__ENABLED"],
+        )?;
+        apply_path(
+            &mut self.queue_persistence.state_path,
+            &["TIDALCORDRS_QUEUE_PERSISTENCE__STATE_PATH"],
+        )?;
+        apply_bool(
+            &mut self.metadata_cache.enabled,
+            &["TIDALCORDRS_METADATA_CACHE__ENABLED"],
+        )?;
+        apply_path(
+            &mut self.metadata_cache.path,
+            &["TIDALCORDRS_METADATA_CACHE__PATH"],
+        )?;
+        apply_parse(
+            &mut self.metadata_cache.max_entries,
+            &["TIDALCORDRS_METADATA_CACHE__MAX_ENTRIES"],
+        )?;
+        apply_parse(
+            &mut self.metadata_cache.ttl_minutes,
+            &["TIDALCORDRS_METADATA_CACHE__TTL_MINUTES"],
+        )?;
         apply_parse(
             &mut self.technical.spool_read_ahead_mib,
             &[
@@ -271,6 +457,22 @@ impl AppConfig {
             &mut self.tidal.search_url,
             &["TIDALCORDRS_TIDAL__SEARCH_URL", "SEARCH_URL"],
         )?;
+        apply_string(
+            &mut self.tidal.api_base_url,
+            &["TIDALCORDRS_TIDAL__API_BASE_URL"],
+        )?;
+        apply_string(
+            &mut self.tidal.openapi_base_url,
+            &["TIDALCORDRS_TIDAL__OPENAPI_BASE_URL"],
+        )?;
+        apply_parse(
+            &mut self.tidal.audio_quality,
+            &["TIDALCORDRS_TIDAL__AUDIO_QUALITY"],
+        )?;
+        apply_bool(
+            &mut self.tidal.console_login,
+            &["TIDALCORDRS_TIDAL__CONSOLE_LOGIN"],
+        )?;
 
         Ok(())
     }
@@ -287,11 +489,50 @@ impl AppConfig {
                 "technical.collection_track_fetch_concurrency must be greater than 0".into(),
             );
         }
+        if self.metadata_cache.enabled && self.metadata_cache.max_entries == 0 {
+            return Err("metadata_cache.max_entries must be greater than 0".into());
+        }
         self.technical.spool_read_ahead_bytes()?;
+        validate_tidal_accounts(&self.tidal.accounts)?;
         Ok(())
     }
 }
 
+fn validate_tidal_accounts(accounts: &[TidalAccountConfig]) -> Result<(), commands::Error> {
+    let mut names = std::collections::HashSet::new();
+    let mut paths = std::collections::HashSet::new();
+    let mut pinned_guilds = std::collections::HashSet::new();
+
+    for account in accounts {
+        if account.name.trim().is_empty() {
+            return Err("tidal.accounts names must not be empty".into());
+        }
+        if !names.insert(account.name.to_lowercase()) {
+            return Err(format!(
+                "tidal.accounts has more than one account named {}",
+                account.name
+            )
+            .into());
+        }
+        if !paths.insert(account.path_to_session.as_str()) {
+            return Err(format!(
+                "tidal.accounts shares token_session_path {} between accounts",
+                account.path_to_session
+            )
+            .into());
+        }
+        for guild_id in &account.guild_ids {
+            if !pinned_guilds.insert(guild_id) {
+                return Err(
+                    format!("Guild {guild_id} is pinned to more than one Tidal account").into(),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn config_path() -> Result<Option<PathBuf>, commands::Error> {
     match std::env::var("CONFIG_PATH") {
         Ok(value) if value.trim().is_empty() => Ok(None),
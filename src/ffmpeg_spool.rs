@@ -3,8 +3,19 @@ use songbird::input::{AudioStream, AudioStreamError};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::time::Duration;
 
+// How long to wait for ffmpeg's first output before assuming the stream URL works
+const STREAM_START_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Provides the URL ffmpeg reads from. It is asked again with `force_refresh` when a stream
+/// fails to start, so sources with expiring URLs hand out a new one instead of a cached one.
+#[serenity::async_trait]
+pub trait StreamSource: Send + Sync {
+    async fn stream_url(&self, force_refresh: bool) -> Result<String, crate::commands::Error>;
+}
+
 pub struct FfmpegStream {
-    url: String,
+    program: std::ffi::OsString,
+    source: Box<dyn StreamSource>,
     spool_read_ahead_bytes: u64,
     start_position: Duration,
     audio_bitrate: u32,
@@ -24,9 +35,16 @@ struct SharedSpool {
     room: std::sync::Condvar,
 }
 
+/// Segment URLs of a DASH stream, written to a file so ffmpeg can read them with the
+/// `concatf:` protocol. Long tracks have too many segments to pass on the command line.
+struct SegmentList {
+    path: std::path::PathBuf,
+}
+
 struct SpoolReader {
     file: std::fs::File,
     path: std::path::PathBuf,
+    _segment_list: Option<SegmentList>,
     shared: std::sync::Arc<SharedSpool>,
     child: std::sync::Arc<std::sync::Mutex<std::process::Child>>,
     writer: std::sync::Mutex<Option<std::thread::JoinHandle<()>>>,
@@ -56,9 +74,43 @@ impl SharedSpool {
     }
 }
 
+impl SegmentList {
+    fn write(segments: &str) -> Result<Self, AudioStreamError> {
+        let path = temp_path("txt")?;
+        let contents = segments
+            .split('|')
+            .map(|segment| format!("{segment}\n"))
+            .collect::<String>();
+        std::fs::write(&path, contents).map_err(|error| AudioStreamError::Fail(Box::new(error)))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for SegmentList {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn temp_path(extension: &str) -> Result<std::path::PathBuf, AudioStreamError> {
+    static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+    let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|error| AudioStreamError::Fail(Box::new(error)))?
+        .as_nanos();
+
+    Ok(std::env::temp_dir().join(format!(
+        "tidalcordrs-spool-{}-{timestamp}-{id}.{extension}",
+        std::process::id()
+    )))
+}
+
 impl SpoolReader {
     fn new(
         mut child: std::process::Child,
+        segment_list: Option<SegmentList>,
         spool_read_ahead_bytes: u64,
     ) -> Result<Self, AudioStreamError> {
         let stdout = child
@@ -66,7 +118,7 @@ impl SpoolReader {
             .take()
             .ok_or_else(|| AudioStreamError::Fail("ffmpeg stdout was not piped".into()))?;
 
-        let path = Self::create_spool_path()?;
+        let path = temp_path("ogg")?;
         let mut writer_file = std::fs::OpenOptions::new()
             .write(true)
             .create_new(true)
@@ -95,27 +147,13 @@ impl SpoolReader {
         Ok(Self {
             file,
             path,
+            _segment_list: segment_list,
             shared,
             child,
             writer: std::sync::Mutex::new(Some(writer)),
         })
     }
 
-    fn create_spool_path() -> Result<std::path::PathBuf, AudioStreamError> {
-        static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
-
-        let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|error| AudioStreamError::Fail(Box::new(error)))?
-            .as_nanos();
-
-        Ok(std::env::temp_dir().join(format!(
-            "tidalcordrs-spool-{}-{timestamp}-{id}.ogg",
-            std::process::id()
-        )))
-    }
-
     fn write_spool(
         mut stdout: std::process::ChildStdout,
         writer_file: &mut std::fs::File,
@@ -174,6 +212,26 @@ impl SpoolReader {
     }
 }
 
+impl SpoolReader {
+    /// Blocks until ffmpeg produces output or exits. Returns the error if it exited
+    /// without writing anything, which usually means the stream URL was rejected.
+    fn wait_for_start(&self, timeout: Duration) -> Result<(), String> {
+        let state = self.shared.state.lock().unwrap();
+        let (state, _) = self
+            .shared
+            .available
+            .wait_timeout_while(state, timeout, |state| {
+                state.bytes_written == 0 && !state.done
+            })
+            .unwrap();
+
+        match &state.error {
+            Some(error) if state.bytes_written == 0 => Err(error.clone()),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl Read for SpoolReader {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         if buf.is_empty() {
@@ -252,26 +310,48 @@ impl Drop for SpoolReader {
 }
 
 impl FfmpegStream {
-    pub fn new(url: &str, spool_read_ahead_bytes: u64, audio_bitrate: u32) -> Self {
-        Self::new_at(url, spool_read_ahead_bytes, Duration::ZERO, audio_bitrate)
+    pub fn new(
+        source: Box<dyn StreamSource>,
+        spool_read_ahead_bytes: u64,
+        audio_bitrate: u32,
+    ) -> Self {
+        Self::new_at(
+            source,
+            spool_read_ahead_bytes,
+            Duration::ZERO,
+            audio_bitrate,
+        )
     }
 
     pub fn new_at(
-        url: &str,
+        source: Box<dyn StreamSource>,
         spool_read_ahead_bytes: u64,
         start_position: Duration,
         audio_bitrate: u32,
     ) -> Self {
         Self {
-            url: url.to_string(),
+            program: "ffmpeg".into(),
+            source,
             spool_read_ahead_bytes,
             start_position,
             audio_bitrate,
         }
     }
 
-    fn spawn(&self) -> Result<std::process::Child, AudioStreamError> {
-        let mut command = std::process::Command::new("ffmpeg");
+    fn spawn(
+        &self,
+        url: &str,
+    ) -> Result<(std::process::Child, Option<SegmentList>), AudioStreamError> {
+        let segment_list = url
+            .strip_prefix("concat:")
+            .map(SegmentList::write)
+            .transpose()?;
+        let input = match &segment_list {
+            Some(segment_list) => format!("concatf:{}", segment_list.path.display()),
+            None => url.to_string(),
+        };
+
+        let mut command = std::process::Command::new(&self.program);
         command.args([
             "-loglevel",
             "error",
@@ -293,7 +373,7 @@ impl FfmpegStream {
         }
 
         let child = command
-            .args(["-i", &self.url, "-vn", "-c:a", "libopus", "-b:a"])
+            .args(["-i", &input, "-vn", "-c:a", "libopus", "-b:a"])
             .arg(self.audio_bitrate.to_string())
             .args(["-f", "opus", "pipe:1"])
             .stdin(std::process::Stdio::null())
@@ -306,28 +386,107 @@ impl FfmpegStream {
             return Err(AudioStreamError::Fail("ffmpeg stdout was not piped".into()));
         }
 
-        Ok(child)
+        Ok((child, segment_list))
     }
 }
 
 #[serenity::async_trait]
 impl songbird::input::Compose for FfmpegStream {
     fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
-        let child = self.spawn()?;
-        self.start_position = Duration::ZERO;
-        let input = SpoolReader::new(child, self.spool_read_ahead_bytes)?;
-        Ok(AudioStream {
-            input: Box::new(input) as Box<dyn MediaSource>,
-        })
+        Err(AudioStreamError::Unsupported)
     }
 
     async fn create_async(
         &mut self,
     ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
-        self.create()
+        let mut retried = false;
+
+        loop {
+            let url = self
+                .source
+                .stream_url(retried)
+                .await
+                .map_err(AudioStreamError::Fail)?;
+            let (child, segment_list) = self.spawn(&url)?;
+            let input = SpoolReader::new(child, segment_list, self.spool_read_ahead_bytes)?;
+
+            let (input, started) = tokio::task::spawn_blocking(move || {
+                let started = input.wait_for_start(STREAM_START_TIMEOUT);
+                (input, started)
+            })
+            .await
+            .map_err(|error| AudioStreamError::Fail(Box::new(error)))?;
+
+            match started {
+                Ok(()) => {
+                    self.start_position = Duration::ZERO;
+                    return Ok(AudioStream {
+                        input: Box::new(input) as Box<dyn MediaSource>,
+                    });
+                }
+                // Signed URLs can expire between resolving and opening, so ask for a fresh one once
+                Err(error) if !retried => {
+                    tracing::warn!(%error, "Stream failed to start; resolving a new stream URL");
+                    retried = true;
+                }
+                Err(error) => return Err(AudioStreamError::Fail(error.into())),
+            }
+        }
     }
 
     fn should_create_async(&self) -> bool {
-        false
+        true
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use songbird::input::Compose;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Hands out an expired URL first, and a working one once asked to refresh.
+    struct ExpiringSource {
+        refreshes: std::sync::Arc<std::sync::Mutex<Vec<bool>>>,
+    }
+
+    #[serenity::async_trait]
+    impl StreamSource for ExpiringSource {
+        async fn stream_url(&self, force_refresh: bool) -> Result<String, crate::commands::Error> {
+            self.refreshes.lock().unwrap().push(force_refresh);
+            Ok(if force_refresh { "fresh" } else { "expired" }.to_string())
+        }
+    }
+
+    /// Stands in for ffmpeg: fails on the expired URL and writes a little output otherwise.
+    fn fake_ffmpeg() -> std::path::PathBuf {
+        let path = temp_path("sh").unwrap();
+        std::fs::write(
+            &path,
+            "#!/bin/sh\ncase \"$*\" in *expired*) exit 1 ;; esac\nprintf OggS\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn fetches_a_new_url_when_the_stream_fails_to_start() {
+        let program = fake_ffmpeg();
+        let refreshes = std::sync::Arc::default();
+        let mut stream = FfmpegStream::new(
+            Box::new(ExpiringSource {
+                refreshes: std::sync::Arc::clone(&refreshes),
+            }),
+            1024,
+            64_000,
+        );
+        stream.program = program.clone().into();
+
+        let result = stream.create_async().await;
+        let _ = std::fs::remove_file(program);
+
+        assert!(result.is_ok());
+        assert_eq!(*refreshes.lock().unwrap(), [false, true]);
     }
 }
@@ -0,0 +1,375 @@
+use crate::commands::Error;
+use std::time::{Duration, Instant};
+
+const MAX_ATTEMPTS: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// Tidal's `subStatus` codes for a missing, invalid or expired token
+const TOKEN_SUB_STATUSES: std::ops::Range<u64> = 11000..12000;
+
+/// A failed Tidal request. Kept typed so callers can tell a rejected token from other failures.
+#[derive(Debug)]
+pub enum TidalHttpError {
+    Status {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    Transport(reqwest::Error),
+}
+
+impl TidalHttpError {
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            Self::Status { status, .. } => Some(*status),
+            Self::Transport(error) => error.status(),
+        }
+    }
+
+    /// Tidal's own error code from the response body, which tells apart the reasons behind
+    /// one HTTP status.
+    pub fn sub_status(&self) -> Option<u64> {
+        match self {
+            Self::Status { body, .. } => serde_json::from_str::<serde_json::Value>(body)
+                .ok()?
+                .get("subStatus")?
+                .as_u64(),
+            Self::Transport(_) => None,
+        }
+    }
+
+    /// Whether Tidal rejected the access token, which is the only case worth a token refresh.
+    /// Tidal also answers 401 or 403 when the account may not play a track or quality; those
+    /// carry a different `subStatus`.
+    pub fn is_unauthorized(&self) -> bool {
+        self.status().is_some_and(|status| {
+            status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN
+        }) && self
+            .sub_status()
+            .is_none_or(|sub_status| TOKEN_SUB_STATUSES.contains(&sub_status))
+    }
+}
+
+impl std::fmt::Display for TidalHttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Status { status, body } => write!(f, "Tidal returned status {status}: {body}"),
+            Self::Transport(error) => write!(f, "Tidal request failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for TidalHttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Status { .. } => None,
+            Self::Transport(error) => Some(error),
+        }
+    }
+}
+
+pub fn is_auth_error(error: &Error) -> bool {
+    error
+        .downcast_ref::<TidalHttpError>()
+        .is_some_and(TidalHttpError::is_unauthorized)
+}
+
+/// Joins a configured base URL and a path, whether or not the base ends with a slash.
+pub fn endpoint(base_url: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+/// HTTP client shared by every Tidal request. Clones share the throttle, so a 429 on one
+/// request holds back the others until Tidal's `Retry-After` has passed.
+#[derive(Clone, Debug, Default)]
+pub struct TidalHttp {
+    client: reqwest::Client,
+    paused_until: std::sync::Arc<std::sync::Mutex<Option<Instant>>>,
+}
+
+impl TidalHttp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Builds a request carrying the user agent and access token Tidal expects.
+    pub fn authorized(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        user_agent: &str,
+        authorization: &str,
+    ) -> Result<reqwest::RequestBuilder, Error> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("User-Agent", user_agent.parse()?);
+        headers.insert("Authorization", authorization.parse()?);
+
+        Ok(self.client.request(method, url).headers(headers))
+    }
+
+    /// Sends the request and returns the response when it succeeds. Rate limits (429) are
+    /// retried with jittered exponential backoff, honouring `Retry-After`. Server errors (5xx)
+    /// and timeouts are retried the same way, but only for idempotent methods, so a POST that
+    /// Tidal may have applied is never sent twice. Any other status is returned as an error
+    /// straight away.
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, TidalHttpError> {
+        let idempotent = request
+            .try_clone()
+            .and_then(|request| request.build().ok())
+            .is_some_and(|request| request.method().is_idempotent());
+        let mut attempt = 0;
+
+        loop {
+            self.wait_for_throttle().await;
+
+            // Requests with streaming bodies cannot be replayed, so they only get one try
+            let Some(attempt_request) = request.try_clone() else {
+                return check_status(request.send().await.map_err(TidalHttpError::Transport)?)
+                    .await;
+            };
+            attempt += 1;
+
+            let response = match attempt_request.send().await {
+                Ok(response) => response,
+                Err(error)
+                    if attempt < MAX_ATTEMPTS
+                        && (error.is_connect() || (idempotent && error.is_timeout())) =>
+                {
+                    let delay = backoff_delay(attempt, None, fastrand::f64());
+                    tracing::debug!(%error, attempt, ?delay, "Retrying Tidal request");
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                Err(error) => return Err(TidalHttpError::Transport(error)),
+            };
+
+            let status = response.status();
+            let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || (idempotent && status.is_server_error());
+            if !retryable || attempt >= MAX_ATTEMPTS {
+                return check_status(response).await;
+            }
+
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+            let delay = backoff_delay(attempt, retry_after, fastrand::f64());
+            tracing::warn!(
+                url = %response.url(),
+                %status,
+                attempt,
+                ?delay,
+                "Tidal request throttled or failed; backing off"
+            );
+
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.pause_for(delay);
+            } else {
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+
+    fn pause_for(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if paused_until.is_none_or(|current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    async fn wait_for_throttle(&self) {
+        let paused_until = *self.paused_until.lock().unwrap();
+        if let Some(until) = paused_until
+            && until > Instant::now()
+        {
+            tokio::time::sleep_until(until.into()).await;
+        }
+    }
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, TidalHttpError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response
+        .text()
+        .await
+        .unwrap_or_else(|error| format!("failed to read response body: {error}"));
+
+    Err(TidalHttpError::Status { status, body })
+}
+
+/// Only the delay-seconds form is handled; Tidal does not send HTTP dates.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+/// `jitter` is a random value in `0..1` that spreads the delay over its upper half, so
+/// concurrent requests do not all retry at the same moment.
+fn backoff_delay(attempt: u32, retry_after: Option<Duration>, jitter: f64) -> Duration {
+    if let Some(retry_after) = retry_after {
+        return retry_after.min(MAX_BACKOFF);
+    }
+
+    let exponential = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF);
+
+    exponential.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        assert_eq!(backoff_delay(1, None, 1.0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(3, None, 1.0), Duration::from_secs(2));
+        assert_eq!(backoff_delay(3, None, 0.0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(20, None, 1.0), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn honours_retry_after_up_to_the_cap() {
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(
+            backoff_delay(1, Some(Duration::from_secs(7)), 0.0),
+            Duration::from_secs(7)
+        );
+        assert_eq!(
+            backoff_delay(1, Some(Duration::from_secs(3600)), 0.0),
+            MAX_BACKOFF
+        );
+    }
+
+    /// Answers each connection with the next canned response and counts the requests seen.
+    async fn serve_responses(
+        responses: Vec<&'static str>,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let seen = requests.clone();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 4096];
+                let _ = stream.read(&mut buffer).await.unwrap();
+                seen.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_requests() {
+        let (url, requests) = serve_responses(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        ])
+        .await;
+        let http = TidalHttp::new();
+
+        let response = http.send(http.get(&url)).await.unwrap();
+
+        assert_eq!(response.text().await.unwrap(), "ok");
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (url, requests) = serve_responses(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 7\r\nConnection: close\r\n\r\nmissing",
+        ])
+        .await;
+        let http = TidalHttp::new();
+
+        let error = http.send(http.get(&url)).await.unwrap_err();
+
+        assert_eq!(error.status(), Some(reqwest::StatusCode::NOT_FOUND));
+        assert!(!error.is_unauthorized());
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn joins_base_urls_and_paths() {
+        assert_eq!(
+            endpoint("https://api.tidal.com/v1", "tracks/1"),
+            "https://api.tidal.com/v1/tracks/1"
+        );
+        assert_eq!(
+            endpoint("http://127.0.0.1:8080/v1/", "/tracks/1"),
+            "http://127.0.0.1:8080/v1/tracks/1"
+        );
+    }
+
+    #[test]
+    fn only_401_and_403_count_as_auth_errors() {
+        let status_error = |status| -> Error {
+            Box::new(TidalHttpError::Status {
+                status,
+                body: String::new(),
+            })
+        };
+        let sub_status_error = |status, sub_status: u64| -> Error {
+            Box::new(TidalHttpError::Status {
+                status,
+                body: format!(
+                    r#"{{"status":{},"subStatus":{sub_status}}}"#,
+                    status.as_u16()
+                ),
+            })
+        };
+
+        assert!(is_auth_error(&status_error(
+            reqwest::StatusCode::UNAUTHORIZED
+        )));
+        assert!(is_auth_error(&status_error(reqwest::StatusCode::FORBIDDEN)));
+        assert!(!is_auth_error(&status_error(
+            reqwest::StatusCode::NOT_FOUND
+        )));
+        assert!(!is_auth_error(&status_error(
+            reqwest::StatusCode::TOO_MANY_REQUESTS
+        )));
+        assert!(!is_auth_error(&"Expected stream URL".into()));
+
+        assert!(is_auth_error(&sub_status_error(
+            reqwest::StatusCode::UNAUTHORIZED,
+            11002
+        )));
+        assert!(!is_auth_error(&sub_status_error(
+            reqwest::StatusCode::UNAUTHORIZED,
+            4005
+        )));
+        assert!(!is_auth_error(&sub_status_error(
+            reqwest::StatusCode::FORBIDDEN,
+            4035
+        )));
+    }
+}
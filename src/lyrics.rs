@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+const LYRICS_PAGE_CHARS: usize = 1800;
+const SYNCED_LINES_BEFORE: usize = 2;
+const SYNCED_LINES_AFTER: usize = 4;
+const INSTRUMENTAL_LINE: &str = "♪";
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TidalLyricsResponse {
+    #[serde(default)]
+    lyrics: Option<String>,
+    #[serde(default)]
+    subtitles: Option<String>,
+}
+
+impl TidalLyricsResponse {
+    /// Returns `None` when Tidal has neither plain nor synced lyrics for the track.
+    pub fn into_lyrics(self) -> Option<Lyrics> {
+        let synced = self.subtitles.as_deref().map(parse_lrc).unwrap_or_default();
+        let plain = match self.lyrics {
+            Some(lyrics) if !lyrics.trim().is_empty() => lyrics.trim().to_string(),
+            _ => synced
+                .iter()
+                .map(|line| line.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+
+        if plain.trim().is_empty() && synced.is_empty() {
+            return None;
+        }
+
+        Some(Lyrics { plain, synced })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncedLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Lyrics {
+    pub plain: String,
+    pub synced: Vec<SyncedLine>,
+}
+
+impl Lyrics {
+    /// Index of the synced line being sung at `position`, or `None` before the first line.
+    pub fn current_line(&self, position: Duration) -> Option<usize> {
+        self.synced
+            .partition_point(|line| line.time <= position)
+            .checked_sub(1)
+    }
+
+    /// A few lines around the current one, with the current line in bold.
+    pub fn format_synced(&self, current: Option<usize>) -> String {
+        let center = current.unwrap_or(0);
+        let start = center.saturating_sub(SYNCED_LINES_BEFORE);
+        let end = (center + SYNCED_LINES_AFTER + 1).min(self.synced.len());
+
+        self.synced[start..end]
+            .iter()
+            .enumerate()
+            .map(|(offset, line)| {
+                let text = if line.text.is_empty() {
+                    INSTRUMENTAL_LINE
+                } else {
+                    line.text.as_str()
+                };
+
+                if Some(start + offset) == current {
+                    format!("**{}**", text)
+                } else {
+                    text.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Splits the plain lyrics on line boundaries into pages that fit in a Discord message.
+    /// Discord counts characters rather than bytes, and lines longer than a page are split.
+    pub fn pages(&self) -> Vec<String> {
+        let mut pages = Vec::new();
+        let mut page = String::new();
+        let mut page_chars = 0;
+
+        for line in self.plain.lines().flat_map(split_long_line) {
+            let line_chars = line.chars().count();
+            if page_chars > 0 && page_chars + line_chars + 1 > LYRICS_PAGE_CHARS {
+                pages.push(std::mem::take(&mut page));
+                page_chars = 0;
+            }
+
+            if page_chars > 0 {
+                page.push('\n');
+                page_chars += 1;
+            }
+            page.push_str(line);
+            page_chars += line_chars;
+        }
+
+        if !page.trim().is_empty() {
+            pages.push(page);
+        }
+
+        pages
+    }
+}
+
+fn split_long_line(line: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = line;
+    while let Some((split, _)) = rest.char_indices().nth(LYRICS_PAGE_CHARS) {
+        let (part, after) = rest.split_at(split);
+        parts.push(part);
+        rest = after;
+    }
+    parts.push(rest);
+    parts
+}
+
+/// Parses LRC subtitles such as `[01:02.34] line`, ignoring metadata tags like `[ar:Artist]`.
+/// A line may carry several timestamps when it repeats.
+pub fn parse_lrc(subtitles: &str) -> Vec<SyncedLine> {
+    let mut lines = Vec::new();
+
+    for raw_line in subtitles.lines() {
+        let mut rest = raw_line.trim();
+        let mut times = Vec::new();
+
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some((timestamp, after)) = tag.split_once(']') else {
+                break;
+            };
+            let Some(time) = parse_lrc_timestamp(timestamp) else {
+                break;
+            };
+
+            times.push(time);
+            rest = after;
+        }
+
+        let text = rest.trim();
+        lines.extend(times.into_iter().map(|time| SyncedLine {
+            time,
+            text: text.to_string(),
+        }));
+    }
+
+    lines.sort_by_key(|line| line.time);
+    lines
+}
+
+fn parse_lrc_timestamp(timestamp: &str) -> Option<Duration> {
+    let (minutes, seconds) = timestamp.split_once(':')?;
+    let minutes = minutes.parse::<u64>().ok()?;
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, "0"));
+    let seconds = seconds.parse::<u64>().ok()?;
+    if seconds >= 60 || fraction.is_empty() || fraction.len() > 3 {
+        return None;
+    }
+
+    // `.5`, `.50`, and `.500` are all half a second
+    let millis = fraction.parse::<u64>().ok()? * 10u64.pow(3 - fraction.len() as u32);
+
+    Some(Duration::from_millis(
+        (minutes * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lrc_subtitles() {
+        let lines = parse_lrc(
+            "[ar:Some Artist]\n[00:12.34] First line\n[00:05.1]\n[01:02.345][00:20.00] Chorus\n",
+        );
+
+        assert_eq!(
+            lines,
+            vec![
+                SyncedLine {
+                    time: Duration::from_millis(5_100),
+                    text: String::new(),
+                },
+                SyncedLine {
+                    time: Duration::from_millis(12_340),
+                    text: "First line".to_string(),
+                },
+                SyncedLine {
+                    time: Duration::from_millis(20_000),
+                    text: "Chorus".to_string(),
+                },
+                SyncedLine {
+                    time: Duration::from_millis(62_345),
+                    text: "Chorus".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_synced_lines_by_position() {
+        let lyrics = TidalLyricsResponse {
+            lyrics: None,
+            subtitles: Some("[00:01.00] One\n[00:02.00] Two\n[00:03.00] Three".to_string()),
+        }
+        .into_lyrics()
+        .unwrap();
+
+        assert_eq!(lyrics.plain, "One\nTwo\nThree");
+        assert_eq!(lyrics.current_line(Duration::from_millis(500)), None);
+        assert_eq!(lyrics.current_line(Duration::from_millis(2_500)), Some(1));
+        assert_eq!(lyrics.current_line(Duration::from_secs(60)), Some(2));
+        assert_eq!(lyrics.format_synced(Some(1)), "One\n**Two**\nThree");
+    }
+
+    #[test]
+    fn paginates_plain_lyrics_on_line_boundaries() {
+        let line = "x".repeat(700);
+        let lyrics = Lyrics {
+            plain: [line.as_str(); 5].join("\n"),
+            synced: Vec::new(),
+        };
+
+        let pages = lyrics.pages();
+
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0], [line.as_str(); 2].join("\n"));
+        assert_eq!(pages[2], line);
+    }
+
+    #[test]
+    fn splits_lines_longer_than_a_page() {
+        let lyrics = Lyrics {
+            plain: "é".repeat(LYRICS_PAGE_CHARS * 2 + 10),
+            synced: Vec::new(),
+        };
+
+        let pages = lyrics.pages();
+
+        assert_eq!(
+            pages
+                .iter()
+                .map(|page| page.chars().count())
+                .collect::<Vec<_>>(),
+            [LYRICS_PAGE_CHARS, LYRICS_PAGE_CHARS, 10]
+        );
+    }
+
+    #[test]
+    fn empty_lyrics_are_missing() {
+        let response = TidalLyricsResponse {
+            lyrics: Some("  ".to_string()),
+            subtitles: None,
+        };
+
+        assert!(response.into_lyrics().is_none());
+    }
+}
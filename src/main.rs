@@ -1,9 +1,20 @@
 mod commands;
 mod config;
 mod ffmpeg_spool;
+mod http;
+mod lyrics;
+mod metadata_cache;
+#[cfg(test)]
+mod mock_tidal;
+mod open_graph;
+mod queue_state;
 mod session;
+mod session_pool;
+mod spotify;
 mod track;
+mod track_match;
 mod url_handler;
+mod youtube;
 
 use config::BotProfileConfig;
 use poise::serenity_prelude as serenity;
@@ -37,6 +48,10 @@ async fn event_handler(
     _framework: poise::FrameworkContext<'_, commands::Data, commands::Error>,
     data: &commands::Data,
 ) -> Result<(), commands::Error> {
+    if let serenity::FullEvent::Ready { .. } = event {
+        commands::restore_guild_queues(ctx, data).await;
+    }
+
     if let serenity::FullEvent::VoiceStateUpdate { old: _, new } = event {
         let guild_id = match new.guild_id {
             Some(id) => id,
@@ -282,10 +297,42 @@ async fn run() -> Result<(), commands::Error> {
     let spool_read_ahead_bytes = app_config.technical.spool_read_ahead_bytes()?;
     let collection_track_fetch_concurrency =
         app_config.technical.collection_track_fetch_concurrency;
+    let queue_persistence = std::sync::Arc::new(queue_state::QueuePersistence::new(
+        &app_config.queue_persistence,
+    ));
+    let repeat_modes =
+        std::sync::Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
+    let default_audio_quality = app_config.tidal.audio_quality;
+    let audio_qualities =
+        std::sync::Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new()));
+    let voice_manager = songbird::Songbird::serenity();
     let version = env!("CARGO_PKG_VERSION");
 
     // Initialize the Tidal session
-    let tidal_session = session::Session::new(app_config.tidal).await?;
+    let metadata_cache = std::sync::Arc::new(metadata_cache::MetadataCache::load(
+        &app_config.metadata_cache,
+    ));
+    let console_login = app_config.tidal.console_login;
+    let tidal_accounts = std::sync::Arc::new(
+        session_pool::SessionPool::new(app_config.tidal, metadata_cache.clone()).await?,
+    );
+    for account in tidal_accounts.accounts() {
+        tokio::spawn(session::keep_token_fresh(
+            account.session.clone(),
+            account.name.clone(),
+        ));
+
+        // Waits for approval in the background, so Discord still connects in the meantime
+        if console_login && account.session.status().is_none() {
+            let session = account.session.clone();
+            let name = account.name.clone();
+            tokio::spawn(async move {
+                if let Err(error) = session.console_login().await {
+                    tracing::error!(account = %name, %error, "Tidal console login failed");
+                }
+            });
+        }
+    }
 
     // Set the intents
     let intents = serenity::GatewayIntents::GUILDS
@@ -293,30 +340,45 @@ async fn run() -> Result<(), commands::Error> {
         | serenity::GatewayIntents::GUILD_VOICE_STATES
         | serenity::GatewayIntents::MESSAGE_CONTENT;
 
+    // Shared with the background tasks that save guild queues
+    let snapshot_queue_persistence = queue_persistence.clone();
+    let snapshot_repeat_modes = repeat_modes.clone();
+    let snapshot_audio_qualities = audio_qualities.clone();
+    let snapshot_voice_manager = voice_manager.clone();
+
     // Create a new Poise framework instance
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![
+            commands: commands::required_options_first(vec![
                 commands::help(),
                 commands::ping(),
                 commands::join(),
                 commands::volume(),
+                commands::quality(),
                 commands::play(),
                 commands::search(),
+                commands::artist(),
+                commands::radio(),
+                commands::favorites(),
+                commands::myplaylists(),
+                commands::playlist(),
                 commands::playnext(),
                 commands::pause(),
                 commands::resume(),
                 commands::seek(),
                 commands::skip(),
                 commands::repeat(),
+                commands::autoplay(),
                 commands::shuffle(),
                 commands::remove(),
                 commands::clear(),
                 commands::stop(),
                 commands::current(),
+                commands::lyrics(),
                 commands::leave(),
                 commands::queue(),
-            ],
+                commands::tidal(),
+            ]),
             prefix_options: poise::PrefixFrameworkOptions {
                 prefix: Some(prefix.clone()),
                 ..Default::default()
@@ -340,16 +402,23 @@ async fn run() -> Result<(), commands::Error> {
                 }
                 tracing::info!(user = %current_user.name, version, "Bot connected");
                 Ok(commands::Data {
-                    session: tokio::sync::Mutex::new(tidal_session),
+                    tidal: tidal_accounts,
                     spool_read_ahead_bytes,
                     collection_track_fetch_concurrency,
                     command_prefix: prefix.clone(),
-                    repeat_modes: std::sync::Arc::new(tokio::sync::Mutex::new(
-                        std::collections::HashMap::new(),
-                    )),
+                    repeat_modes,
+                    default_audio_quality,
+                    audio_qualities,
                     playback_status: std::sync::Arc::new(tokio::sync::Mutex::new(
                         commands::PlaybackStatusState::default(),
                     )),
+                    autoplay: std::sync::Arc::new(tokio::sync::Mutex::new(
+                        std::collections::HashMap::new(),
+                    )),
+                    track_autocomplete: std::sync::Arc::new(tokio::sync::Mutex::new(
+                        commands::TrackAutocompleteState::default(),
+                    )),
+                    queue_persistence,
                 })
             })
         })
@@ -358,7 +427,7 @@ async fn run() -> Result<(), commands::Error> {
     // Create new client
     let mut client = serenity::Client::builder(&token, intents)
         .framework(framework)
-        .register_songbird()
+        .register_songbird_with(voice_manager)
         .await
         .map_err(|error| format!("Error creating client: {error}"))?;
 
@@ -371,16 +440,65 @@ async fn run() -> Result<(), commands::Error> {
         }
     });
 
-    // Handle Ctrl+C to gracefully shut down the client
+    // Periodically snapshot guild queues so a crash loses at most one interval of progress
+    tokio::spawn({
+        let queue_persistence = snapshot_queue_persistence.clone();
+        let repeat_modes = snapshot_repeat_modes.clone();
+        let audio_qualities = snapshot_audio_qualities.clone();
+        let voice_manager = snapshot_voice_manager.clone();
+        async move {
+            let mut interval = tokio::time::interval(queue_state::QUEUE_SNAPSHOT_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(error) = queue_persistence
+                    .save(&voice_manager, &repeat_modes, &audio_qualities)
+                    .await
+                {
+                    tracing::warn!(%error, "Failed to save guild queues");
+                }
+            }
+        }
+    });
+
+    // Save the metadata cache now and then so lookups stay cached across restarts
+    tokio::spawn({
+        let metadata_cache = metadata_cache.clone();
+        async move {
+            let mut interval = tokio::time::interval(metadata_cache::METADATA_CACHE_SAVE_INTERVAL);
+            // The first tick fires at once, before anything was cached
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(error) = metadata_cache.save().await {
+                    tracing::warn!(%error, "Failed to save metadata cache");
+                }
+            }
+        }
+    });
+
+    // Handle Ctrl+C and SIGTERM to gracefully shut down the client
     tokio::spawn(async move {
-        match tokio::signal::ctrl_c().await {
-            Ok(()) => {
-                tracing::info!("Ctrl+C received, shutting down");
+        match shutdown_signal().await {
+            Ok(signal) => {
+                tracing::info!("{signal} received, shutting down");
+                if let Err(error) = snapshot_queue_persistence
+                    .save(
+                        &snapshot_voice_manager,
+                        &snapshot_repeat_modes,
+                        &snapshot_audio_qualities,
+                    )
+                    .await
+                {
+                    tracing::warn!(%error, "Failed to save guild queues before shutdown");
+                }
+                if let Err(error) = metadata_cache.save().await {
+                    tracing::warn!(%error, "Failed to save metadata cache before shutdown");
+                }
                 shard_manager.shutdown_all().await;
                 tracing::info!("Shutdown complete");
             }
             Err(error) => {
-                tracing::error!(%error, "Failed to listen for Ctrl+C");
+                tracing::error!(%error, "Failed to listen for shutdown signals");
             }
         }
     });
@@ -392,3 +510,23 @@ async fn run() -> Result<(), commands::Error> {
 
     Ok(())
 }
+
+/// Waits for Ctrl+C or, on Unix, SIGTERM (sent by `docker stop` and service
+/// managers), returning the name of the signal that arrived.
+async fn shutdown_signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|()| "Ctrl+C"),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map(|()| "Ctrl+C")
+    }
+}
@@ -0,0 +1,435 @@
+use crate::commands::Error;
+use crate::config::MetadataCacheConfig;
+use crate::track::AudioQuality;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+pub const METADATA_CACHE_SAVE_INTERVAL: Duration = Duration::from_secs(300);
+/// Stream URLs are dropped this long before Tidal's expiry, so a track never starts on a URL
+/// that dies mid-download.
+const STREAM_URL_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+struct CacheFile {
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+struct CacheEntry {
+    value: serde_json::Value,
+    stored_at_ms: u64,
+    last_used_ms: u64,
+}
+
+struct StreamUrlEntry {
+    url: String,
+    quality: AudioQuality,
+    expires_at_ms: u64,
+}
+
+/// Track metadata, search results, and collection track ids from Tidal, kept so replays and
+/// queue loops skip the API. Entries older than the TTL are fetched again, and the least
+/// recently used ones are dropped once the cache is full. Stream URLs stay in memory and only
+/// until the URL expires.
+pub struct MetadataCache {
+    enabled: bool,
+    path: Option<PathBuf>,
+    max_entries: usize,
+    ttl: Duration,
+    entries: std::sync::Mutex<HashMap<String, CacheEntry>>,
+    stream_urls: std::sync::Mutex<HashMap<String, StreamUrlEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    dirty: AtomicBool,
+}
+
+impl std::fmt::Debug for MetadataCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataCache")
+            .field("enabled", &self.enabled)
+            .field("path", &self.path)
+            .field("max_entries", &self.max_entries)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for MetadataCache {
+    /// A cache that stores nothing, for sessions that should always ask Tidal.
+    fn default() -> Self {
+        Self::in_memory(false, 0, Duration::ZERO)
+    }
+}
+
+impl MetadataCache {
+    /// Opens the cache saved at the configured path. A missing or unreadable file starts an
+    /// empty cache rather than stopping the bot.
+    pub fn load(config: &MetadataCacheConfig) -> Self {
+        let mut cache = Self::in_memory(config.enabled, config.max_entries, config.ttl());
+        if !config.enabled {
+            return cache;
+        }
+
+        match read_cache_file(&config.path) {
+            Ok(file) => {
+                let now = unix_now_ms();
+                let mut entries = file.entries;
+                entries.retain(|_, entry| !cache.is_expired(entry, now));
+                tracing::info!(
+                    entries = entries.len(),
+                    path = %config.path.display(),
+                    "Loaded metadata cache"
+                );
+                *cache.entries.get_mut().unwrap() = entries;
+                cache.evict_least_recently_used();
+            }
+            Err(error) => {
+                tracing::warn!(%error, "Failed to load metadata cache; starting empty");
+            }
+        }
+        cache.path = Some(config.path.clone());
+        cache
+    }
+
+    fn in_memory(enabled: bool, max_entries: usize, ttl: Duration) -> Self {
+        Self {
+            enabled,
+            path: None,
+            max_entries,
+            ttl,
+            entries: Default::default(),
+            stream_urls: Default::default(),
+            hits: Default::default(),
+            misses: Default::default(),
+            dirty: Default::default(),
+        }
+    }
+
+    pub fn get<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        if !self.enabled {
+            return None;
+        }
+
+        let now = unix_now_ms();
+        let value = {
+            let mut entries = self.entries.lock().unwrap();
+            match entries.get_mut(key) {
+                Some(entry) if !self.is_expired(entry, now) => {
+                    entry.last_used_ms = now;
+                    Some(entry.value.clone())
+                }
+                Some(_) => {
+                    entries.remove(key);
+                    None
+                }
+                None => None,
+            }
+        };
+
+        // An entry saved by an older version may no longer match the type
+        match value.and_then(|value| serde_json::from_value(value).ok()) {
+            Some(value) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn insert<T: serde::Serialize>(&self, key: String, value: &T) {
+        if !self.enabled {
+            return;
+        }
+
+        let value = match serde_json::to_value(value) {
+            Ok(value) => value,
+            Err(error) => {
+                tracing::warn!(key, %error, "Failed to cache Tidal metadata");
+                return;
+            }
+        };
+        let now = unix_now_ms();
+        self.entries.lock().unwrap().insert(
+            key,
+            CacheEntry {
+                value,
+                stored_at_ms: now,
+                last_used_ms: now,
+            },
+        );
+        self.evict_least_recently_used();
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Drops an entry that no longer matches Tidal, such as a playlist the bot just changed.
+    pub fn remove(&self, key: &str) {
+        if self.entries.lock().unwrap().remove(key).is_some() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stream_url(&self, key: &str) -> Option<(String, AudioQuality)> {
+        if !self.enabled {
+            return None;
+        }
+
+        let now = unix_now_ms();
+        let mut stream_urls = self.stream_urls.lock().unwrap();
+        stream_urls.retain(|_, entry| entry.expires_at_ms > now);
+        match stream_urls.get(key) {
+            Some(entry) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some((entry.url.clone(), entry.quality))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Keeps a stream URL until shortly before it expires. URLs that do not say when they
+    /// expire are not cached.
+    pub fn insert_stream_url(&self, key: String, url: &str, quality: AudioQuality) {
+        if !self.enabled {
+            return;
+        }
+        let Some(expires_at) = stream_url_expiry(url) else {
+            return;
+        };
+
+        let expires_at_ms = expires_at
+            .saturating_mul(1000)
+            .saturating_sub(STREAM_URL_EXPIRY_MARGIN.as_millis() as u64)
+            .min(unix_now_ms().saturating_add(self.ttl.as_millis() as u64));
+        self.stream_urls.lock().unwrap().insert(
+            key,
+            StreamUrlEntry {
+                url: url.to_string(),
+                quality,
+                expires_at_ms,
+            },
+        );
+    }
+
+    /// Writes the cache to disk if anything was added since the last save, and logs how often
+    /// lookups were served from it. The file is written on a blocking thread, since a full
+    /// cache takes a while to serialize.
+    pub async fn save(&self) -> Result<(), Error> {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        if hits + misses > 0 {
+            tracing::info!(hits, misses, "Metadata cache lookups");
+        }
+
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let file = CacheFile {
+            entries: self.entries.lock().unwrap().clone(),
+        };
+        tokio::task::spawn_blocking(move || write_cache_file(&path, &file))
+            .await
+            .map_err(Error::from)
+            .flatten()
+            .inspect_err(|_| self.dirty.store(true, Ordering::Relaxed))
+    }
+
+    fn is_expired(&self, entry: &CacheEntry, now_ms: u64) -> bool {
+        now_ms.saturating_sub(entry.stored_at_ms) >= self.ttl.as_millis() as u64
+    }
+
+    /// Drops the least recently used tenth once the cache is over capacity, so a full cache
+    /// does not sort its entries on every insert.
+    fn evict_least_recently_used(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() <= self.max_entries {
+            return;
+        }
+
+        let keep = self.max_entries - self.max_entries / 10;
+        let mut by_last_use = entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used_ms, key.clone()))
+            .collect::<Vec<_>>();
+        by_last_use.sort_unstable();
+        for (_, key) in by_last_use.into_iter().take(entries.len() - keep) {
+            entries.remove(&key);
+        }
+    }
+}
+
+/// The earliest expiry, in Unix seconds, signed into a stream URL or any segment URL of a
+/// manifest. Tidal's CDNs use either an `Expires=` query parameter or an `exp=` token field.
+fn stream_url_expiry(url: &str) -> Option<u64> {
+    let url = url.to_ascii_lowercase();
+    ["expires=", "exp="]
+        .iter()
+        .flat_map(|name| {
+            url.match_indices(name).filter_map(|(index, _)| {
+                let preceded_by_separator = url[..index]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|separator| matches!(separator, '?' | '&' | '~' | '='));
+                let digits = url[index + name.len()..]
+                    .chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect::<String>();
+                preceded_by_separator
+                    .then(|| digits.parse::<u64>().ok())
+                    .flatten()
+            })
+        })
+        .min()
+}
+
+fn unix_now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn read_cache_file(path: &Path) -> Result<CacheFile, Error> {
+    match std::fs::File::open(path) {
+        Ok(file) => Ok(serde_json::from_reader(std::io::BufReader::new(file))?),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(CacheFile::default()),
+        Err(error) => Err(format!("Failed to read {}: {error}", path.display()).into()),
+    }
+}
+
+fn write_cache_file(path: &Path, file: &CacheFile) -> Result<(), Error> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Write to a sibling file first so a crash mid-write never leaves a truncated cache
+    let temp_path = path.with_extension("json.tmp");
+    let temp_file = std::fs::File::create(&temp_path)
+        .map_err(|error| format!("Failed to create {}: {error}", temp_path.display()))?;
+    serde_json::to_writer(std::io::BufWriter::new(temp_file), file)?;
+    std::fs::rename(&temp_path, path)
+        .map_err(|error| format!("Failed to replace {}: {error}", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_config(name: &str) -> MetadataCacheConfig {
+        let path = std::env::temp_dir().join(format!(
+            "tidalcordrs-{}-{name}-metadata-cache.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        MetadataCacheConfig {
+            enabled: true,
+            path,
+            max_entries: 10,
+            ttl_minutes: 60,
+        }
+    }
+
+    #[tokio::test]
+    async fn survives_a_restart_and_counts_hits() {
+        let config = cache_config("restart");
+        let cache = MetadataCache::load(&config);
+        assert_eq!(cache.get::<Vec<String>>("collection:US:albums:1"), None);
+        cache.insert(
+            "collection:US:albums:1".to_string(),
+            &vec!["1001".to_string(), "1002".to_string()],
+        );
+        cache.save().await.unwrap();
+
+        let reloaded = MetadataCache::load(&config);
+        assert_eq!(
+            reloaded.get::<Vec<String>>("collection:US:albums:1"),
+            Some(vec!["1001".to_string(), "1002".to_string()])
+        );
+        assert_eq!(reloaded.hits.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 1);
+
+        std::fs::remove_file(&config.path).unwrap();
+    }
+
+    #[test]
+    fn expires_entries_and_evicts_the_least_recently_used() {
+        let cache = MetadataCache::in_memory(true, 10, Duration::from_secs(60));
+        for index in 0..10 {
+            cache.insert(format!("track:{index}"), &index);
+            cache
+                .entries
+                .lock()
+                .unwrap()
+                .get_mut(&format!("track:{index}"))
+                .unwrap()
+                .last_used_ms = index;
+        }
+        // Reading an entry makes it the most recently used
+        assert_eq!(cache.get::<u64>("track:0"), Some(0));
+        cache.insert("track:10".to_string(), &10);
+
+        let entries = cache.entries.lock().unwrap();
+        assert_eq!(entries.len(), 9);
+        assert!(entries.contains_key("track:0"));
+        assert!(entries.contains_key("track:10"));
+        assert!(!entries.contains_key("track:1"));
+        assert!(!entries.contains_key("track:2"));
+        drop(entries);
+
+        let expired = MetadataCache::in_memory(true, 10, Duration::ZERO);
+        expired.insert("track:1".to_string(), &1);
+        assert_eq!(expired.get::<u32>("track:1"), None);
+    }
+
+    #[test]
+    fn caches_stream_urls_only_while_they_are_valid() {
+        let cache = MetadataCache::in_memory(true, 10, Duration::from_secs(3_600));
+        let now = unix_now_ms() / 1000;
+        let valid = format!(
+            "https://cdn.example/track.flac?Expires={}&Signature=x",
+            now + 600
+        );
+        let expiring = format!("https://cdn.example/track.flac?Expires={}", now + 30);
+
+        cache.insert_stream_url("valid".to_string(), &valid, AudioQuality::High);
+        cache.insert_stream_url("expiring".to_string(), &expiring, AudioQuality::High);
+        cache.insert_stream_url(
+            "unsigned".to_string(),
+            "https://cdn.example/track.flac",
+            AudioQuality::High,
+        );
+
+        assert_eq!(cache.stream_url("valid"), Some((valid, AudioQuality::High)));
+        assert_eq!(cache.stream_url("expiring"), None);
+        assert_eq!(cache.stream_url("unsigned"), None);
+    }
+
+    #[test]
+    fn reads_expiry_from_signed_urls_and_manifests() {
+        assert_eq!(
+            stream_url_expiry("https://a.example/x.mp4?Expires=1700000600&Key-Pair-Id=k"),
+            Some(1_700_000_600)
+        );
+        assert_eq!(
+            stream_url_expiry(
+                "concat:https://b.example/0.mp4?__token__=exp=1700000900~hmac=f|https://b.example/1.mp4?__token__=exp=1700000500~hmac=f"
+            ),
+            Some(1_700_000_500)
+        );
+        assert_eq!(stream_url_expiry("https://c.example/x.flac?noexp=1"), None);
+    }
+}
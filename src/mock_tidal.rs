@@ -0,0 +1,543 @@
+//! A local stand-in for the Tidal endpoints the bot calls, so the session flow can be tested
+//! offline. It covers device login, tokens, sessions, search, tracks and ISRC lookups, playlist
+//! and album items, and stream URLs that serve a small audio file from `tests/fixtures`.
+
+use crate::config::TidalConfig;
+use base64::Engine;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const AUDIO_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/silence.wav");
+const OPENAPI_PAGE_SIZE: usize = 2;
+// Signed into stream URLs the way Tidal's CDN does, far enough out that they stay cacheable
+const STREAM_URL_EXPIRES: u64 = 4_102_444_800;
+pub const SESSION_ID: &str = "mock-session";
+pub const USER_ID: u64 = 42;
+pub const PLAYLIST_ID: &str = "mock-playlist";
+/// Only the legacy endpoint lists this album's tracks, as happens for some real albums.
+pub const LEGACY_ALBUM_ID: &str = "2001";
+/// A playlist mixing a playable track with a region-locked one, one that is not stream-ready,
+/// a video, and a track that no longer exists.
+pub const MIXED_PLAYLIST_ID: &str = "mock-mixed-playlist";
+
+struct MockTrack {
+    id: &'static str,
+    title: &'static str,
+    artist: &'static str,
+    duration: u32,
+    allow_streaming: bool,
+    stream_ready: bool,
+    isrc: &'static str,
+}
+
+const TRACKS: &[MockTrack] = &[
+    MockTrack {
+        id: "1001",
+        title: "First Light",
+        artist: "Mock Artist",
+        duration: 181,
+        allow_streaming: true,
+        stream_ready: true,
+        isrc: "QZMCK2400001",
+    },
+    MockTrack {
+        id: "1002",
+        title: "Second Wind",
+        artist: "Mock Artist",
+        duration: 202,
+        allow_streaming: true,
+        stream_ready: true,
+        isrc: "QZMCK2400002",
+    },
+    MockTrack {
+        id: "1003",
+        title: "Third Time",
+        artist: "Other Artist",
+        duration: 223,
+        allow_streaming: true,
+        stream_ready: true,
+        isrc: "QZMCK2400003",
+    },
+];
+
+/// Tracks that can be looked up by id but are not listed in searches or the default playlist.
+const UNAVAILABLE_TRACKS: &[MockTrack] = &[
+    MockTrack {
+        id: "1004",
+        title: "Locked Away",
+        artist: "Mock Artist",
+        duration: 194,
+        allow_streaming: false,
+        stream_ready: true,
+        isrc: "QZMCK2400004",
+    },
+    MockTrack {
+        id: "1005",
+        title: "Coming Soon",
+        artist: "Mock Artist",
+        duration: 205,
+        allow_streaming: true,
+        stream_ready: false,
+        isrc: "QZMCK2400005",
+    },
+];
+
+impl MockTrack {
+    fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id.parse::<u64>().unwrap(),
+            "title": self.title,
+            "artists": [{"id": 1, "name": self.artist}],
+            "duration": self.duration,
+            "allowStreaming": self.allow_streaming,
+            "streamReady": self.stream_ready,
+            "isrc": self.isrc,
+        })
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    token_generation: u32,
+    requests: Vec<String>,
+}
+
+impl MockState {
+    fn access_token(&self) -> String {
+        format!("access-{}", self.token_generation)
+    }
+}
+
+struct MockRequest {
+    method: String,
+    url: url::Url,
+    authorization: Option<String>,
+    body: String,
+}
+
+impl MockRequest {
+    fn query(&self, name: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+
+    fn form(&self, name: &str) -> Option<String> {
+        url::form_urlencoded::parse(self.body.as_bytes())
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+struct MockResponse {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    fn json(value: serde_json::Value) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: format!(r#"{{"status": "{status}"}}"#).into_bytes(),
+        }
+    }
+
+    /// An error carrying Tidal's `subStatus`, which says why a request was refused.
+    fn error_with_sub_status(status: &'static str, sub_status: u64, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::json!({
+                "subStatus": sub_status,
+                "userMessage": message,
+            })
+            .to_string()
+            .into_bytes(),
+        }
+    }
+}
+
+pub struct MockTidal {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTidal {
+    pub async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState {
+            token_generation: 1,
+            ..Default::default()
+        }));
+
+        let server = Self {
+            base_url: base_url.clone(),
+            state: state.clone(),
+        };
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, base_url.clone(), state.clone()));
+            }
+        });
+
+        server
+    }
+
+    /// Config pointing every endpoint at this server, with the token stored at `path_to_session`.
+    pub fn config(&self, path_to_session: &std::path::Path) -> TidalConfig {
+        TidalConfig {
+            tidal_client_id: "mock-client".to_string(),
+            tidal_client_secret: "mock-secret".to_string(),
+            path_to_session: path_to_session.to_string_lossy().into_owned(),
+            user_agent: "tidalcordrs-tests".to_string(),
+            oauth_device_auth_url: format!("{}/oauth2/device_authorization", self.base_url),
+            oauth_token_url: format!("{}/oauth2/token", self.base_url),
+            sessions_url: format!("{}/v1/sessions", self.base_url),
+            search_url: format!("{}/v1/search", self.base_url),
+            api_base_url: format!("{}/v1", self.base_url),
+            openapi_base_url: format!("{}/v2", self.base_url),
+            audio_quality: crate::track::AudioQuality::High,
+            console_login: false,
+            accounts: Vec::new(),
+        }
+    }
+
+    /// Rejects the access token handed out so far, as Tidal does once a token expires.
+    pub fn expire_access_token(&self) {
+        self.state.lock().unwrap().token_generation += 1;
+    }
+
+    /// Requests seen so far, as `METHOD /path` without the query string.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn request_count(&self, request: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|seen| seen.as_str() == request)
+            .count()
+    }
+}
+
+async fn serve_connection(
+    mut stream: tokio::net::TcpStream,
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+) {
+    let Some(request) = read_request(&mut stream, &base_url).await else {
+        return;
+    };
+    state
+        .lock()
+        .unwrap()
+        .requests
+        .push(format!("{} {}", request.method, request.url.path()));
+
+    let response = route(&request, &base_url, &state).await;
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nETag: \"mock-etag\"\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&response.body).await;
+}
+
+async fn read_request(stream: &mut tokio::net::TcpStream, base_url: &str) -> Option<MockRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse().ok()?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.trim().to_string());
+        }
+    }
+
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    Some(MockRequest {
+        method,
+        url: url::Url::parse(&format!("{base_url}{target}")).ok()?,
+        authorization,
+        body: String::from_utf8_lossy(&buffer[header_end..]).into_owned(),
+    })
+}
+
+async fn route(request: &MockRequest, base_url: &str, state: &Mutex<MockState>) -> MockResponse {
+    let path = request
+        .url
+        .path()
+        .trim_matches('/')
+        .split('/')
+        .collect::<Vec<_>>();
+
+    match (request.method.as_str(), path.as_slice()) {
+        ("POST", ["oauth2", "device_authorization"]) => MockResponse::json(serde_json::json!({
+            "deviceCode": "mock-device",
+            "userCode": "MOCK",
+            "verificationUri": "link.tidal.com",
+            "verificationUriComplete": "link.tidal.com/MOCK",
+            "expiresIn": 300,
+            "interval": 1,
+        })),
+        ("POST", ["oauth2", "token"]) => issue_token(request, state),
+        ("GET", ["media", "silence.wav"]) => match tokio::fs::read(AUDIO_FIXTURE).await {
+            Ok(body) => MockResponse {
+                status: "200 OK",
+                content_type: "audio/wav",
+                body,
+            },
+            Err(_) => MockResponse::error("500 Internal Server Error"),
+        },
+        // The legacy stream endpoint is authorized by the session id rather than a token
+        ("GET", ["v1", "tracks", id, "urlpostpaywall"]) => legacy_stream_url(id, base_url, request),
+        _ if !is_authorized(request, state) => {
+            MockResponse::error_with_sub_status("401 Unauthorized", 11002, "Token has expired")
+        }
+        ("GET", ["v1", "sessions"]) => MockResponse::json(serde_json::json!({
+            "sessionId": SESSION_ID,
+            "userId": USER_ID,
+            "countryCode": "US",
+            "channelId": 1,
+            "partnerId": 1,
+        })),
+        ("GET", ["v1", "search"]) => search(request),
+        ("GET", ["v1", "tracks", id]) => match find_track(id) {
+            Some(track) => MockResponse::json(track.json()),
+            None => MockResponse::error("404 Not Found"),
+        },
+        ("GET", ["v1", "tracks", id, "playbackinfopostpaywall"]) => {
+            playback_info(id, base_url, request)
+        }
+        ("GET", ["v2", "tracks"]) => {
+            let isrc = request.query("filter[isrc]").unwrap_or_default();
+            MockResponse::json(serde_json::json!({
+                "data": TRACKS
+                    .iter()
+                    .chain(UNAVAILABLE_TRACKS)
+                    .filter(|track| track.isrc == isrc)
+                    .map(|track| serde_json::json!({"id": track.id, "type": "tracks"}))
+                    .collect::<Vec<_>>(),
+            }))
+        }
+        ("GET", ["v2", "playlists", PLAYLIST_ID, "relationships", "items"]) => {
+            playlist_items_page(request)
+        }
+        ("GET", ["v1", "playlists", PLAYLIST_ID]) => {
+            MockResponse::json(serde_json::json!({"uuid": PLAYLIST_ID}))
+        }
+        ("POST", ["v1", "playlists", PLAYLIST_ID, "items"]) => {
+            MockResponse::json(serde_json::json!({}))
+        }
+        (
+            "GET",
+            [
+                "v2",
+                "playlists",
+                MIXED_PLAYLIST_ID,
+                "relationships",
+                "items",
+            ],
+        ) => MockResponse::json(serde_json::json!({
+            "data": [
+                {"id": "1001", "type": "tracks"},
+                {"id": "1004", "type": "tracks"},
+                {"id": "1005", "type": "tracks"},
+                {"id": "3001", "type": "videos"},
+                {"id": "9999", "type": "tracks"},
+            ],
+            "links": {"next": null},
+        })),
+        ("GET", ["v1", "albums", LEGACY_ALBUM_ID, "tracks"]) => {
+            MockResponse::json(serde_json::json!({
+                "items": TRACKS.iter().map(MockTrack::json).collect::<Vec<_>>(),
+                "totalNumberOfItems": TRACKS.len(),
+            }))
+        }
+        _ => MockResponse::error("404 Not Found"),
+    }
+}
+
+fn is_authorized(request: &MockRequest, state: &Mutex<MockState>) -> bool {
+    let expected = format!("Bearer {}", state.lock().unwrap().access_token());
+    request.authorization.as_deref() == Some(expected.as_str())
+}
+
+fn issue_token(request: &MockRequest, state: &Mutex<MockState>) -> MockResponse {
+    let refresh_token = match request.form("grant_type").as_deref() {
+        Some("urn:ietf:params:oauth:grant-type:device_code")
+            if request.form("device_code").as_deref() == Some("mock-device") =>
+        {
+            Some("mock-refresh")
+        }
+        Some("refresh_token")
+            if request.form("refresh_token").as_deref() == Some("mock-refresh") =>
+        {
+            None
+        }
+        _ => return MockResponse::error("400 Bad Request"),
+    };
+
+    MockResponse::json(serde_json::json!({
+        "access_token": state.lock().unwrap().access_token(),
+        "refresh_token": refresh_token,
+        "token_type": "Bearer",
+        "expires_in": 3600,
+    }))
+}
+
+fn find_track(id: &str) -> Option<&'static MockTrack> {
+    TRACKS
+        .iter()
+        .chain(UNAVAILABLE_TRACKS)
+        .find(|track| track.id == id)
+}
+
+fn search(request: &MockRequest) -> MockResponse {
+    let query = request.query("query").unwrap_or_default().to_lowercase();
+    let limit = request
+        .query("limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(usize::MAX);
+    let tracks = UNAVAILABLE_TRACKS
+        .iter()
+        .chain(TRACKS)
+        .filter(|track| {
+            query.split_whitespace().all(|word| {
+                track.title.to_lowercase().contains(word)
+                    || track.artist.to_lowercase().contains(word)
+            })
+        })
+        .take(limit)
+        .map(MockTrack::json)
+        .collect::<Vec<_>>();
+
+    MockResponse::json(serde_json::json!({
+        "tracks": {"items": tracks},
+        "artists": {"items": []},
+        "albums": {"items": []},
+        "playlists": {"items": []},
+    }))
+}
+
+/// The mock account has no hi-res plan, which Tidal refuses with a 401 that is not about
+/// the token.
+fn refuse_hi_res(request: &MockRequest) -> Option<MockResponse> {
+    (request.query("audioquality").as_deref() == Some("HI_RES")).then(|| {
+        MockResponse::error_with_sub_status(
+            "401 Unauthorized",
+            4005,
+            "Asset is not ready for playback",
+        )
+    })
+}
+
+fn legacy_stream_url(id: &str, base_url: &str, request: &MockRequest) -> MockResponse {
+    if request.query("sessionId").as_deref() != Some(SESSION_ID) {
+        return MockResponse::error_with_sub_status("401 Unauthorized", 6001, "Invalid session");
+    }
+    if find_track(id).is_none() {
+        return MockResponse::error("404 Not Found");
+    }
+    if let Some(refused) = refuse_hi_res(request) {
+        return refused;
+    }
+
+    MockResponse::json(serde_json::json!({
+        "urls": [format!("{base_url}/media/silence.wav")],
+        "audioQuality": request.query("audioquality"),
+    }))
+}
+
+fn playback_info(id: &str, base_url: &str, request: &MockRequest) -> MockResponse {
+    if find_track(id).is_none() {
+        return MockResponse::error("404 Not Found");
+    }
+    if let Some(refused) = refuse_hi_res(request) {
+        return refused;
+    }
+
+    let manifest = serde_json::json!({
+        "mimeType": "audio/wav",
+        "codecs": "pcm",
+        "encryptionType": "NONE",
+        "urls": [format!("{base_url}/media/silence.wav?Expires={STREAM_URL_EXPIRES}")],
+    });
+
+    MockResponse::json(serde_json::json!({
+        "trackId": id.parse::<u64>().unwrap(),
+        "audioQuality": request.query("audioquality"),
+        "manifestMimeType": "application/vnd.tidal.bts",
+        "manifest": base64::engine::general_purpose::STANDARD.encode(manifest.to_string()),
+    }))
+}
+
+/// Pages through the playlist a couple of items at a time, linking to the next page the way
+/// the v2 API does.
+fn playlist_items_page(request: &MockRequest) -> MockResponse {
+    let offset = request
+        .query("page[cursor]")
+        .and_then(|cursor| cursor.parse().ok())
+        .unwrap_or(0);
+    let end = (offset + OPENAPI_PAGE_SIZE).min(TRACKS.len());
+    let next = (end < TRACKS.len()).then(|| {
+        format!(
+            "/playlists/{PLAYLIST_ID}/relationships/items?countryCode=US&page%5Bcursor%5D={end}"
+        )
+    });
+
+    MockResponse::json(serde_json::json!({
+        "data": TRACKS[offset..end]
+            .iter()
+            .map(|track| serde_json::json!({"id": track.id, "type": "tracks"}))
+            .collect::<Vec<_>>(),
+        "links": {"next": next},
+    }))
+}
@@ -0,0 +1,384 @@
+//! Matches Apple Music, Deezer, SoundCloud, and Bandcamp track links on Tidal using the open
+//! graph tags and title of their pages, which name the track and usually its artist.
+
+use crate::commands::Error;
+use crate::track::FoundTracks;
+use crate::url_handler::{ExternalTrack, ResolveContext, UrlResolver, host_matches};
+use html_escape::decode_html_entities;
+use lol_html::{HtmlRewriter, Settings, element, text};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Site {
+    AppleMusic,
+    Deezer,
+    SoundCloud,
+    Bandcamp,
+}
+
+impl Site {
+    fn from_url(url: &url::Url) -> Option<Self> {
+        [
+            (Self::AppleMusic, &["music.apple.com"][..]),
+            (Self::Deezer, &["deezer.com", "deezer.page.link"][..]),
+            (Self::SoundCloud, &["soundcloud.com"][..]),
+            (Self::Bandcamp, &["bandcamp.com"][..]),
+        ]
+        .into_iter()
+        .find(|(_, domains)| host_matches(url, domains))
+        .map(|(site, _)| site)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::AppleMusic => "Apple Music",
+            Self::Deezer => "Deezer",
+            Self::SoundCloud => "SoundCloud",
+            Self::Bandcamp => "Bandcamp",
+        }
+    }
+}
+
+/// Matches a single track page from one of the sites in [`Site`]. Album and playlist pages do
+/// not list their tracks in their tags, so they resolve to nothing.
+pub struct OpenGraphResolver;
+
+#[serenity::async_trait]
+impl UrlResolver for OpenGraphResolver {
+    fn name(&self) -> &'static str {
+        "Open Graph"
+    }
+
+    fn matches(&self, url: &url::Url) -> bool {
+        Site::from_url(url).is_some()
+    }
+
+    async fn resolve(
+        &self,
+        url: &url::Url,
+        context: &ResolveContext<'_>,
+    ) -> Result<FoundTracks, Error> {
+        let Some(site) = Site::from_url(url) else {
+            return Ok(FoundTracks::default());
+        };
+
+        let client = reqwest::Client::builder()
+            .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:150.0) Gecko/20100101 Firefox/150.0")
+            .build()?;
+        let html = client
+            .get(url.as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let Some(track) = page_track(site, &read_page(&html)?) else {
+            tracing::warn!(%url, site = site.name(), "Link is not a single track page");
+            return Ok(FoundTracks::default());
+        };
+        tracing::info!(
+            %url,
+            site = site.name(),
+            title = %track.title,
+            artist = %track.artist,
+            "Read track from page"
+        );
+        Ok(context.find_external_tracks(vec![track]).await)
+    }
+}
+
+/// The `<meta>` tags, title, and JSON-LD objects of a page.
+#[derive(Debug, Default)]
+pub struct PageTags {
+    meta: HashMap<String, String>,
+    title: Option<String>,
+    structured_data: Vec<serde_json::Value>,
+}
+
+impl PageTags {
+    /// The content of the first `<meta>` tag with this `property` or `name`.
+    pub fn meta(&self, name: &str) -> Option<&str> {
+        self.meta
+            .get(name)
+            .map(|content| content.trim())
+            .filter(|content| !content.is_empty())
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref().map(str::trim)
+    }
+
+    /// Length in seconds, from a `music:duration` tag or else the ISO 8601 duration of an
+    /// `itemprop` tag or JSON-LD object, as schema.org markup gives it.
+    fn duration(&self) -> Option<u32> {
+        if let Some(seconds) = self
+            .meta("music:duration")
+            .and_then(|duration| duration.parse().ok())
+        {
+            return Some(seconds);
+        }
+
+        self.meta("duration")
+            .or_else(|| {
+                self.structured_data
+                    .iter()
+                    .find_map(|data| data.get("duration")?.as_str())
+            })
+            .and_then(parse_iso_duration)
+    }
+}
+
+/// Seconds in an ISO 8601 duration such as `PT3M35S` or `P00H03M07S`.
+fn parse_iso_duration(duration: &str) -> Option<u32> {
+    let mut rest = duration.trim().strip_prefix('P')?;
+    let mut seconds = 0_u32;
+    let mut read_any = false;
+
+    while !rest.is_empty() {
+        rest = rest.strip_prefix('T').unwrap_or(rest);
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (value, after) = rest.split_at(digits);
+        let mut units = after.chars();
+        let scale = match units.next()? {
+            'H' => 3600,
+            'M' => 60,
+            'S' => 1,
+            _ => return None,
+        };
+        let whole = value.split('.').next()?.parse::<u32>().ok()?;
+        seconds = seconds.checked_add(whole.checked_mul(scale)?)?;
+        read_any = true;
+        rest = units.as_str();
+    }
+
+    read_any.then_some(seconds)
+}
+
+pub fn read_page(html: &str) -> Result<PageTags, Error> {
+    let meta = Rc::new(RefCell::new(HashMap::new()));
+    let title = Rc::new(RefCell::new(String::new()));
+    let scripts = Rc::new(RefCell::new(vec![String::new()]));
+    let meta_handle = Rc::clone(&meta);
+    let title_handle = Rc::clone(&title);
+    let scripts_handle = Rc::clone(&scripts);
+
+    let mut rewriter = HtmlRewriter::new(
+        Settings {
+            element_content_handlers: vec![
+                element!("meta[content]", move |el| {
+                    let name = el
+                        .get_attribute("property")
+                        .or_else(|| el.get_attribute("name"))
+                        .or_else(|| el.get_attribute("itemprop"));
+                    if let (Some(name), Some(content)) = (name, el.get_attribute("content")) {
+                        meta_handle
+                            .borrow_mut()
+                            .entry(name.to_ascii_lowercase())
+                            .or_insert_with(|| decode_html_entities(&content).into_owned());
+                    }
+                    Ok(())
+                }),
+                text!("head > title", move |t| {
+                    title_handle.borrow_mut().push_str(t.as_str());
+                    Ok(())
+                }),
+                text!(r#"script[type="application/ld+json"]"#, move |t| {
+                    let mut scripts = scripts_handle.borrow_mut();
+                    if let Some(script) = scripts.last_mut() {
+                        script.push_str(t.as_str());
+                    }
+                    if t.last_in_text_node() {
+                        scripts.push(String::new());
+                    }
+                    Ok(())
+                }),
+            ],
+            ..Settings::default()
+        },
+        |_: &[u8]| {},
+    );
+    rewriter.write(html.as_bytes())?;
+    rewriter.end()?;
+
+    let title = decode_html_entities(&title.take()).into_owned();
+    let structured_data = scripts
+        .take()
+        .iter()
+        .filter_map(|script| serde_json::from_str(script).ok())
+        .collect();
+    Ok(PageTags {
+        meta: meta.take(),
+        title: (!title.trim().is_empty()).then_some(title),
+        structured_data,
+    })
+}
+
+fn page_track(site: Site, page: &PageTags) -> Option<ExternalTrack> {
+    let kind = page.meta("og:type").unwrap_or_default();
+    if kind.contains("album") || kind.contains("playlist") {
+        return None;
+    }
+
+    // Apple prefixes titles with a left-to-right mark
+    let og_title = page.meta("og:title")?.trim_start_matches('\u{200e}');
+
+    let (title, artist) = match site {
+        // `Song - Song by Artist - Apple Music`
+        Site::AppleMusic => og_title
+            .trim_end_matches(" - Apple Music")
+            .split_once(" - Song by ")
+            .map(|(title, artist)| (title.to_string(), artist.to_string()))
+            .unwrap_or_else(|| (og_title.to_string(), String::new())),
+        // `<title>Song - song and lyrics by Artist | Deezer</title>`
+        Site::Deezer => page
+            .title()
+            .and_then(|title| title.split(" | ").next())
+            .and_then(|title| title.split_once(" - song and lyrics by "))
+            .map(|(title, artist)| (title.to_string(), artist.to_string()))
+            .unwrap_or_else(|| (og_title.to_string(), String::new())),
+        // `<title>Stream Song by Artist | Listen online for free on SoundCloud</title>`
+        Site::SoundCloud => {
+            let artist = page
+                .meta("twitter:audio:artist_name")
+                .map(str::to_string)
+                .or_else(|| {
+                    page.title()
+                        .and_then(|title| title.split(" | ").next())
+                        .and_then(|title| title.strip_prefix("Stream "))
+                        .and_then(|title| title.strip_prefix(og_title))
+                        .and_then(|rest| rest.strip_prefix(" by "))
+                        .map(str::to_string)
+                })
+                .unwrap_or_default();
+            (og_title.to_string(), artist)
+        }
+        // `Song, by Artist`, on a page named after the artist
+        Site::Bandcamp => og_title
+            .rsplit_once(", by ")
+            .map(|(title, artist)| (title.to_string(), artist.to_string()))
+            .unwrap_or_else(|| {
+                let artist = page.meta("og:site_name").unwrap_or_default();
+                (og_title.to_string(), artist.to_string())
+            }),
+    };
+
+    ExternalTrack::new(
+        title.trim().to_string(),
+        artist.trim().to_string(),
+        String::new(),
+        None,
+        page.duration(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_track(site: Site, html: &str) -> Option<ExternalTrack> {
+        page_track(site, &read_page(html).unwrap())
+    }
+
+    fn site(url: &str) -> Option<Site> {
+        Site::from_url(&url::Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn matches_supported_sites() {
+        assert_eq!(
+            site("https://music.apple.com/us/album/song/123?i=456"),
+            Some(Site::AppleMusic)
+        );
+        assert_eq!(
+            site("https://www.deezer.com/en/track/3135556"),
+            Some(Site::Deezer)
+        );
+        assert_eq!(
+            site("https://soundcloud.com/artist/song"),
+            Some(Site::SoundCloud)
+        );
+        assert_eq!(
+            site("https://artist.bandcamp.com/track/song"),
+            Some(Site::Bandcamp)
+        );
+        assert_eq!(site("https://www.apple.com/music/"), None);
+        assert_eq!(site("https://notbandcamp.com/track/song"), None);
+    }
+
+    #[test]
+    fn parses_iso_durations() {
+        assert_eq!(parse_iso_duration("PT3M35S"), Some(215));
+        assert_eq!(parse_iso_duration("P00H03M07S"), Some(187));
+        assert_eq!(parse_iso_duration("PT1H0M2.5S"), Some(3602));
+        assert_eq!(parse_iso_duration("P"), None);
+        assert_eq!(parse_iso_duration("3:35"), None);
+    }
+
+    #[test]
+    fn reads_apple_music_song_pages() {
+        let track = fixture_track(
+            Site::AppleMusic,
+            include_str!("../tests/fixtures/apple_music_song.html"),
+        )
+        .unwrap();
+
+        assert_eq!(track.title, "Song Title");
+        assert_eq!(track.artist, "Main Artist");
+        assert_eq!(track.duration, Some(215));
+    }
+
+    #[test]
+    fn reads_deezer_track_pages() {
+        let track = fixture_track(
+            Site::Deezer,
+            include_str!("../tests/fixtures/deezer_track.html"),
+        )
+        .unwrap();
+
+        assert_eq!(track.title, "Song & Title");
+        assert_eq!(track.artist, "Main Artist");
+        assert_eq!(track.duration, Some(201));
+    }
+
+    #[test]
+    fn reads_soundcloud_track_pages() {
+        let track = fixture_track(
+            Site::SoundCloud,
+            include_str!("../tests/fixtures/soundcloud_track.html"),
+        )
+        .unwrap();
+
+        assert_eq!(track.title, "Song Title (Extended Mix)");
+        assert_eq!(track.artist, "Uploader Name");
+        assert_eq!(track.duration, Some(252));
+    }
+
+    #[test]
+    fn reads_bandcamp_track_pages() {
+        let track = fixture_track(
+            Site::Bandcamp,
+            include_str!("../tests/fixtures/bandcamp_track.html"),
+        )
+        .unwrap();
+
+        assert_eq!(track.title, "Song Title");
+        assert_eq!(track.artist, "Band Name");
+        assert_eq!(track.duration, Some(187));
+    }
+
+    #[test]
+    fn skips_album_pages() {
+        assert_eq!(
+            fixture_track(
+                Site::Bandcamp,
+                include_str!("../tests/fixtures/bandcamp_album.html")
+            ),
+            None
+        );
+    }
+}
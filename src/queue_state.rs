@@ -0,0 +1,213 @@
+use crate::commands::{Error, RepeatMode};
+use crate::config::QueuePersistenceConfig;
+use crate::track::{AudioQuality, Track};
+use serenity::model::id::{ChannelId, GuildId};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+pub const QUEUE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(15);
+const TRACK_POSITION_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct QueueSnapshot {
+    pub guilds: Vec<GuildQueueSnapshot>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub struct GuildQueueSnapshot {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub repeat_mode: RepeatMode,
+    #[serde(default)]
+    pub audio_quality: Option<AudioQuality>,
+    pub position_ms: u64,
+    pub tracks: Vec<Track>,
+}
+
+impl GuildQueueSnapshot {
+    pub fn position(&self) -> Duration {
+        Duration::from_millis(self.position_ms)
+    }
+}
+
+#[derive(Debug)]
+pub struct QueuePersistence {
+    enabled: bool,
+    state_path: PathBuf,
+    restore_started: AtomicBool,
+    restored: AtomicBool,
+}
+
+impl QueuePersistence {
+    pub fn new(config: &QueuePersistenceConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            state_path: config.state_path.clone(),
+            restore_started: AtomicBool::new(false),
+            restored: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the saved snapshot the first time it is called, so reconnects do not restore twice.
+    pub fn take_snapshot_for_restore(&self) -> Result<Option<QueueSnapshot>, Error> {
+        if !self.enabled || self.restore_started.swap(true, Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        read_queue_snapshot(&self.state_path).map(Some)
+    }
+
+    pub fn finish_restore(&self) {
+        self.restored.store(true, Ordering::SeqCst);
+    }
+
+    /// Writes the current queues to disk. Skipped until the saved queues have been restored,
+    /// otherwise an early save would overwrite them with an empty snapshot.
+    pub async fn save(
+        &self,
+        manager: &songbird::Songbird,
+        repeat_modes: &tokio::sync::Mutex<HashMap<GuildId, RepeatMode>>,
+        audio_qualities: &tokio::sync::Mutex<HashMap<GuildId, AudioQuality>>,
+    ) -> Result<(), Error> {
+        if !self.enabled || !self.restored.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let snapshot = capture_queue_snapshot(manager, repeat_modes, audio_qualities).await;
+        write_queue_snapshot(&self.state_path, &snapshot)
+    }
+}
+
+async fn capture_queue_snapshot(
+    manager: &songbird::Songbird,
+    repeat_modes: &tokio::sync::Mutex<HashMap<GuildId, RepeatMode>>,
+    audio_qualities: &tokio::sync::Mutex<HashMap<GuildId, AudioQuality>>,
+) -> QueueSnapshot {
+    let calls = manager.iter().collect::<Vec<_>>();
+    let mut guilds = Vec::new();
+
+    for (guild_id, handler_lock) in calls {
+        let guild_id = GuildId::new(guild_id.0.get());
+        let (channel_id, queue) = {
+            let handler = handler_lock.lock().await;
+            let Some(channel_id) = handler.current_channel() else {
+                continue;
+            };
+            (
+                ChannelId::new(channel_id.0.get()),
+                handler.queue().current_queue(),
+            )
+        };
+
+        let Some(current) = queue.first() else {
+            continue;
+        };
+
+        let position = match tokio::time::timeout(TRACK_POSITION_TIMEOUT, current.get_info()).await
+        {
+            Ok(Ok(track_info)) => track_info.position,
+            _ => Duration::ZERO,
+        };
+        let repeat_mode = repeat_modes
+            .lock()
+            .await
+            .get(&guild_id)
+            .copied()
+            .unwrap_or(RepeatMode::Off);
+        let audio_quality = audio_qualities.lock().await.get(&guild_id).copied();
+
+        guilds.push(GuildQueueSnapshot {
+            guild_id,
+            channel_id,
+            repeat_mode,
+            audio_quality,
+            position_ms: position.as_millis() as u64,
+            tracks: queue
+                .iter()
+                .map(|track_handle| track_handle.data::<Track>().as_ref().clone())
+                .collect(),
+        });
+    }
+
+    QueueSnapshot { guilds }
+}
+
+fn read_queue_snapshot(path: &Path) -> Result<QueueSnapshot, Error> {
+    match std::fs::File::open(path) {
+        Ok(file) => Ok(serde_json::from_reader(std::io::BufReader::new(file))?),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(QueueSnapshot::default()),
+        Err(error) => Err(format!("Failed to read {}: {error}", path.display()).into()),
+    }
+}
+
+fn write_queue_snapshot(path: &Path, snapshot: &QueueSnapshot) -> Result<(), Error> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Write to a sibling file first so a crash mid-write never leaves a truncated snapshot
+    let temp_path = path.with_extension("json.tmp");
+    let file = std::fs::File::create(&temp_path)
+        .map_err(|error| format!("Failed to create {}: {error}", temp_path.display()))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), snapshot)?;
+    std::fs::rename(&temp_path, path)
+        .map_err(|error| format!("Failed to replace {}: {error}", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_queue_snapshot_without_served_quality() {
+        let snapshot = QueueSnapshot {
+            guilds: vec![GuildQueueSnapshot {
+                guild_id: GuildId::new(1),
+                channel_id: ChannelId::new(2),
+                repeat_mode: RepeatMode::Queue,
+                audio_quality: Some(AudioQuality::Lossless),
+                position_ms: 42_500,
+                tracks: vec![Track {
+                    id: "123".to_string(),
+                    title: "Song Title".to_string(),
+                    artist: "Main Artist".to_string(),
+                    featured_artists: vec!["Guest Artist".to_string()],
+                    duration: 185,
+                    audio_quality: AudioQuality::High,
+                    isrc: Some("USABC1234567".to_string()),
+                    served_quality: std::sync::Arc::new(std::sync::Mutex::new(Some(
+                        AudioQuality::Low,
+                    ))),
+                }],
+            }],
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        let restored: QueueSnapshot = serde_json::from_str(&json).unwrap();
+        let guild = &restored.guilds[0];
+        assert_eq!(guild.guild_id, GuildId::new(1));
+        assert_eq!(guild.channel_id, ChannelId::new(2));
+        assert_eq!(guild.repeat_mode, RepeatMode::Queue);
+        assert_eq!(guild.audio_quality, Some(AudioQuality::Lossless));
+        assert_eq!(guild.position(), Duration::from_millis(42_500));
+        assert_eq!(guild.tracks[0].id, "123");
+        assert_eq!(guild.tracks[0].audio_quality, AudioQuality::High);
+        assert_eq!(guild.tracks[0].isrc.as_deref(), Some("USABC1234567"));
+        assert_eq!(guild.tracks[0].playback_quality(), AudioQuality::High);
+    }
+
+    #[test]
+    fn missing_snapshot_file_is_empty() {
+        let path = std::env::temp_dir().join("tidalcordrs-missing-queue-state.json");
+        let snapshot = read_queue_snapshot(&path).unwrap();
+
+        assert!(snapshot.guilds.is_empty());
+    }
+}
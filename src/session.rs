@@ -1,5 +1,6 @@
 use crate::commands::Error;
 use crate::config::TidalConfig;
+use crate::metadata_cache::MetadataCache;
 use crate::track;
 
 #[derive(serde::Deserialize, Debug)]
@@ -33,8 +34,7 @@ struct TokenResponse {
     access_token: String,
     refresh_token: Option<String>,
     token_type: String,
-    #[serde(rename = "expires_in")]
-    _expires_in: u64,
+    expires_in: u64,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -42,11 +42,125 @@ struct SearchTracksResponse {
     tracks: TrackItemsResponse,
 }
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, Debug, Default)]
 struct TrackItemsResponse {
     items: Vec<track::TidalTrackResponse>,
 }
 
+impl TrackItemsResponse {
+    fn into_tracks(self, audio_quality: track::AudioQuality) -> Result<Vec<track::Track>, Error> {
+        self.items
+            .iter()
+            .filter(|item| item.is_streamable())
+            .map(|item| track::Track::from_track_response(item, audio_quality))
+            .collect()
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct SearchArtistsResponse {
+    artists: ArtistItemsResponse,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+struct ArtistItemsResponse {
+    items: Vec<track::TidalArtistResponse>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+struct AlbumItemsResponse {
+    items: Vec<track::TidalAlbumResponse>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct FavoriteItemsResponse<T> {
+    items: Vec<FavoriteItem<T>>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct FavoriteItem<T> {
+    item: T,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+struct PlaylistItemsResponse {
+    items: Vec<track::TidalPlaylistResponse>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct SearchCatalogResponse {
+    #[serde(default)]
+    tracks: TrackItemsResponse,
+    #[serde(default)]
+    albums: AlbumItemsResponse,
+    #[serde(default)]
+    playlists: PlaylistItemsResponse,
+    #[serde(default)]
+    artists: ArtistItemsResponse,
+}
+
+impl SearchCatalogResponse {
+    fn into_results(self) -> Result<SearchResults, Error> {
+        Ok(SearchResults {
+            tracks: self
+                .tracks
+                .items
+                .iter()
+                .filter(|item| item.is_streamable())
+                .map(track::TidalTrackResponse::summary)
+                .collect::<Result<_, _>>()?,
+            albums: self
+                .albums
+                .items
+                .iter()
+                .map(track::TidalAlbumResponse::summary)
+                .collect(),
+            playlists: self
+                .playlists
+                .items
+                .iter()
+                .map(track::TidalPlaylistResponse::summary)
+                .collect(),
+            artists: self
+                .artists
+                .items
+                .iter()
+                .map(track::TidalArtistResponse::summary)
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    pub tracks: Vec<track::TrackSummary>,
+    pub albums: Vec<track::AlbumSummary>,
+    pub playlists: Vec<track::PlaylistSummary>,
+    pub artists: Vec<track::ArtistSummary>,
+}
+
+pub const ARTIST_TOP_TRACK_LIMIT: u32 = 10;
+/// Search results scored when matching a track from another service.
+const MATCH_CANDIDATE_LIMIT: u32 = 5;
+/// Search results to look through for a streamable track when a query should queue one.
+const FIRST_TRACK_SEARCH_LIMIT: u32 = 5;
+const USER_COLLECTION_LIMIT: &str = "100";
+pub const TRACK_RADIO_LIMIT: u32 = 25;
+const ARTIST_ALBUM_LIMIT: u32 = 50;
+const PLAYLIST_ADD_CHUNK_SIZE: usize = 50;
+const TOKEN_REFRESH_MARGIN_SECS: u64 = 300;
+const TOKEN_REFRESH_RETRY: std::time::Duration = std::time::Duration::from_secs(60);
+const TOKEN_REFRESH_REJECTED_RETRY: std::time::Duration = std::time::Duration::from_secs(1800);
+const NOT_LOGGED_IN: &str =
+    "The bot is not logged in to Tidal. The bot owner can log in with `/tidal login`.";
+
+/// The track ids of a playlist or album, and how many of its items were videos.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+struct CollectionItems {
+    track_ids: Vec<String>,
+    video_count: usize,
+}
+
 #[derive(serde::Deserialize, Debug)]
 struct CollectionRelationshipsResponse {
     #[serde(default)]
@@ -93,43 +207,43 @@ struct LegacyCollectionTracksPage {
 
 #[derive(Clone)]
 struct TrackFetchContext {
-    client: reqwest::Client,
+    http: crate::http::TidalHttp,
     user_agent: String,
-    token_type: String,
-    access_token: String,
-    session_id: String,
-    country_code: String,
+    api_base_url: String,
+    credentials: std::sync::Arc<Credentials>,
+    audio_quality: track::AudioQuality,
+    cache: std::sync::Arc<MetadataCache>,
 }
 
 impl TrackFetchContext {
     async fn get_track_response(&self, track_id: &str) -> Result<track::TidalTrackResponse, Error> {
-        let url = format!("https://api.tidal.com/v1/tracks/{}", track_id);
+        cached(
+            &self.cache,
+            track_cache_key(&self.credentials.country_code, track_id),
+            self.request_track_response(track_id),
+        )
+        .await
+    }
+
+    async fn request_track_response(&self, track_id: &str) -> Result<serde_json::Value, Error> {
+        let url = crate::http::endpoint(&self.api_base_url, &format!("tracks/{}", track_id));
         let params = [
-            ("sessionId", self.session_id.as_str()),
-            ("countryCode", self.country_code.as_str()),
+            ("sessionId", self.credentials.session_id.as_str()),
+            ("countryCode", self.credentials.country_code.as_str()),
         ];
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("User-Agent", self.user_agent.parse()?);
-        headers.insert(
-            "Authorization",
-            format!("{} {}", self.token_type, self.access_token).parse()?,
-        );
-        headers.insert("Accept", "application/json".parse()?);
-
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .query(&params)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(response_error("Track lookup failed", response).await);
-        }
-
-        Ok(response.json().await?)
+        let request = self
+            .http
+            .authorized(
+                reqwest::Method::GET,
+                &url,
+                &self.user_agent,
+                &self.credentials.authorization(),
+            )?
+            .header("Accept", "application/json")
+            .query(&params);
+
+        Ok(self.http.send(request).await?.json().await?)
     }
 
     async fn fetch_track(&self, track_id: &str) -> Result<track::Track, FetchTrackError> {
@@ -138,26 +252,35 @@ impl TrackFetchContext {
             .await
             .map_err(FetchTrackError::TrackResponse)?;
 
-        track::Track::from_track_response(
-            &self.client,
-            &self.session_id,
-            &self.country_code,
-            &track_response,
-        )
-        .await
-        .map_err(FetchTrackError::Stream)
+        if let Some(reason) = track_response.unavailable_reason() {
+            return Err(FetchTrackError::Unavailable(reason));
+        }
+
+        track::Track::from_track_response(&track_response, self.audio_quality)
+            .map_err(FetchTrackError::Metadata)
     }
 }
 
 enum FetchTrackError {
     TrackResponse(Error),
-    Stream(Error),
+    Metadata(Error),
+    Unavailable(track::SkipReason),
 }
 
 impl std::fmt::Display for FetchTrackError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::TrackResponse(error) | Self::Stream(error) => write!(f, "{error}"),
+            Self::TrackResponse(error) | Self::Metadata(error) => write!(f, "{error}"),
+            Self::Unavailable(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl FetchTrackError {
+    fn skip_reason(&self) -> track::SkipReason {
+        match self {
+            Self::Unavailable(reason) => *reason,
+            Self::TrackResponse(_) | Self::Metadata(_) => track::SkipReason::StreamError,
         }
     }
 }
@@ -173,26 +296,125 @@ struct Token {
     access_token: String,
     refresh_token: String,
     token_type: String,
+    /// Unix time in seconds. Missing from token files written by older versions.
+    #[serde(default)]
+    expires_at: Option<u64>,
 }
 
 impl Token {
-    pub fn new(access_token: String, refresh_token: String, token_type: String) -> Self {
+    pub fn new(
+        access_token: String,
+        refresh_token: String,
+        token_type: String,
+        expires_at: Option<u64>,
+    ) -> Self {
         Token {
             access_token,
             refresh_token,
             token_type,
+            expires_at,
         }
     }
 }
 
-async fn response_error(message: &str, response: reqwest::Response) -> Error {
-    let status = response.status();
-    let body = response
-        .text()
-        .await
-        .unwrap_or_else(|error| format!("failed to read response body: {error}"));
+fn track_cache_key(country_code: &str, track_id: &str) -> String {
+    format!("track:{country_code}:{track_id}")
+}
+
+fn collection_cache_key(country_code: &str, collection_type: &str, collection_id: &str) -> String {
+    format!("collection:{country_code}:{collection_type}:{collection_id}")
+}
+
+/// Returns the cached response stored under `key`, or awaits `fetch` and caches its response.
+async fn cached<T, Fut>(cache: &MetadataCache, key: String, fetch: Fut) -> Result<T, Error>
+where
+    T: serde::de::DeserializeOwned,
+    Fut: std::future::Future<Output = Result<serde_json::Value, Error>>,
+{
+    if let Some(response) = cache.get(&key) {
+        return Ok(response);
+    }
+
+    let value = fetch.await?;
+    let response = serde_json::from_value(value.clone())?;
+    cache.insert(key, &value);
+    Ok(response)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// How long to wait before refreshing a token that expires at `expires_at`. A token with an
+/// unknown expiry is refreshed straight away so its expiry becomes known.
+fn token_refresh_delay(expires_at: Option<u64>, now: u64) -> std::time::Duration {
+    let Some(expires_at) = expires_at else {
+        return std::time::Duration::ZERO;
+    };
+
+    std::time::Duration::from_secs(expires_at.saturating_sub(now + TOKEN_REFRESH_MARGIN_SECS))
+}
+
+/// Tidal answers 400 (`invalid_grant`) or 401 once a refresh token is revoked or expired.
+fn is_refresh_token_rejected(error: &Error) -> bool {
+    error
+        .downcast_ref::<crate::http::TidalHttpError>()
+        .and_then(crate::http::TidalHttpError::status)
+        .is_some_and(|status| {
+            status == reqwest::StatusCode::BAD_REQUEST
+                || status == reqwest::StatusCode::UNAUTHORIZED
+                || status == reqwest::StatusCode::FORBIDDEN
+        })
+}
 
-    format!("{message}: status {status}, body: {body}").into()
+/// Refreshes the access token shortly before it expires, so commands never have to recover
+/// from an expired token first.
+pub async fn keep_token_fresh(session: std::sync::Arc<Session>, account: String) {
+    loop {
+        let credentials = session.credentials();
+        if !credentials.is_logged_in() {
+            tokio::time::sleep(TOKEN_REFRESH_RETRY).await;
+            continue;
+        }
+        tokio::time::sleep(token_refresh_delay(credentials.expires_at, unix_now())).await;
+
+        // Skipped when a command already refreshed the token while this task slept
+        match session.refresh_stale_credentials(&credentials).await {
+            Ok((_, false)) => {}
+            Ok((credentials, true)) => {
+                tracing::info!(
+                    %account,
+                    expires_at = ?credentials.expires_at,
+                    "Refreshed Tidal access token ahead of expiry"
+                );
+            }
+            Err(error) if is_refresh_token_rejected(&error) => {
+                tracing::error!(
+                    %account,
+                    %error,
+                    "Tidal rejected the refresh token. Log in again with /tidal login"
+                );
+                tokio::time::sleep(TOKEN_REFRESH_REJECTED_RETRY).await;
+            }
+            Err(error) => {
+                tracing::warn!(%account, %error, "Failed to refresh Tidal access token; will retry");
+                tokio::time::sleep(TOKEN_REFRESH_RETRY).await;
+            }
+        }
+    }
+}
+
+/// Newest first, capped to a single page.
+fn user_collection_params() -> [(&'static str, &'static str); 4] {
+    [
+        ("limit", USER_COLLECTION_LIMIT),
+        ("offset", "0"),
+        ("order", "DATE"),
+        ("orderDirection", "DESC"),
+    ]
 }
 
 #[cfg(unix)]
@@ -218,42 +440,117 @@ fn create_token_file(path: &str) -> std::io::Result<std::fs::File> {
         .open(path)
 }
 
+/// The access token and the account details it unlocks. Requests read a shared snapshot, so a
+/// refresh never has to wait for lookups that are still in flight.
+#[derive(Debug, Default)]
+struct Credentials {
+    access_token: String,
+    refresh_token: String,
+    token_type: String,
+    expires_at: Option<u64>,
+    session_id: String,
+    country_code: String,
+    user_id: u64,
+}
+
+impl Credentials {
+    fn is_logged_in(&self) -> bool {
+        !self.access_token.is_empty()
+    }
+
+    fn authorization(&self) -> String {
+        format!("{} {}", self.token_type, self.access_token)
+    }
+
+    fn token(&self) -> Token {
+        Token::new(
+            self.access_token.clone(),
+            self.refresh_token.clone(),
+            self.token_type.clone(),
+            self.expires_at,
+        )
+    }
+}
+
+/// What `/tidal status` reports about the logged in account.
+#[derive(Debug)]
+pub struct SessionStatus {
+    pub user_id: u64,
+    pub country_code: String,
+    /// Time left until the access token expires, when Tidal reported its lifetime.
+    pub expires_in: Option<std::time::Duration>,
+}
+
+/// A device login waiting for the user to open the verification link. Only one can be pending
+/// at a time; it stays reserved until this is dropped.
+pub struct DeviceLogin {
+    response: LoginResponse,
+    _pending: tokio::sync::OwnedMutexGuard<()>,
+}
+
+impl DeviceLogin {
+    pub fn verification_url(&self) -> String {
+        format!("https://{}", self.response.verification_uri_complete)
+    }
+
+    pub fn expires_in(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.response.expires_in)
+    }
+}
+
 #[derive(Debug)]
 pub struct Session {
-    pub client: reqwest::Client,
+    pub http: crate::http::TidalHttp,
     config: TidalConfig,
-    pub access_token: String,
-    refresh_token: String,
-    pub token_type: String,
-    pub session_id: String,
-    pub country_code: String,
-    pub user_id: u64,
+    credentials: std::sync::RwLock<std::sync::Arc<Credentials>>,
+    /// Held while refreshing the token, so concurrent rejections trigger a single refresh.
+    refresh_lock: tokio::sync::Mutex<()>,
+    login_lock: std::sync::Arc<tokio::sync::Mutex<()>>,
+    /// Set when the last token refresh failed, so an account pool can route around this account.
+    refresh_failed: std::sync::atomic::AtomicBool,
+    cache: std::sync::Arc<MetadataCache>,
 }
 
 impl Session {
-    pub async fn new(config: TidalConfig) -> Result<Self, Error> {
-        let mut session = Session {
-            client: reqwest::Client::new(),
-            config,
-            access_token: String::new(),
-            refresh_token: String::new(),
-            token_type: String::new(),
-            session_id: String::new(),
-            country_code: String::new(),
-            user_id: 0,
-        };
+    pub async fn new(
+        config: TidalConfig,
+        cache: std::sync::Arc<MetadataCache>,
+    ) -> Result<Self, Error> {
+        let session = Self::logged_out(config, cache);
 
         session.start().await?;
 
         Ok(session)
     }
 
-    async fn start(&mut self) -> Result<(), Error> {
+    /// A session without an account, which fails every lookup until someone logs it in.
+    pub fn logged_out(config: TidalConfig, cache: std::sync::Arc<MetadataCache>) -> Self {
+        Session {
+            http: crate::http::TidalHttp::new(),
+            config,
+            credentials: Default::default(),
+            refresh_lock: Default::default(),
+            login_lock: Default::default(),
+            refresh_failed: Default::default(),
+            cache,
+        }
+    }
+
+    /// Whether the session is logged in and its token is still being refreshed.
+    pub fn is_healthy(&self) -> bool {
+        self.credentials().is_logged_in()
+            && !self
+                .refresh_failed
+                .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    async fn start(&self) -> Result<(), Error> {
         // Attempt to load the token from the file
-        if let Ok(()) = self.load_token_from_file().await {
+        if let Ok(token) = self.load_token_from_file() {
             tracing::info!("Token loaded from file successfully");
-            match self.set_session_response().await {
-                Ok(()) => {
+            match self.session_credentials(&token).await {
+                Ok(credentials) => {
+                    self.set_credentials(credentials);
                     tracing::info!("Session started successfully");
                 }
                 Err(e) => {
@@ -261,11 +558,12 @@ impl Session {
                     tracing::info!("Attempting to refresh token");
 
                     // If setting the session response fails, try to refresh the token
-                    self.refresh_token().await?;
+                    let token = self.exchange_refresh_token(&token.refresh_token).await?;
 
                     // Retry setting the session response after refreshing the token
-                    match self.set_session_response().await {
-                        Ok(()) => {
+                    match self.session_credentials(&token).await {
+                        Ok(credentials) => {
+                            self.store_credentials(credentials)?;
                             tracing::info!(
                                 "Session response set successfully after refreshing token"
                             );
@@ -285,18 +583,39 @@ impl Session {
                 }
             }
             return Ok(());
-        } else {
-            tracing::info!("No token found, starting device authorization flow");
         }
 
-        // If no token, then start the login process
-        self.login().await?;
+        // Stay logged out rather than block startup, so the owner can log in from Discord
+        tracing::warn!(
+            path = %self.config.path_to_session,
+            "No Tidal token found; run /tidal login as the bot owner to link an account"
+        );
+
+        Ok(())
+    }
+
+    /// Logs in through the device authorization flow, printing the verification link to the
+    /// console instead of sending it over Discord.
+    pub async fn console_login(&self) -> Result<(), Error> {
+        let device_login = self.start_device_login().await?;
+
+        let verification_url = device_login.verification_url();
+        tracing::info!(%verification_url, "Please authorize Tidal device login");
+
+        self.finish_device_login(device_login).await?;
         tracing::info!("Login successful, session started");
 
         Ok(())
     }
 
-    async fn login(&mut self) -> Result<(), Error> {
+    /// Asks Tidal for a device code. The login completes once the user opens the verification
+    /// link and [`Session::finish_device_login`] sees the approval.
+    pub async fn start_device_login(&self) -> Result<DeviceLogin, Error> {
+        let pending = self
+            .login_lock
+            .clone()
+            .try_lock_owned()
+            .map_err(|_| "A Tidal login is already waiting for approval")?;
         let auth_url = self.config.oauth_device_auth_url.clone();
 
         let mut headers = reqwest::header::HeaderMap::new();
@@ -308,25 +627,21 @@ impl Session {
         params.insert("scope", "r_usr w_usr w_sub".to_string());
 
         let response = self
-            .client
-            .post(auth_url)
-            .headers(headers)
-            .form(&params)
-            .send()
-            .await?
-            .error_for_status()?;
-
-        let login_response: LoginResponse = response.json().await?;
-
-        let verification_url = format!("https://{}", login_response.verification_uri_complete);
-        tracing::info!(%verification_url, "Please authorize Tidal device login");
-
-        self.create_token(login_response).await?;
+            .http
+            .send(self.http.post(&auth_url).headers(headers).form(&params))
+            .await?;
 
-        Ok(())
+        Ok(DeviceLogin {
+            response: response.json().await?,
+            _pending: pending,
+        })
     }
 
-    async fn create_token(&mut self, login_response: LoginResponse) -> Result<(), Error> {
+    /// Waits for the user to approve the device login, then switches every request over to the
+    /// new account and saves its token.
+    pub async fn finish_device_login(&self, device_login: DeviceLogin) -> Result<(), Error> {
+        let login_response = &device_login.response;
+
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert("User-Agent", self.config.user_agent.parse()?);
         headers.insert("Content-Type", "application/x-www-form-urlencoded".parse()?);
@@ -345,8 +660,9 @@ impl Session {
         let mut counter = 0;
 
         while counter < login_response.expires_in {
+            // Polled directly: Tidal answers 400 until the user approves the device
             let response = self
-                .client
+                .http
                 .post(&self.config.oauth_token_url)
                 .headers(headers.clone())
                 .form(&params)
@@ -358,20 +674,20 @@ impl Session {
                     if resp.status().is_success() {
                         let token_response: TokenResponse = resp.json().await?;
 
-                        self.access_token = token_response.access_token;
-                        self.refresh_token = token_response
-                            .refresh_token
-                            .ok_or("Token response did not include a refresh token")?;
-                        self.token_type = token_response.token_type;
-
-                        self.set_session_response().await?;
-
                         let token = Token::new(
-                            self.access_token.clone(),
-                            self.refresh_token.clone(),
-                            self.token_type.clone(),
+                            token_response.access_token,
+                            token_response
+                                .refresh_token
+                                .ok_or("Token response did not include a refresh token")?,
+                            token_response.token_type,
+                            Some(unix_now() + token_response.expires_in),
                         );
-                        self.save_token_to_file(token)?;
+
+                        let credentials = self.session_credentials(&token).await?;
+                        let _refreshing = self.refresh_lock.lock().await;
+                        self.store_credentials(credentials)?;
+                        self.refresh_failed
+                            .store(false, std::sync::atomic::Ordering::Relaxed);
 
                         tracing::info!("Token created successfully");
                         return Ok(());
@@ -389,95 +705,154 @@ impl Session {
         Err("Failed to verify login within the allowed time".into())
     }
 
-    pub async fn refresh_token(&mut self) -> Result<(), Error> {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("User-Agent", self.config.user_agent.parse()?);
-        headers.insert("Content-Type", "application/x-www-form-urlencoded".parse()?);
+    /// The credentials requests should currently use.
+    fn credentials(&self) -> std::sync::Arc<Credentials> {
+        self.credentials.read().unwrap().clone()
+    }
 
-        let mut params = std::collections::HashMap::new();
-        params.insert("client_id", self.config.tidal_client_id.clone());
-        params.insert("client_secret", self.config.tidal_client_secret.clone());
-        params.insert("refresh_token", self.refresh_token.clone());
-        params.insert("grant_type", "refresh_token".to_string());
+    fn set_credentials(&self, credentials: Credentials) -> std::sync::Arc<Credentials> {
+        let credentials = std::sync::Arc::new(credentials);
+        *self.credentials.write().unwrap() = credentials.clone();
+        credentials
+    }
 
-        let response = self
-            .client
-            .post(&self.config.oauth_token_url)
-            .headers(headers)
-            .form(&params)
-            .send()
-            .await;
+    fn store_credentials(
+        &self,
+        credentials: Credentials,
+    ) -> Result<std::sync::Arc<Credentials>, Error> {
+        self.save_token_to_file(&credentials.token())?;
+        Ok(self.set_credentials(credentials))
+    }
+
+    /// `None` while the bot is logged out.
+    pub fn status(&self) -> Option<SessionStatus> {
+        let credentials = self.credentials();
+        if !credentials.is_logged_in() {
+            return None;
+        }
 
-        match response {
-            Ok(resp) => {
-                if resp.status().is_success() {
-                    let token_response: TokenResponse = resp.json().await?;
+        Some(SessionStatus {
+            user_id: credentials.user_id,
+            country_code: credentials.country_code.clone(),
+            expires_in: credentials.expires_at.map(|expires_at| {
+                std::time::Duration::from_secs(expires_at.saturating_sub(unix_now()))
+            }),
+        })
+    }
 
-                    self.access_token = token_response.access_token;
-                    if let Some(refresh_token) = token_response.refresh_token {
-                        self.refresh_token = refresh_token;
-                    }
-                    self.token_type = token_response.token_type;
+    /// Forgets the current account and deletes its saved token. Lookups fail until the next login.
+    pub async fn logout(&self) -> Result<(), Error> {
+        let _refreshing = self.refresh_lock.lock().await;
+        self.set_credentials(Credentials::default());
 
-                    self.set_session_response().await?;
+        match std::fs::remove_file(&self.config.path_to_session) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
 
-                    let token = Token::new(
-                        self.access_token.clone(),
-                        self.refresh_token.clone(),
-                        self.token_type.clone(),
-                    );
+    /// Replaces `stale` with freshly refreshed credentials. When another task already refreshed
+    /// them while this one waited for the lock, its credentials are reused instead.
+    async fn refresh_credentials(
+        &self,
+        stale: &std::sync::Arc<Credentials>,
+    ) -> Result<std::sync::Arc<Credentials>, Error> {
+        self.refresh_stale_credentials(stale)
+            .await
+            .map(|(credentials, _)| credentials)
+    }
 
-                    self.save_token_to_file(token)?;
+    /// Like `refresh_credentials`, but also says whether this call did the refresh.
+    async fn refresh_stale_credentials(
+        &self,
+        stale: &std::sync::Arc<Credentials>,
+    ) -> Result<(std::sync::Arc<Credentials>, bool), Error> {
+        let _refreshing = self.refresh_lock.lock().await;
 
-                    Ok(())
-                } else {
-                    Err(response_error("Failed to refresh token", resp).await)
-                }
-            }
-            Err(e) => Err(format!("Error refreshing token: {}", e).into()),
+        let current = self.credentials();
+        if !current.is_logged_in() {
+            return Err(NOT_LOGGED_IN.into());
         }
+        if !std::sync::Arc::ptr_eq(&current, stale) {
+            return Ok((current, false));
+        }
+
+        let refreshed = match self.exchange_refresh_token(&current.refresh_token).await {
+            Ok(token) => self.session_credentials(&token).await,
+            Err(error) => Err(error),
+        }
+        .and_then(|credentials| self.store_credentials(credentials));
+
+        self.refresh_failed
+            .store(refreshed.is_err(), std::sync::atomic::Ordering::Relaxed);
+        refreshed.map(|credentials| (credentials, true))
     }
 
-    async fn set_session_response(&mut self) -> Result<(), Error> {
+    async fn exchange_refresh_token(&self, refresh_token: &str) -> Result<Token, Error> {
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert("User-Agent", self.config.user_agent.parse()?);
-        headers.insert(
-            "Authorization",
-            format!("{} {}", self.token_type, self.access_token).parse()?,
-        );
+        headers.insert("Content-Type", "application/x-www-form-urlencoded".parse()?);
 
-        let response = self
-            .client
-            .get(&self.config.sessions_url)
-            .headers(headers)
-            .send()
-            .await?;
+        let mut params = std::collections::HashMap::new();
+        params.insert("client_id", self.config.tidal_client_id.clone());
+        params.insert("client_secret", self.config.tidal_client_secret.clone());
+        params.insert("refresh_token", refresh_token.to_string());
+        params.insert("grant_type", "refresh_token".to_string());
 
-        (self.session_id, self.country_code, self.user_id) = {
-            let session_response: SessionResponse = response.json().await?;
-            (
-                session_response.session_id,
-                session_response.country_code,
-                session_response.user_id,
+        let response = self
+            .http
+            .send(
+                self.http
+                    .post(&self.config.oauth_token_url)
+                    .headers(headers)
+                    .form(&params),
             )
-        };
+            .await?;
+        let token_response: TokenResponse = response.json().await?;
+
+        Ok(Token::new(
+            token_response.access_token,
+            token_response
+                .refresh_token
+                .unwrap_or_else(|| refresh_token.to_string()),
+            token_response.token_type,
+            Some(unix_now() + token_response.expires_in),
+        ))
+    }
 
-        Ok(())
+    async fn session_credentials(&self, token: &Token) -> Result<Credentials, Error> {
+        let request = self.http.authorized(
+            reqwest::Method::GET,
+            &self.config.sessions_url,
+            &self.config.user_agent,
+            &format!("{} {}", token.token_type, token.access_token),
+        )?;
+        let session_response: SessionResponse = self.http.send(request).await?.json().await?;
+
+        Ok(Credentials {
+            access_token: token.access_token.clone(),
+            refresh_token: token.refresh_token.clone(),
+            token_type: token.token_type.clone(),
+            expires_at: token.expires_at,
+            session_id: session_response.session_id,
+            country_code: session_response.country_code,
+            user_id: session_response.user_id,
+        })
     }
 
-    fn save_token_to_file(&self, token: Token) -> Result<(), Error> {
+    fn save_token_to_file(&self, token: &Token) -> Result<(), Error> {
         if let Some(parent) = std::path::Path::new(&self.config.path_to_session).parent() {
             std::fs::create_dir_all(parent)?;
         }
 
         let f = create_token_file(&self.config.path_to_session)?;
         let writer = std::io::BufWriter::new(f);
-        serde_json::to_writer_pretty(writer, &token)?;
+        serde_json::to_writer_pretty(writer, token)?;
 
         Ok(())
     }
 
-    async fn load_token_from_file(&mut self) -> Result<(), Error> {
+    fn load_token_from_file(&self) -> Result<Token, Error> {
         if !std::path::Path::new(&self.config.path_to_session).exists() {
             return Err("Session file does not exist".into());
         }
@@ -485,26 +860,44 @@ impl Session {
         let f = std::fs::File::open(&self.config.path_to_session)?;
         let reader = std::io::BufReader::new(f);
 
-        let token: Token = serde_json::from_reader(reader)?;
+        Ok(serde_json::from_reader(reader)?)
+    }
 
-        self.access_token = token.access_token.clone();
-        self.refresh_token = token.refresh_token.clone();
-        self.token_type = token.token_type.clone();
+    /// Runs `request` with the current credentials. If Tidal rejects the access token, the
+    /// token is refreshed and the request retried once with the new credentials.
+    async fn with_credentials<T, F, Fut>(&self, request: F) -> Result<T, Error>
+    where
+        F: Fn(std::sync::Arc<Credentials>) -> Fut,
+        Fut: std::future::Future<Output = Result<T, Error>>,
+    {
+        let credentials = self.credentials();
+        if !credentials.is_logged_in() {
+            return Err(NOT_LOGGED_IN.into());
+        }
 
-        Ok(())
+        let result = request(credentials.clone()).await;
+
+        if !result.as_ref().is_err_and(crate::http::is_auth_error) {
+            return result;
+        }
+
+        request(self.refresh_credentials(&credentials).await?).await
     }
 
-    fn track_fetch_context(&self) -> TrackFetchContext {
+    fn track_fetch_context(
+        &self,
+        credentials: std::sync::Arc<Credentials>,
+        audio_quality: track::AudioQuality,
+    ) -> TrackFetchContext {
         TrackFetchContext {
-            client: self.client.clone(),
+            http: self.http.clone(),
             user_agent: self.config.user_agent.clone(),
-            token_type: self.token_type.clone(),
-            access_token: self.access_token.clone(),
-            session_id: self.session_id.clone(),
-            country_code: self.country_code.clone(),
+            api_base_url: self.config.api_base_url.clone(),
+            credentials,
+            audio_quality,
+            cache: self.cache.clone(),
         }
     }
-
     async fn fetch_tracks_bounded(
         context: TrackFetchContext,
         ids: Vec<(usize, String)>,
@@ -544,35 +937,45 @@ impl Session {
     }
 
     async fn find_collection_tracks_by_ids(
-        &mut self,
+        &self,
         ids: Vec<String>,
         concurrency: usize,
-    ) -> Vec<track::Track> {
+        audio_quality: track::AudioQuality,
+    ) -> track::FoundTracks {
+        let mut skipped = track::SkippedTracks::default();
         let mut tracks = Vec::new();
         tracks.resize_with(ids.len(), || None);
 
+        let credentials = self.credentials();
         let indexed_ids = ids.into_iter().enumerate().collect::<Vec<_>>();
-        let first_pass =
-            Self::fetch_tracks_bounded(self.track_fetch_context(), indexed_ids, concurrency).await;
+        let first_pass = Self::fetch_tracks_bounded(
+            self.track_fetch_context(credentials.clone(), audio_quality),
+            indexed_ids,
+            concurrency,
+        )
+        .await;
         let mut retry_ids = Vec::new();
 
         for outcome in first_pass {
             match outcome.result {
                 Ok(track) => tracks[outcome.index] = Some(track),
-                Err(FetchTrackError::TrackResponse(_)) => {
+                Err(FetchTrackError::TrackResponse(error))
+                    if crate::http::is_auth_error(&error) =>
+                {
                     retry_ids.push((outcome.index, outcome.id));
                 }
-                Err(FetchTrackError::Stream(error)) => {
+                Err(error) => {
                     tracing::warn!(track_id = %outcome.id, %error, "Skipping collection track");
+                    skipped.record(error.skip_reason());
                 }
             }
         }
 
         if !retry_ids.is_empty() {
-            match self.refresh_token().await {
-                Ok(()) => {
+            match self.refresh_credentials(&credentials).await {
+                Ok(credentials) => {
                     let second_pass = Self::fetch_tracks_bounded(
-                        self.track_fetch_context(),
+                        self.track_fetch_context(credentials, audio_quality),
                         retry_ids,
                         concurrency,
                     )
@@ -587,6 +990,7 @@ impl Session {
                                     %error,
                                     "Skipping collection track"
                                 );
+                                skipped.record(error.skip_reason());
                             }
                         }
                     }
@@ -594,199 +998,224 @@ impl Session {
                 Err(error) => {
                     for (_, id) in retry_ids {
                         tracing::warn!(track_id = %id, %error, "Skipping collection track");
+                        skipped.record(track::SkipReason::StreamError);
                     }
                 }
             }
         }
 
-        tracks.into_iter().flatten().collect()
+        track::FoundTracks {
+            tracks: tracks.into_iter().flatten().collect(),
+            skipped,
+            ..Default::default()
+        }
     }
 
-    async fn search_tracks(&self, query: &str, limit: u32) -> Result<SearchTracksResponse, Error> {
-        let limit = limit.to_string();
-
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("User-Agent", self.config.user_agent.parse()?);
-        headers.insert(
-            "Authorization",
-            format!("{} {}", self.token_type, self.access_token).parse()?,
+    async fn search<T: serde::de::DeserializeOwned>(
+        &self,
+        credentials: &Credentials,
+        query: &str,
+        limit: u32,
+        types: &str,
+    ) -> Result<T, Error> {
+        let cache_key = format!(
+            "search:{}:{types}:{limit}:{}",
+            credentials.country_code,
+            query.trim().to_lowercase()
         );
-        headers.insert("Accept", "application/json".parse()?);
-
-        let mut params = std::collections::HashMap::new();
-        params.insert("query", query);
-        params.insert("limit", &limit);
-        params.insert("countryCode", &self.country_code);
-        params.insert("offset", "0");
-        params.insert("types", "tracks");
-
-        let response = self
-            .client
-            .get(&self.config.search_url)
-            .headers(headers)
-            .query(&params)
-            .send()
-            .await;
 
-        match response {
-            Ok(resp) if resp.status().is_success() => Ok(resp.json().await?),
-            Ok(resp) => Err(format!("Search failed with status: {}", resp.status()).into()),
-            Err(e) => {
-                tracing::warn!("Please check your access token and network connection");
-                Err(format!("Error during search: {}", e).into())
-            }
-        }
+        cached(
+            &self.cache,
+            cache_key,
+            self.search_request(credentials, query, limit, types),
+        )
+        .await
     }
 
-    async fn get_track_response(&self, track_id: &str) -> Result<track::TidalTrackResponse, Error> {
-        let url = format!("https://api.tidal.com/v1/tracks/{}", track_id);
+    async fn search_request(
+        &self,
+        credentials: &Credentials,
+        query: &str,
+        limit: u32,
+        types: &str,
+    ) -> Result<serde_json::Value, Error> {
+        let limit = limit.to_string();
         let params = [
-            ("sessionId", self.session_id.as_str()),
-            ("countryCode", self.country_code.as_str()),
+            ("query", query),
+            ("limit", limit.as_str()),
+            ("countryCode", credentials.country_code.as_str()),
+            ("offset", "0"),
+            ("types", types),
         ];
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("User-Agent", self.config.user_agent.parse()?);
-        headers.insert(
-            "Authorization",
-            format!("{} {}", self.token_type, self.access_token).parse()?,
-        );
-        headers.insert("Accept", "application/json".parse()?);
-
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .query(&params)
-            .send()
-            .await?
-            .error_for_status()?;
+        let request = self
+            .authorized(credentials, reqwest::Method::GET, &self.config.search_url)?
+            .header("Accept", "application/json")
+            .query(&params);
 
-        Ok(response.json().await?)
+        Ok(self.http.send(request).await?.json().await?)
     }
 
-    pub async fn find_track_by_id(&mut self, track_id: &str) -> Result<track::Track, Error> {
-        let mut track_response = self.get_track_response(track_id).await;
+    pub async fn find_track_by_id(
+        &self,
+        track_id: &str,
+        audio_quality: track::AudioQuality,
+    ) -> Result<track::Track, Error> {
+        let track_response: track::TidalTrackResponse = self
+            .with_credentials(|credentials| async move {
+                cached(
+                    &self.cache,
+                    track_cache_key(&credentials.country_code, track_id),
+                    self.get_api_json(
+                        &credentials,
+                        &format!("tracks/{}", track_id),
+                        &[("sessionId", credentials.session_id.as_str())],
+                    ),
+                )
+                .await
+            })
+            .await?;
+
+        track::Track::from_track_response(&track_response, audio_quality)
+    }
 
-        if track_response.is_err() {
-            self.refresh_token().await?;
-            track_response = self.get_track_response(track_id).await;
+    /// Resolves a signed stream URL, reusing a cached one unless `force_refresh` is set, as
+    /// when a cached URL failed to play.
+    pub async fn find_stream_url(
+        &self,
+        track_id: &str,
+        audio_quality: track::AudioQuality,
+        force_refresh: bool,
+    ) -> Result<(String, track::AudioQuality), Error> {
+        let cache_key = format!(
+            "stream:{}:{track_id}:{}",
+            self.credentials().user_id,
+            audio_quality.api_name()
+        );
+        if !force_refresh && let Some(stream) = self.cache.stream_url(&cache_key) {
+            return Ok(stream);
         }
 
-        track::Track::from_track_id(self, &track_response?).await
+        let (stream_url, served_quality) = self
+            .with_credentials(|credentials| async move {
+                track::resolve_stream_url(
+                    &self.http,
+                    &self.config.api_base_url,
+                    &credentials.authorization(),
+                    &credentials.session_id,
+                    &credentials.country_code,
+                    track_id,
+                    audio_quality,
+                )
+                .await
+            })
+            .await?;
+
+        self.cache
+            .insert_stream_url(cache_key, &stream_url, served_quality);
+        Ok((stream_url, served_quality))
     }
 
-    async fn collection_track_ids(
+    async fn collection_items(
         &self,
+        credentials: &Credentials,
         collection_type: &str,
         collection_id: &str,
-    ) -> Result<Vec<String>, Error> {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("User-Agent", self.config.user_agent.parse()?);
-        headers.insert(
-            "Authorization",
-            format!("{} {}", self.token_type, self.access_token).parse()?,
-        );
-        headers.insert("Accept", "application/vnd.api+json".parse()?);
+    ) -> Result<CollectionItems, Error> {
+        let cache_key =
+            collection_cache_key(&credentials.country_code, collection_type, collection_id);
+        if let Some(items) = self.cache.get(&cache_key) {
+            return Ok(items);
+        }
 
         let mut next_url = Some(format!(
-            "https://openapi.tidal.com/v2/{}/{}/relationships/items?countryCode={}",
-            collection_type, collection_id, self.country_code
+            "{}?countryCode={}",
+            crate::http::endpoint(
+                &self.config.openapi_base_url,
+                &format!("{}/{}/relationships/items", collection_type, collection_id),
+            ),
+            credentials.country_code
         ));
-        let mut track_ids = Vec::new();
+        let mut items = CollectionItems::default();
 
         while let Some(url) = next_url.take() {
-            let response = self
-                .client
-                .get(&url)
-                .headers(headers.clone())
-                .send()
+            let request = self
+                .authorized(credentials, reqwest::Method::GET, &url)?
+                .header("Accept", "application/vnd.api+json");
+            let page = self
+                .http
+                .send(request)
                 .await?
-                .error_for_status()?;
-            let page = response.json::<CollectionRelationshipsResponse>().await?;
-
-            track_ids.extend(page.data.into_iter().filter_map(|item| {
-                if item.item_type == "tracks" {
-                    Some(item.id)
-                } else {
-                    None
+                .json::<CollectionRelationshipsResponse>()
+                .await?;
+
+            for item in page.data {
+                match item.item_type.as_str() {
+                    "tracks" => items.track_ids.push(item.id),
+                    "videos" => items.video_count += 1,
+                    _ => {}
                 }
-            }));
+            }
 
             next_url = page.links.next.filter(|link| !link.is_empty()).map(|link| {
                 if link.starts_with("http") {
                     link
                 } else {
-                    format!("https://openapi.tidal.com/v2{}", link)
+                    crate::http::endpoint(&self.config.openapi_base_url, &link)
                 }
             });
         }
 
-        Ok(track_ids)
+        self.cache.insert(cache_key, &items);
+        Ok(items)
     }
 
     async fn legacy_collection_tracks_page(
         &self,
+        credentials: &Credentials,
         collection_type: &str,
         collection_id: &str,
         limit: u32,
         offset: u32,
     ) -> Result<LegacyCollectionTracksPage, Error> {
-        let url = format!(
-            "https://api.tidal.com/v1/{}/{}/tracks",
-            collection_type, collection_id
-        );
         let limit = limit.to_string();
         let offset = offset.to_string();
-        let params = [
-            ("sessionId", self.session_id.as_str()),
-            ("countryCode", self.country_code.as_str()),
-            ("limit", limit.as_str()),
-            ("offset", offset.as_str()),
-        ];
-
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("User-Agent", self.config.user_agent.parse()?);
-        headers.insert(
-            "Authorization",
-            format!("{} {}", self.token_type, self.access_token).parse()?,
-        );
-        headers.insert("Accept", "application/json".parse()?);
 
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .query(&params)
-            .send()
-            .await?
-            .error_for_status()?;
-
-        Ok(response.json().await?)
+        self.get_api_json(
+            credentials,
+            &format!("{}/{}/tracks", collection_type, collection_id),
+            &[
+                ("sessionId", credentials.session_id.as_str()),
+                ("limit", limit.as_str()),
+                ("offset", offset.as_str()),
+            ],
+        )
+        .await
     }
 
     async fn legacy_collection_tracks(
-        &mut self,
+        &self,
         collection_type: &str,
         collection_id: &str,
-    ) -> Result<Vec<track::Track>, Error> {
-        let mut tracks = Vec::new();
+        audio_quality: track::AudioQuality,
+    ) -> Result<track::FoundTracks, Error> {
+        let mut found = track::FoundTracks::default();
         let limit = 100;
         let mut offset = 0;
 
         loop {
-            let mut page = self
-                .legacy_collection_tracks_page(collection_type, collection_id, limit, offset)
-                .await;
-
-            if page.is_err() {
-                self.refresh_token().await?;
-                page = self
-                    .legacy_collection_tracks_page(collection_type, collection_id, limit, offset)
-                    .await;
-            }
-
-            let page = page?;
+            let page = self
+                .with_credentials(|credentials| async move {
+                    self.legacy_collection_tracks_page(
+                        &credentials,
+                        collection_type,
+                        collection_id,
+                        limit,
+                        offset,
+                    )
+                    .await
+                })
+                .await?;
             let item_count = page.items.len();
 
             if page.items.is_empty() {
@@ -795,14 +1224,17 @@ impl Session {
 
             for item in page.items {
                 let track_response = item.into_track_response();
-                if track_response.is_video() {
+                if let Some(reason) = track_response.unavailable_reason() {
+                    tracing::warn!(track_id = %track_response.id(), %reason, "Skipping collection track");
+                    found.skipped.record(reason);
                     continue;
                 }
 
-                match track::Track::from_track_id(self, &track_response).await {
-                    Ok(track) => tracks.push(track),
+                match track::Track::from_track_response(&track_response, audio_quality) {
+                    Ok(track) => found.tracks.push(track),
                     Err(error) => {
                         tracing::warn!(%error, "Skipping collection track");
+                        found.skipped.record(track::SkipReason::StreamError);
                     }
                 }
             }
@@ -816,28 +1248,31 @@ impl Session {
             }
         }
 
-        Ok(tracks)
+        Ok(found)
     }
 
     pub async fn find_collection_tracks(
-        &mut self,
+        &self,
         collection_type: &str,
         collection_id: &str,
         concurrency: usize,
-    ) -> Result<Vec<track::Track>, Error> {
-        let mut ids = self
-            .collection_track_ids(collection_type, collection_id)
+        audio_quality: track::AudioQuality,
+    ) -> Result<track::FoundTracks, Error> {
+        let items = self
+            .with_credentials(|credentials| async move {
+                self.collection_items(&credentials, collection_type, collection_id)
+                    .await
+            })
             .await;
 
-        if ids.is_err() {
-            self.refresh_token().await?;
-            ids = self
-                .collection_track_ids(collection_type, collection_id)
-                .await;
-        }
-
-        match ids {
-            Ok(ids) => Ok(self.find_collection_tracks_by_ids(ids, concurrency).await),
+        match items {
+            Ok(items) => {
+                let mut found = self
+                    .find_collection_tracks_by_ids(items.track_ids, concurrency, audio_quality)
+                    .await;
+                found.skipped.videos += items.video_count;
+                Ok(found)
+            }
             Err(error) => {
                 tracing::warn!(
                     collection_type,
@@ -845,96 +1280,520 @@ impl Session {
                     %error,
                     "Falling back to legacy collection endpoint"
                 );
-                self.legacy_collection_tracks(collection_type, collection_id)
+                self.legacy_collection_tracks(collection_type, collection_id, audio_quality)
                     .await
             }
         }
     }
 
     pub async fn find_tracks(
-        &mut self,
+        &self,
         query: &str,
         limit: u32,
+        audio_quality: track::AudioQuality,
     ) -> Result<Vec<track::Track>, Error> {
-        async fn try_search(
-            this: &crate::session::Session,
-            query: &str,
-            limit: u32,
-        ) -> Result<SearchTracksResponse, Error> {
-            let res = this.search_tracks(query, limit).await?;
-            Ok(res)
-        }
-
-        let mut search_result = try_search(self, query, limit).await;
-
-        // If search fails, refresh token and try again
-        if search_result.is_err() {
-            self.refresh_token().await?;
-            search_result = try_search(self, query, limit).await;
-        }
+        let search_result: SearchTracksResponse = self
+            .with_credentials(|credentials| async move {
+                self.search(&credentials, query, limit, "tracks").await
+            })
+            .await?;
 
-        let items = search_result?.tracks.items;
+        search_result.tracks.into_tracks(audio_quality)
+    }
 
-        let mut tracks = Vec::with_capacity(items.len());
+    /// Searches for `query` and keeps the best ranked track Tidal will stream. Tracks ranked
+    /// above it that cannot be streamed are reported as skipped.
+    pub async fn find_first_track(
+        &self,
+        query: &str,
+        audio_quality: track::AudioQuality,
+    ) -> Result<track::FoundTracks, Error> {
+        let search_result: SearchTracksResponse = self
+            .with_credentials(|credentials| async move {
+                self.search(&credentials, query, FIRST_TRACK_SEARCH_LIMIT, "tracks")
+                    .await
+            })
+            .await?;
 
-        for item in &items {
-            let track = track::Track::from_track_id(self, item).await?;
-            tracks.push(track);
+        let mut found = track::FoundTracks::default();
+        for item in &search_result.tracks.items {
+            match item.unavailable_reason() {
+                Some(reason) => found.skipped.record(reason),
+                None => {
+                    found
+                        .tracks
+                        .push(track::Track::from_track_response(item, audio_quality)?);
+                    break;
+                }
+            }
         }
 
-        Ok(tracks)
+        Ok(found)
     }
 
+    /// Searches tracks for autocomplete. These skip the metadata cache, which would otherwise
+    /// keep a day-long entry for every keystroke.
     pub async fn search_track_summaries(
-        &mut self,
+        &self,
         query: &str,
         limit: u32,
     ) -> Result<Vec<track::TrackSummary>, Error> {
-        async fn try_search(
-            this: &crate::session::Session,
-            query: &str,
-            limit: u32,
-        ) -> Result<SearchTracksResponse, Error> {
-            this.search_tracks(query, limit).await
-        }
+        let response = self
+            .with_credentials(|credentials| async move {
+                self.search_request(&credentials, query, limit, "tracks")
+                    .await
+            })
+            .await?;
 
-        let mut search_result = try_search(self, query, limit).await;
+        Ok(serde_json::from_value::<SearchCatalogResponse>(response)?
+            .into_results()?
+            .tracks)
+    }
 
-        if search_result.is_err() {
-            self.refresh_token().await?;
-            search_result = try_search(self, query, limit).await;
-        }
+    /// Searches the Tidal catalog. `types` is a comma separated list of the sections to
+    /// return, such as `tracks` or `albums,artists`; other sections come back empty.
+    pub async fn search_catalog(
+        &self,
+        query: &str,
+        limit: u32,
+        types: &str,
+    ) -> Result<SearchResults, Error> {
+        self.with_credentials(|credentials| async move {
+            self.search::<SearchCatalogResponse>(&credentials, query, limit, types)
+                .await
+        })
+        .await?
+        .into_results()
+    }
 
-        let mut tracks = Vec::new();
-        for item in search_result?.tracks.items {
-            if item.is_video() {
-                continue;
+    /// Looks a track up by its ISRC. A recording can be on several releases, so the first one
+    /// that can be streamed is used.
+    pub async fn find_track_by_isrc(
+        &self,
+        isrc: &str,
+        audio_quality: track::AudioQuality,
+    ) -> Result<Option<track::Track>, Error> {
+        let track_ids = self
+            .with_credentials(
+                |credentials| async move { self.isrc_track_ids(&credentials, isrc).await },
+            )
+            .await?;
+
+        for track_id in track_ids {
+            match self.find_track_by_id(&track_id, audio_quality).await {
+                Ok(track) => return Ok(Some(track)),
+                Err(error) => {
+                    tracing::debug!(isrc, track_id, %error, "Skipping ISRC match");
+                }
             }
+        }
+
+        Ok(None)
+    }
 
-            tracks.push(item.summary()?);
+    async fn isrc_track_ids(
+        &self,
+        credentials: &Credentials,
+        isrc: &str,
+    ) -> Result<Vec<String>, Error> {
+        let cache_key = format!("isrc:{}:{isrc}", credentials.country_code);
+        if let Some(track_ids) = self.cache.get(&cache_key) {
+            return Ok(track_ids);
         }
 
-        Ok(tracks)
+        let url = crate::http::endpoint(&self.config.openapi_base_url, "tracks");
+        let request = self
+            .authorized(credentials, reqwest::Method::GET, &url)?
+            .header("Accept", "application/vnd.api+json")
+            .query(&[
+                ("countryCode", credentials.country_code.as_str()),
+                ("filter[isrc]", isrc),
+            ]);
+        let response = self
+            .http
+            .send(request)
+            .await?
+            .json::<CollectionRelationshipsResponse>()
+            .await?;
+
+        let track_ids = response
+            .data
+            .into_iter()
+            .filter(|item| item.item_type == "tracks")
+            .map(|item| item.id)
+            .collect::<Vec<_>>();
+        self.cache.insert(cache_key, &track_ids);
+        Ok(track_ids)
     }
 
+    /// Searches for a track from another service and scores the results against it, so a
+    /// karaoke or cover version is not queued just because the search ranked it first.
     pub async fn find_track_by_details(
-        &mut self,
-        title: &str,
-        artist: &str,
-        album: &str,
-    ) -> Result<Option<track::Track>, Error> {
+        &self,
+        source: &crate::url_handler::ExternalTrack,
+        audio_quality: track::AudioQuality,
+    ) -> Result<crate::track_match::TrackMatch, Error> {
         // No album included in first search because sometimes album name is a song name that is more popular than the title
-        let short_query = format!("{} {}", artist, title);
-        let mut short_tracks = self.find_tracks(&short_query, 1).await?;
+        let short_query = format!("{} {}", source.artist, source.title);
+        let mut candidates = self
+            .find_tracks(&short_query, MATCH_CANDIDATE_LIMIT, audio_quality)
+            .await?;
+
+        let found = crate::track_match::best_match(source, candidates.clone());
+        if matches!(found, crate::track_match::TrackMatch::Confident(_)) || source.album.is_empty()
+        {
+            return Ok(found);
+        }
+
+        let full_query = format!("{} {} {}", source.artist, source.title, source.album);
+        for track in self
+            .find_tracks(&full_query, MATCH_CANDIDATE_LIMIT, audio_quality)
+            .await?
+        {
+            if !candidates.iter().any(|candidate| candidate.id == track.id) {
+                candidates.push(track);
+            }
+        }
+
+        Ok(crate::track_match::best_match(source, candidates))
+    }
+
+    fn api_request(
+        &self,
+        credentials: &Credentials,
+        method: reqwest::Method,
+        path: &str,
+    ) -> Result<reqwest::RequestBuilder, Error> {
+        let url = crate::http::endpoint(&self.config.api_base_url, path);
+
+        Ok(self
+            .authorized(credentials, method, &url)?
+            .header("Accept", "application/json")
+            .query(&[("countryCode", credentials.country_code.as_str())]))
+    }
+
+    fn authorized(
+        &self,
+        credentials: &Credentials,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, Error> {
+        self.http.authorized(
+            method,
+            url,
+            &self.config.user_agent,
+            &credentials.authorization(),
+        )
+    }
+
+    async fn get_api_json<T: serde::de::DeserializeOwned>(
+        &self,
+        credentials: &Credentials,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, Error> {
+        let request = self
+            .api_request(credentials, reqwest::Method::GET, path)?
+            .query(params);
+
+        Ok(self.http.send(request).await?.json().await?)
+    }
+
+    async fn get_api_json_with_refresh<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, Error> {
+        self.with_credentials(|credentials| async move {
+            self.get_api_json(&credentials, path, params).await
+        })
+        .await
+    }
+
+    pub async fn find_artist_by_id(&self, artist_id: &str) -> Result<track::ArtistSummary, Error> {
+        let artist: track::TidalArtistResponse = self
+            .get_api_json_with_refresh(&format!("artists/{}", artist_id), &[])
+            .await?;
+
+        Ok(artist.summary())
+    }
+
+    pub async fn find_artist_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<track::ArtistSummary>, Error> {
+        let search_result: SearchArtistsResponse = self
+            .with_credentials(|credentials| async move {
+                self.search(&credentials, name, 1, "artists").await
+            })
+            .await?;
+
+        Ok(search_result
+            .artists
+            .items
+            .first()
+            .map(track::TidalArtistResponse::summary))
+    }
+
+    pub async fn find_artist_top_tracks(
+        &self,
+        artist_id: &str,
+        limit: u32,
+        audio_quality: track::AudioQuality,
+    ) -> Result<Vec<track::Track>, Error> {
+        let limit = limit.to_string();
+        let response: TrackItemsResponse = self
+            .get_api_json_with_refresh(
+                &format!("artists/{}/toptracks", artist_id),
+                &[("limit", limit.as_str()), ("offset", "0")],
+            )
+            .await?;
+
+        response.into_tracks(audio_quality)
+    }
+
+    /// Tracks similar to the seed track. The radio returns full track metadata and stream
+    /// URLs are resolved at play time, so no per-track lookups are needed.
+    pub async fn find_track_radio(
+        &self,
+        track_id: &str,
+        limit: u32,
+        audio_quality: track::AudioQuality,
+    ) -> Result<Vec<track::Track>, Error> {
+        let limit = limit.to_string();
+        let response: TrackItemsResponse = self
+            .get_api_json_with_refresh(
+                &format!("tracks/{}/radio", track_id),
+                &[("limit", limit.as_str()), ("offset", "0")],
+            )
+            .await?;
+
+        Ok(response
+            .into_tracks(audio_quality)?
+            .into_iter()
+            .filter(|track| track.id != track_id)
+            .collect())
+    }
+
+    pub async fn find_favorite_tracks(&self) -> Result<Vec<track::TrackSummary>, Error> {
+        let response: FavoriteItemsResponse<track::TidalTrackResponse> = self
+            .with_credentials(|credentials| async move {
+                self.get_api_json(
+                    &credentials,
+                    &format!("users/{}/favorites/tracks", credentials.user_id),
+                    &user_collection_params(),
+                )
+                .await
+            })
+            .await?;
+
+        response
+            .items
+            .iter()
+            .filter(|favorite| favorite.item.is_streamable())
+            .map(|favorite| favorite.item.summary())
+            .collect()
+    }
+
+    pub async fn find_favorite_albums(&self) -> Result<Vec<track::AlbumSummary>, Error> {
+        let response: FavoriteItemsResponse<track::TidalAlbumResponse> = self
+            .with_credentials(|credentials| async move {
+                self.get_api_json(
+                    &credentials,
+                    &format!("users/{}/favorites/albums", credentials.user_id),
+                    &user_collection_params(),
+                )
+                .await
+            })
+            .await?;
+
+        Ok(response
+            .items
+            .iter()
+            .map(|favorite| favorite.item.summary())
+            .collect())
+    }
+
+    pub async fn find_user_playlists(&self) -> Result<Vec<track::PlaylistSummary>, Error> {
+        let response: PlaylistItemsResponse = self
+            .with_credentials(|credentials| async move {
+                self.get_api_json(
+                    &credentials,
+                    &format!("users/{}/playlists", credentials.user_id),
+                    &user_collection_params(),
+                )
+                .await
+            })
+            .await?;
+
+        Ok(response
+            .items
+            .iter()
+            .map(track::TidalPlaylistResponse::summary)
+            .collect())
+    }
+
+    async fn create_playlist_request(
+        &self,
+        credentials: &Credentials,
+        title: &str,
+        description: &str,
+    ) -> Result<track::PlaylistSummary, Error> {
+        let request = self
+            .api_request(
+                credentials,
+                reqwest::Method::POST,
+                &format!("users/{}/playlists", credentials.user_id),
+            )?
+            .form(&[("title", title), ("description", description)]);
+        let response = self.http.send(request).await?;
+
+        let playlist: track::TidalPlaylistResponse = response.json().await?;
+        Ok(playlist.summary())
+    }
+
+    /// Creates an empty playlist on the logged in account.
+    pub async fn create_playlist(
+        &self,
+        title: &str,
+        description: &str,
+    ) -> Result<track::PlaylistSummary, Error> {
+        self.with_credentials(|credentials| async move {
+            self.create_playlist_request(&credentials, title, description)
+                .await
+        })
+        .await
+    }
+
+    /// Tidal rejects playlist edits unless they carry the playlist's current ETag.
+    async fn playlist_etag(
+        &self,
+        credentials: &Credentials,
+        playlist_id: &str,
+    ) -> Result<String, Error> {
+        let request = self.api_request(
+            credentials,
+            reqwest::Method::GET,
+            &format!("playlists/{}", playlist_id),
+        )?;
+        let response = self.http.send(request).await?;
+
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .ok_or_else(|| format!("Playlist {} did not return an ETag", playlist_id))?;
+
+        Ok(etag.to_str()?.to_string())
+    }
 
-        if !short_tracks.is_empty() {
-            return Ok(short_tracks.pop());
+    async fn add_playlist_tracks_request(
+        &self,
+        credentials: &Credentials,
+        playlist_id: &str,
+        track_ids: &str,
+    ) -> Result<(), Error> {
+        let etag = self.playlist_etag(credentials, playlist_id).await?;
+
+        let request = self
+            .api_request(
+                credentials,
+                reqwest::Method::POST,
+                &format!("playlists/{}/items", playlist_id),
+            )?
+            .header(reqwest::header::IF_NONE_MATCH, etag)
+            .form(&[
+                ("trackIds", track_ids),
+                ("onArtifactNotFound", "SKIP"),
+                ("onDupes", "SKIP"),
+            ]);
+        self.http.send(request).await?;
+
+        Ok(())
+    }
+
+    async fn add_playlist_track_chunks(
+        &self,
+        playlist_id: &str,
+        track_ids: &[String],
+    ) -> Result<(), Error> {
+        for chunk in track_ids.chunks(PLAYLIST_ADD_CHUNK_SIZE) {
+            let track_ids = &chunk.join(",");
+            self.with_credentials(|credentials| async move {
+                self.add_playlist_tracks_request(&credentials, playlist_id, track_ids)
+                    .await
+            })
+            .await?;
         }
 
-        let full_query = format!("{} {} {}", artist, title, album);
-        let mut tracks = self.find_tracks(&full_query, 1).await?;
+        Ok(())
+    }
 
-        Ok(tracks.pop())
+    /// Appends tracks to a playlist owned by the logged in account, skipping ones already in it.
+    /// The playlist's cached track list is dropped, even if only some tracks were added, so it is
+    /// read again on the next play.
+    pub async fn add_tracks_to_playlist(
+        &self,
+        playlist_id: &str,
+        track_ids: &[String],
+    ) -> Result<(), Error> {
+        let added = self.add_playlist_track_chunks(playlist_id, track_ids).await;
+        self.cache.remove(&collection_cache_key(
+            &self.credentials().country_code,
+            "playlists",
+            playlist_id,
+        ));
+        added
+    }
+
+    async fn lyrics_request(
+        &self,
+        credentials: &Credentials,
+        track_id: &str,
+    ) -> Result<Option<crate::lyrics::TidalLyricsResponse>, Error> {
+        let request = self.api_request(
+            credentials,
+            reqwest::Method::GET,
+            &format!("tracks/{}/lyrics", track_id),
+        )?;
+
+        match self.http.send(request).await {
+            Ok(response) => Ok(Some(response.json().await?)),
+            // Tidal answers 404 for tracks without lyrics
+            Err(error) if error.status() == Some(reqwest::StatusCode::NOT_FOUND) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub async fn find_lyrics(
+        &self,
+        track_id: &str,
+    ) -> Result<Option<crate::lyrics::Lyrics>, Error> {
+        let response = self
+            .with_credentials(|credentials| async move {
+                self.lyrics_request(&credentials, track_id).await
+            })
+            .await?;
+
+        Ok(response.and_then(crate::lyrics::TidalLyricsResponse::into_lyrics))
+    }
+
+    pub async fn find_artist_albums(
+        &self,
+        artist_id: &str,
+    ) -> Result<Vec<track::AlbumSummary>, Error> {
+        let limit = ARTIST_ALBUM_LIMIT.to_string();
+        let response: AlbumItemsResponse = self
+            .get_api_json_with_refresh(
+                &format!("artists/{}/albums", artist_id),
+                &[("limit", limit.as_str()), ("offset", "0")],
+            )
+            .await?;
+
+        Ok(response
+            .items
+            .iter()
+            .map(track::TidalAlbumResponse::summary)
+            .collect())
     }
 }
 
@@ -942,6 +1801,511 @@ impl Session {
 mod tests {
     use super::*;
 
+    #[test]
+    fn schedules_token_refresh_before_expiry() {
+        assert_eq!(
+            token_refresh_delay(Some(10_000), 1_000),
+            std::time::Duration::from_secs(9_000 - TOKEN_REFRESH_MARGIN_SECS)
+        );
+        assert!(token_refresh_delay(Some(1_100), 1_000).is_zero());
+        assert!(token_refresh_delay(None, 1_000).is_zero());
+    }
+
+    #[test]
+    fn reads_token_files_without_expiry() {
+        let token: Token = serde_json::from_str(
+            r#"{"access_token": "access", "refresh_token": "refresh", "token_type": "Bearer"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(token.expires_at, None);
+    }
+
+    #[test]
+    fn treats_bad_request_from_token_endpoint_as_rejected_refresh() {
+        let error: Error = Box::new(crate::http::TidalHttpError::Status {
+            status: reqwest::StatusCode::BAD_REQUEST,
+            body: r#"{"error": "invalid_grant"}"#.to_string(),
+        });
+
+        assert!(is_refresh_token_rejected(&error));
+        assert!(!is_refresh_token_rejected(&"timed out".into()));
+    }
+
+    #[tokio::test]
+    async fn reuses_credentials_refreshed_by_another_request() {
+        // Any request to these URLs would fail, so the refresh must not reach Tidal
+        let unreachable = "http://127.0.0.1:9/".to_string();
+        let session = Session::logged_out(
+            TidalConfig {
+                tidal_client_id: String::new(),
+                tidal_client_secret: String::new(),
+                path_to_session: String::new(),
+                user_agent: String::new(),
+                oauth_device_auth_url: unreachable.clone(),
+                oauth_token_url: unreachable.clone(),
+                sessions_url: unreachable.clone(),
+                search_url: unreachable.clone(),
+                api_base_url: unreachable.clone(),
+                openapi_base_url: unreachable,
+                audio_quality: track::AudioQuality::Lossless,
+                console_login: false,
+                accounts: Vec::new(),
+            },
+            Default::default(),
+        );
+        let stale = session.credentials();
+        let refreshed = session.set_credentials(Credentials {
+            access_token: "new".to_string(),
+            ..Default::default()
+        });
+
+        let credentials = session.refresh_credentials(&stale).await.unwrap();
+
+        assert!(std::sync::Arc::ptr_eq(&credentials, &refreshed));
+    }
+
+    /// A token file path unique to the test, so tests running in parallel never share a login.
+    fn mock_token_path(test_name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "tidalcordrs-{}-{}.json",
+            std::process::id(),
+            test_name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    async fn logged_in_session(
+        config: TidalConfig,
+        cache: std::sync::Arc<MetadataCache>,
+    ) -> Session {
+        let session = Session::logged_out(config, cache);
+        session.console_login().await.unwrap();
+        session
+    }
+
+    async fn mock_session(test_name: &str) -> (crate::mock_tidal::MockTidal, Session) {
+        let mock = crate::mock_tidal::MockTidal::start().await;
+        let session =
+            logged_in_session(mock.config(&mock_token_path(test_name)), Default::default()).await;
+
+        (mock, session)
+    }
+
+    #[tokio::test]
+    async fn starts_logged_out_without_a_saved_token() {
+        let mock = crate::mock_tidal::MockTidal::start().await;
+
+        let session = Session::new(
+            mock.config(&mock_token_path("no_token")),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        assert!(session.status().is_none());
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn logs_in_once_and_reuses_the_saved_token() {
+        let mock = crate::mock_tidal::MockTidal::start().await;
+        let token_path = mock_token_path("saved_token");
+
+        let session = logged_in_session(mock.config(&token_path), Default::default()).await;
+        assert_eq!(
+            session.credentials().session_id,
+            crate::mock_tidal::SESSION_ID
+        );
+        assert_eq!(session.credentials().user_id, crate::mock_tidal::USER_ID);
+
+        let session = Session::new(mock.config(&token_path), Default::default())
+            .await
+            .unwrap();
+        assert_eq!(session.credentials().refresh_token, "mock-refresh");
+        assert_eq!(mock.request_count("POST /oauth2/device_authorization"), 1);
+        assert_eq!(mock.request_count("POST /oauth2/token"), 1);
+        assert_eq!(mock.request_count("GET /v1/sessions"), 2);
+
+        let _ = std::fs::remove_file(token_path);
+    }
+
+    #[tokio::test]
+    async fn logs_out_and_back_in_without_restarting() {
+        let (mock, session) = mock_session("relogin").await;
+        let token_path = std::path::PathBuf::from(&session.config.path_to_session);
+
+        session.logout().await.unwrap();
+        assert!(session.status().is_none());
+        assert!(!token_path.exists());
+        let error = session
+            .find_track_by_id("1001", track::AudioQuality::High)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), NOT_LOGGED_IN);
+
+        let device_login = session.start_device_login().await.unwrap();
+        assert!(session.start_device_login().await.is_err());
+        session.finish_device_login(device_login).await.unwrap();
+
+        assert_eq!(
+            session.status().unwrap().user_id,
+            crate::mock_tidal::USER_ID
+        );
+        assert!(token_path.exists());
+        session
+            .find_track_by_id("1001", track::AudioQuality::High)
+            .await
+            .unwrap();
+        assert_eq!(mock.request_count("POST /oauth2/device_authorization"), 2);
+
+        let _ = std::fs::remove_file(token_path);
+    }
+
+    #[tokio::test]
+    async fn concurrent_lookups_share_a_single_token_refresh() {
+        let (mock, session) = mock_session("shared_refresh").await;
+        let session = std::sync::Arc::new(session);
+        mock.expire_access_token();
+
+        let mut lookups = tokio::task::JoinSet::new();
+        for track_id in ["1001", "1002", "1003"] {
+            let session = session.clone();
+            lookups.spawn(async move {
+                session
+                    .find_track_by_id(track_id, track::AudioQuality::High)
+                    .await
+            });
+        }
+
+        while let Some(lookup) = lookups.join_next().await {
+            lookup.unwrap().unwrap();
+        }
+        // One token for the login and one for the refresh
+        assert_eq!(mock.request_count("POST /oauth2/token"), 2);
+    }
+
+    #[tokio::test]
+    async fn steps_down_past_a_refused_quality_without_refreshing_the_token() {
+        let (mock, session) = mock_session("refused_quality").await;
+
+        let (_, served_quality) = session
+            .find_stream_url("1001", track::AudioQuality::HiRes, false)
+            .await
+            .unwrap();
+
+        assert_eq!(served_quality, track::AudioQuality::Lossless);
+        assert_eq!(mock.request_count("POST /oauth2/token"), 1);
+    }
+
+    #[tokio::test]
+    async fn searches_tracks_on_the_configured_endpoint() {
+        let (_mock, session) = mock_session("search").await;
+
+        let tracks = session
+            .find_tracks("mock artist", 10, track::AudioQuality::High)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracks
+                .iter()
+                .map(|track| track.title.as_str())
+                .collect::<Vec<_>>(),
+            ["First Light", "Second Wind"]
+        );
+    }
+
+    #[tokio::test]
+    async fn queues_the_first_streamable_search_result() {
+        let (_mock, session) = mock_session("first_track").await;
+
+        let found = session
+            .find_first_track("mock artist", track::AudioQuality::High)
+            .await
+            .unwrap();
+        assert_eq!(
+            found
+                .tracks
+                .iter()
+                .map(|track| track.id.as_str())
+                .collect::<Vec<_>>(),
+            ["1001"]
+        );
+        assert_eq!(found.skipped.region_locked, 1);
+        assert_eq!(found.skipped.not_stream_ready, 1);
+
+        let found = session
+            .find_first_track("locked away", track::AudioQuality::High)
+            .await
+            .unwrap();
+        assert!(found.tracks.is_empty());
+        assert_eq!(found.skipped.region_locked, 1);
+    }
+
+    #[tokio::test]
+    async fn serves_repeated_lookups_from_the_metadata_cache() {
+        let mock = crate::mock_tidal::MockTidal::start().await;
+        let cache = MetadataCache::load(&crate::config::MetadataCacheConfig {
+            enabled: true,
+            path: mock_token_path("metadata_cache"),
+            max_entries: 100,
+            ttl_minutes: 60,
+        });
+        let session = logged_in_session(
+            mock.config(&mock_token_path("cached_lookups")),
+            std::sync::Arc::new(cache),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let track = session
+                .find_track_by_id("1001", track::AudioQuality::High)
+                .await
+                .unwrap();
+            assert_eq!(track.title, "First Light");
+            let tracks = session
+                .find_tracks("Mock Artist", 10, track::AudioQuality::High)
+                .await
+                .unwrap();
+            assert_eq!(tracks.len(), 2);
+        }
+
+        assert_eq!(mock.request_count("GET /v1/tracks/1001"), 1);
+        assert_eq!(mock.request_count("GET /v1/search"), 1);
+
+        // Autocomplete searches are not cached
+        for _ in 0..2 {
+            session.search_track_summaries("mock", 5).await.unwrap();
+        }
+        assert_eq!(mock.request_count("GET /v1/search"), 3);
+
+        // A playlist the bot changed is read again
+        let playlist_items = format!(
+            "GET /v2/playlists/{}/relationships/items",
+            crate::mock_tidal::PLAYLIST_ID
+        );
+        let find_playlist = || {
+            session.find_collection_tracks(
+                "playlists",
+                crate::mock_tidal::PLAYLIST_ID,
+                2,
+                track::AudioQuality::High,
+            )
+        };
+        find_playlist().await.unwrap();
+        find_playlist().await.unwrap();
+        let reads = mock.request_count(&playlist_items);
+        session
+            .add_tracks_to_playlist(crate::mock_tidal::PLAYLIST_ID, &["1002".to_string()])
+            .await
+            .unwrap();
+        find_playlist().await.unwrap();
+        assert_eq!(mock.request_count(&playlist_items), reads * 2);
+    }
+
+    #[tokio::test]
+    async fn finds_streamable_tracks_by_isrc() {
+        let (mock, session) = mock_session("isrc_lookup").await;
+
+        let track = session
+            .find_track_by_isrc("QZMCK2400002", track::AudioQuality::High)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(track.id, "1002");
+        assert_eq!(track.isrc.as_deref(), Some("QZMCK2400002"));
+
+        // Region-locked and unknown recordings leave the caller to search by details.
+        for isrc in ["QZMCK2400004", "QZMCK2499999"] {
+            let track = session
+                .find_track_by_isrc(isrc, track::AudioQuality::High)
+                .await
+                .unwrap();
+            assert!(track.is_none(), "{isrc} should not match");
+        }
+        assert_eq!(mock.request_count("GET /v2/tracks"), 3);
+    }
+
+    #[tokio::test]
+    async fn scores_detail_matches_by_confidence() {
+        let (_mock, session) = mock_session("detail_matches").await;
+        let find = |title: &str, artist: &str| {
+            let source = crate::url_handler::ExternalTrack::new(
+                title.to_string(),
+                artist.to_string(),
+                String::new(),
+                None,
+                None,
+            )
+            .unwrap();
+            let session = &session;
+            async move {
+                session
+                    .find_track_by_details(&source, track::AudioQuality::High)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let found = find("First Light", "Mock Artist").await;
+        assert!(
+            matches!(&found, crate::track_match::TrackMatch::Confident(track) if track.id == "1001"),
+            "{found:?}"
+        );
+
+        let found = find("Light", "Mock Artist").await;
+        assert!(
+            matches!(&found, crate::track_match::TrackMatch::Uncertain(candidates) if candidates[0].id == "1001"),
+            "{found:?}"
+        );
+
+        let found = find("Unknown Song", "Nobody").await;
+        assert!(
+            matches!(found, crate::track_match::TrackMatch::NotFound),
+            "{found:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn follows_playlist_item_pages_in_order() {
+        let (mock, session) = mock_session("playlist_pages").await;
+
+        let tracks = session
+            .find_collection_tracks(
+                "playlists",
+                crate::mock_tidal::PLAYLIST_ID,
+                2,
+                track::AudioQuality::High,
+            )
+            .await
+            .unwrap()
+            .tracks;
+
+        assert_eq!(
+            tracks
+                .iter()
+                .map(|track| track.id.as_str())
+                .collect::<Vec<_>>(),
+            ["1001", "1002", "1003"]
+        );
+        assert_eq!(
+            mock.request_count(&format!(
+                "GET /v2/playlists/{}/relationships/items",
+                crate::mock_tidal::PLAYLIST_ID
+            )),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_unavailable_playlist_tracks_by_reason() {
+        let (_mock, session) = mock_session("mixed_playlist").await;
+
+        let found = session
+            .find_collection_tracks(
+                "playlists",
+                crate::mock_tidal::MIXED_PLAYLIST_ID,
+                2,
+                track::AudioQuality::High,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            found
+                .tracks
+                .iter()
+                .map(|track| track.id.as_str())
+                .collect::<Vec<_>>(),
+            ["1001"]
+        );
+        assert_eq!(
+            found.skipped,
+            track::SkippedTracks {
+                region_locked: 1,
+                not_stream_ready: 1,
+                videos: 1,
+                stream_errors: 1,
+                not_found: Vec::new(),
+            }
+        );
+        assert!(
+            session
+                .find_track_by_id("1004", track::AudioQuality::High)
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("region-locked")
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_legacy_collection_endpoint() {
+        let (_mock, session) = mock_session("legacy_album").await;
+
+        let tracks = session
+            .find_collection_tracks(
+                "albums",
+                crate::mock_tidal::LEGACY_ALBUM_ID,
+                2,
+                track::AudioQuality::High,
+            )
+            .await
+            .unwrap()
+            .tracks;
+
+        assert_eq!(tracks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn resolves_stream_urls_that_serve_audio() {
+        let (_mock, session) = mock_session("stream_url").await;
+
+        let (stream_url, quality) = session
+            .find_stream_url("1001", track::AudioQuality::Lossless, false)
+            .await
+            .unwrap();
+        let audio = reqwest::get(&stream_url)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+
+        assert_eq!(quality, track::AudioQuality::Lossless);
+        assert!(audio.starts_with(b"RIFF"));
+    }
+
+    #[tokio::test]
+    async fn fetches_a_new_stream_url_when_asked_to_refresh() {
+        let mock = crate::mock_tidal::MockTidal::start().await;
+        let cache = MetadataCache::load(&crate::config::MetadataCacheConfig {
+            enabled: true,
+            path: mock_token_path("stream_url_cache"),
+            max_entries: 100,
+            ttl_minutes: 60,
+        });
+        let session = logged_in_session(
+            mock.config(&mock_token_path("stream_url_refresh")),
+            std::sync::Arc::new(cache),
+        )
+        .await;
+
+        for force_refresh in [false, false, true] {
+            session
+                .find_stream_url("1001", track::AudioQuality::High, force_refresh)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            mock.request_count("GET /v1/tracks/1001/playbackinfopostpaywall"),
+            2
+        );
+    }
+
     #[test]
     fn deserializes_search_tracks_response() {
         let response: SearchTracksResponse = serde_json::from_str(
@@ -971,6 +2335,116 @@ mod tests {
         assert_eq!(response.tracks.items[1].id(), "456");
     }
 
+    #[test]
+    fn converts_radio_items_to_tracks_without_videos() {
+        let response: TrackItemsResponse = serde_json::from_str(
+            r#"{
+                "items": [
+                    {
+                        "id": 123,
+                        "title": "Similar Track",
+                        "artists": [{"name": "Similar Artist"}],
+                        "duration": 200
+                    },
+                    {
+                        "id": 456,
+                        "title": "Music Video",
+                        "artists": [{"name": "Similar Artist"}],
+                        "duration": 210,
+                        "type": "video"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let tracks = response.into_tracks(track::AudioQuality::High).unwrap();
+
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].id, "123");
+        assert_eq!(tracks[0].title, "Similar Track");
+    }
+
+    #[test]
+    fn deserializes_favorite_items_and_user_playlists() {
+        let favorites: FavoriteItemsResponse<track::TidalAlbumResponse> = serde_json::from_str(
+            r#"{
+                "items": [
+                    {
+                        "created": "2024-01-01T00:00:00.000+0000",
+                        "item": {"id": 55555, "title": "Favorite Album", "numberOfTracks": 9}
+                    }
+                ],
+                "totalNumberOfItems": 1
+            }"#,
+        )
+        .unwrap();
+        let playlists: PlaylistItemsResponse = serde_json::from_str(
+            r#"{
+                "items": [
+                    {"uuid": "05f75c8a-a5be-4f4c-8d53", "title": "Road Trip", "numberOfTracks": 42}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(favorites.items[0].item.summary().title, "Favorite Album");
+        let playlist = playlists.items[0].summary();
+        assert_eq!(playlist.id, "05f75c8a-a5be-4f4c-8d53");
+        assert_eq!(playlist.track_count, 42);
+    }
+
+    #[test]
+    fn deserializes_search_artists_response() {
+        let response: SearchArtistsResponse = serde_json::from_str(
+            r#"{
+                "artists": {
+                    "items": [
+                        {"id": 4761957, "name": "First Artist", "popularity": 80}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let artist = response.artists.items[0].summary();
+        assert_eq!(artist.id, "4761957");
+        assert_eq!(artist.name, "First Artist");
+    }
+
+    #[test]
+    fn converts_mixed_search_sections_and_skips_missing_ones() {
+        let response: SearchCatalogResponse = serde_json::from_str(
+            r#"{
+                "tracks": {
+                    "items": [
+                        {"id": 1, "title": "Song", "artists": [{"name": "Artist"}], "duration": 200},
+                        {"id": 2, "title": "Clip", "artists": [{"name": "Artist"}], "duration": 200, "type": "VIDEO"}
+                    ]
+                },
+                "albums": {
+                    "items": [
+                        {"id": 10, "title": "Album", "artists": [{"name": "Artist"}], "numberOfTracks": 9}
+                    ]
+                },
+                "playlists": {
+                    "items": [
+                        {"uuid": "abc-def", "title": "Mix", "numberOfTracks": 30}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let results = response.into_results().unwrap();
+
+        assert_eq!(results.tracks.len(), 1);
+        assert_eq!(results.tracks[0].id, "1");
+        assert_eq!(results.albums[0].id, "10");
+        assert_eq!(results.playlists[0].id, "abc-def");
+        assert!(results.artists.is_empty());
+    }
+
     #[test]
     fn deserializes_collection_relationships_response() {
         let response: CollectionRelationshipsResponse = serde_json::from_str(
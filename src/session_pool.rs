@@ -0,0 +1,273 @@
+use crate::commands::Error;
+use crate::config::TidalConfig;
+use crate::metadata_cache::MetadataCache;
+use crate::session::Session;
+use poise::serenity_prelude as serenity;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub struct TidalAccount {
+    pub name: String,
+    pub session: Arc<Session>,
+}
+
+/// The Tidal accounts the bot streams with. A guild uses the account it is pinned to, or else
+/// one handed out round-robin the first time it asks. Requests move to the next healthy account
+/// while a guild's account is logged out or failing to refresh its token.
+pub struct SessionPool {
+    accounts: Vec<TidalAccount>,
+    pinned: HashMap<serenity::GuildId, usize>,
+    assigned: std::sync::Mutex<HashMap<serenity::GuildId, usize>>,
+    next_account: std::sync::atomic::AtomicUsize,
+}
+
+impl SessionPool {
+    /// Starts every configured account at once. An account without a saved token, or one that
+    /// cannot start, is kept logged out so the owner can log it in later with `/tidal login`.
+    /// Starting fails only when an account failed and no account came up healthy. The accounts
+    /// share one metadata cache.
+    pub async fn new(config: TidalConfig, cache: Arc<MetadataCache>) -> Result<Self, Error> {
+        let account_configs = config.account_configs();
+        let mut starts = tokio::task::JoinSet::new();
+        for (index, account) in account_configs.iter().enumerate() {
+            let account_config = config.for_account(account);
+            let cache = cache.clone();
+            starts.spawn(async move {
+                if !std::path::Path::new(&account_config.path_to_session).exists() {
+                    return (index, Ok(Session::logged_out(account_config, cache)));
+                }
+
+                let started = Session::new(account_config.clone(), cache.clone())
+                    .await
+                    .map_err(|error| (error, Session::logged_out(account_config, cache)));
+                (index, started)
+            });
+        }
+
+        let mut sessions = Vec::with_capacity(account_configs.len());
+        while let Some(started) = starts.join_next().await {
+            sessions.push(started?);
+        }
+        sessions.sort_by_key(|(index, _)| *index);
+
+        let mut accounts = Vec::with_capacity(account_configs.len());
+        let mut pinned = HashMap::new();
+        let mut last_error = None;
+        for ((index, started), account) in sessions.into_iter().zip(&account_configs) {
+            let session = match started {
+                Ok(session) => session,
+                Err((error, session)) => {
+                    tracing::error!(
+                        account = %account.name,
+                        %error,
+                        "Failed to start Tidal account; log it in again with /tidal login"
+                    );
+                    last_error = Some(error);
+                    session
+                }
+            };
+
+            for guild_id in &account.guild_ids {
+                pinned.insert(serenity::GuildId::new(*guild_id), index);
+            }
+            accounts.push(TidalAccount {
+                name: account.name.clone(),
+                session: Arc::new(session),
+            });
+        }
+
+        if let Some(error) = last_error
+            && !accounts.iter().any(|account| account.session.is_healthy())
+        {
+            return Err(error);
+        }
+
+        Ok(Self::from_accounts(accounts, pinned))
+    }
+
+    fn from_accounts(
+        accounts: Vec<TidalAccount>,
+        pinned: HashMap<serenity::GuildId, usize>,
+    ) -> Self {
+        Self {
+            accounts,
+            pinned,
+            assigned: Default::default(),
+            next_account: Default::default(),
+        }
+    }
+
+    pub fn accounts(&self) -> &[TidalAccount] {
+        &self.accounts
+    }
+
+    pub fn account(&self, name: &str) -> Option<&TidalAccount> {
+        self.accounts
+            .iter()
+            .find(|account| account.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The account a guild's requests should use right now. Outside a guild this is the first
+    /// healthy account.
+    pub fn for_guild(&self, guild_id: Option<serenity::GuildId>) -> &TidalAccount {
+        let preferred = guild_id.map_or(0, |guild_id| self.preferred_account(guild_id));
+        let healthy = self
+            .accounts
+            .iter()
+            .map(|account| account.session.is_healthy())
+            .collect::<Vec<_>>();
+        let index = choose_account(preferred, &healthy);
+
+        if index != preferred {
+            tracing::debug!(
+                guild_id = ?guild_id,
+                from = %self.accounts[preferred].name,
+                to = %self.accounts[index].name,
+                "Failing over to another Tidal account"
+            );
+        }
+
+        &self.accounts[index]
+    }
+
+    /// The account a guild is pinned or assigned to, whether or not it is healthy.
+    pub fn assigned(&self, guild_id: Option<serenity::GuildId>) -> &TidalAccount {
+        &self.accounts[guild_id.map_or(0, |guild_id| self.preferred_account(guild_id))]
+    }
+
+    pub fn session(&self, guild_id: Option<serenity::GuildId>) -> Arc<Session> {
+        self.for_guild(guild_id).session.clone()
+    }
+
+    fn preferred_account(&self, guild_id: serenity::GuildId) -> usize {
+        if let Some(index) = self.pinned.get(&guild_id) {
+            return *index;
+        }
+
+        *self
+            .assigned
+            .lock()
+            .unwrap()
+            .entry(guild_id)
+            .or_insert_with(|| {
+                self.next_account
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                    % self.accounts.len()
+            })
+    }
+}
+
+/// `preferred` when it is healthy, otherwise the next healthy account after it. Falls back to
+/// `preferred` when none are healthy, so its error reaches the user.
+fn choose_account(preferred: usize, healthy: &[bool]) -> usize {
+    (0..healthy.len())
+        .map(|offset| (preferred + offset) % healthy.len())
+        .find(|index| healthy[*index])
+        .unwrap_or(preferred)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_the_assigned_account_while_healthy() {
+        assert_eq!(choose_account(1, &[true, true, true]), 1);
+        assert_eq!(choose_account(1, &[true, false, true]), 2);
+        assert_eq!(choose_account(2, &[true, true, false]), 0);
+        assert_eq!(choose_account(1, &[false, false]), 1);
+    }
+
+    #[tokio::test]
+    async fn assigns_guilds_round_robin_and_fails_over() {
+        let mock = crate::mock_tidal::MockTidal::start().await;
+        let mut accounts = Vec::new();
+        for name in ["first", "second"] {
+            let token_path = std::env::temp_dir().join(format!(
+                "tidalcordrs-{}-pool-{name}.json",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&token_path);
+            let session = Session::logged_out(mock.config(&token_path), Default::default());
+            session.console_login().await.unwrap();
+            accounts.push(TidalAccount {
+                name: name.to_string(),
+                session: Arc::new(session),
+            });
+        }
+        let pinned_guild = serenity::GuildId::new(1);
+        let pool = SessionPool::from_accounts(accounts, HashMap::from([(pinned_guild, 1)]));
+
+        let first_guild = serenity::GuildId::new(10);
+        let second_guild = serenity::GuildId::new(20);
+        assert_eq!(pool.for_guild(Some(first_guild)).name, "first");
+        assert_eq!(pool.for_guild(Some(second_guild)).name, "second");
+        assert_eq!(pool.for_guild(Some(first_guild)).name, "first");
+        assert_eq!(pool.for_guild(Some(pinned_guild)).name, "second");
+
+        pool.account("second")
+            .unwrap()
+            .session
+            .logout()
+            .await
+            .unwrap();
+
+        assert_eq!(pool.for_guild(Some(pinned_guild)).name, "first");
+        assert_eq!(pool.for_guild(Some(second_guild)).name, "first");
+        pool.session(Some(second_guild))
+            .find_track_by_id("1001", crate::track::AudioQuality::High)
+            .await
+            .unwrap();
+
+        // Logging out also deletes the token file
+        pool.account("first")
+            .unwrap()
+            .session
+            .logout()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_accounts_without_a_token_logged_out() {
+        let mock = crate::mock_tidal::MockTidal::start().await;
+        let token_path = |name: &str| {
+            let path = std::env::temp_dir().join(format!(
+                "tidalcordrs-{}-pool-start-{name}.json",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            path.to_string_lossy().into_owned()
+        };
+        let linked_path = token_path("linked");
+        Session::logged_out(mock.config(linked_path.as_ref()), Default::default())
+            .console_login()
+            .await
+            .unwrap();
+
+        let mut config = mock.config(linked_path.as_ref());
+        config.accounts = vec![
+            crate::config::TidalAccountConfig {
+                name: "linked".to_string(),
+                path_to_session: linked_path.clone(),
+                guild_ids: Vec::new(),
+            },
+            crate::config::TidalAccountConfig {
+                name: "unlinked".to_string(),
+                path_to_session: token_path("unlinked"),
+                guild_ids: vec![1],
+            },
+        ];
+        let pool = SessionPool::new(config, Default::default()).await.unwrap();
+
+        assert!(pool.account("linked").unwrap().session.is_healthy());
+        assert!(pool.account("unlinked").unwrap().session.status().is_none());
+        assert_eq!(
+            pool.assigned(Some(serenity::GuildId::new(1))).name,
+            "unlinked"
+        );
+        assert_eq!(mock.request_count("POST /oauth2/device_authorization"), 1);
+
+        let _ = std::fs::remove_file(linked_path);
+    }
+}
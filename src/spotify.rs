@@ -0,0 +1,380 @@
+use crate::commands::Error;
+use crate::track::FoundTracks;
+use crate::url_handler::{ExternalTrack, ResolveContext, UrlResolver, host_matches};
+use lol_html::{HtmlRewriter, Settings, text};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const EMBED_BASE_URL: &str = "https://open.spotify.com/embed";
+const OPEN_BASE_URL: &str = "https://open.spotify.com";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpotifyResource {
+    Track,
+    Album,
+    Playlist,
+}
+
+impl SpotifyResource {
+    fn path(self) -> &'static str {
+        match self {
+            Self::Track => "track",
+            Self::Album => "album",
+            Self::Playlist => "playlist",
+        }
+    }
+}
+
+/// Matches Spotify tracks, albums, and playlists on Tidal.
+pub struct SpotifyResolver;
+
+#[serenity::async_trait]
+impl UrlResolver for SpotifyResolver {
+    fn name(&self) -> &'static str {
+        "Spotify"
+    }
+
+    fn matches(&self, url: &url::Url) -> bool {
+        host_matches(url, &["spotify.com"])
+    }
+
+    async fn resolve(
+        &self,
+        url: &url::Url,
+        context: &ResolveContext<'_>,
+    ) -> Result<FoundTracks, Error> {
+        tracing::info!(%url, "Detected Spotify URL; resolving");
+        let Some((resource, id)) = parse_spotify_resource(url) else {
+            tracing::warn!(%url, "Unsupported Spotify link");
+            return Ok(FoundTracks::default());
+        };
+
+        let tracks = fetch_spotify_tracks(resource, &id).await?;
+        Ok(context.find_external_tracks(tracks).await)
+    }
+}
+
+/// Reads `open.spotify.com/{track,album,playlist}/{id}` links, including localized
+/// `/intl-xx/` and `/embed/` paths.
+pub fn parse_spotify_resource(url: &url::Url) -> Option<(SpotifyResource, String)> {
+    let segments = url.path_segments()?.collect::<Vec<_>>();
+
+    for (index, segment) in segments.iter().enumerate() {
+        let resource = match *segment {
+            "track" => SpotifyResource::Track,
+            "album" => SpotifyResource::Album,
+            "playlist" => SpotifyResource::Playlist,
+            _ => continue,
+        };
+
+        let id = segments.get(index + 1)?;
+        if !id.is_empty() {
+            return Some((resource, (*id).to_string()));
+        }
+    }
+
+    None
+}
+
+/// The tracks behind a Spotify link, read from the public embed player. A single track falls
+/// back to the open graph tags of its page when the embed cannot be read.
+pub async fn fetch_spotify_tracks(
+    resource: SpotifyResource,
+    id: &str,
+) -> Result<Vec<ExternalTrack>, Error> {
+    let client = reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:150.0) Gecko/20100101 Firefox/150.0")
+        .build()?;
+
+    let embed_url = format!("{EMBED_BASE_URL}/{}/{id}", resource.path());
+    let embed = client
+        .get(&embed_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    match parse_embed_page(&embed) {
+        Ok(tracks) if !tracks.is_empty() => return Ok(tracks),
+        Ok(_) => {
+            tracing::warn!(url = embed_url, "Spotify embed listed no tracks");
+        }
+        Err(error) => {
+            tracing::warn!(url = embed_url, %error, "Failed to read Spotify embed");
+        }
+    }
+
+    if resource != SpotifyResource::Track {
+        return Ok(Vec::new());
+    }
+
+    let page_url = format!("{OPEN_BASE_URL}/track/{id}");
+    let page = client
+        .get(&page_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(parse_open_graph_track(&page)?.into_iter().collect())
+}
+
+/// Reads the entity the embed player renders from its `__NEXT_DATA__` JSON.
+fn parse_embed_page(html: &str) -> Result<Vec<ExternalTrack>, Error> {
+    let script = Rc::new(RefCell::new(String::new()));
+    let script_handle = Rc::clone(&script);
+
+    let mut rewriter = HtmlRewriter::new(
+        Settings {
+            element_content_handlers: vec![text!("script#__NEXT_DATA__", move |t| {
+                script_handle.borrow_mut().push_str(t.as_str());
+                Ok(())
+            })],
+            ..Settings::default()
+        },
+        |_: &[u8]| {},
+    );
+    rewriter.write(html.as_bytes())?;
+    rewriter.end()?;
+
+    let data: serde_json::Value = serde_json::from_str(&script.take())
+        .map_err(|error| format!("Could not read Spotify embed data: {error}"))?;
+    let entity = data
+        .pointer("/props/pageProps/state/data/entity")
+        .ok_or("Spotify embed data has no entity")?;
+
+    let entity_name = string_field(entity, &["name", "title"]);
+    let track_list = entity
+        .get("trackList")
+        .and_then(serde_json::Value::as_array)
+        .filter(|track_list| !track_list.is_empty());
+
+    let Some(track_list) = track_list else {
+        // A single track
+        let artist = entity
+            .pointer("/artists/0/name")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_string)
+            .or_else(|| first_artist(&string_field(entity, &["subtitle"])))
+            .unwrap_or_default();
+        return Ok(ExternalTrack::new(
+            entity_name,
+            artist,
+            entity
+                .pointer("/album/name")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            isrc(entity),
+            duration_secs(entity),
+        )
+        .into_iter()
+        .collect());
+    };
+
+    let album = if entity.get("type").and_then(serde_json::Value::as_str) == Some("album") {
+        entity_name
+    } else {
+        String::new()
+    };
+
+    Ok(track_list
+        .iter()
+        .filter_map(|item| {
+            ExternalTrack::new(
+                string_field(item, &["title", "name"]),
+                first_artist(&string_field(item, &["subtitle"])).unwrap_or_default(),
+                album.clone(),
+                isrc(item),
+                duration_secs(item),
+            )
+        })
+        .collect())
+}
+
+/// Reads a track page's `og:title` and its `Artist · Album · Song · Year` description.
+fn parse_open_graph_track(html: &str) -> Result<Option<ExternalTrack>, Error> {
+    let page = crate::open_graph::read_page(html)?;
+    let (Some(title), Some(description)) = (page.meta("og:title"), page.meta("og:description"))
+    else {
+        return Ok(None);
+    };
+    let mut parts = description.split(" · ").map(str::trim);
+    let artist = parts.next().unwrap_or_default().to_string();
+    let album = parts.next().unwrap_or_default().to_string();
+
+    Ok(ExternalTrack::new(
+        title.to_string(),
+        artist,
+        album,
+        None,
+        None,
+    ))
+}
+
+fn string_field(value: &serde_json::Value, names: &[&str]) -> String {
+    names
+        .iter()
+        .find_map(|name| value.get(name).and_then(serde_json::Value::as_str))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Embed track lists join every artist into one subtitle, such as `Artist, Guest`.
+fn first_artist(subtitle: &str) -> Option<String> {
+    subtitle
+        .split(',')
+        .map(str::trim)
+        .find(|artist| !artist.is_empty())
+        .map(str::to_string)
+}
+
+/// Embed data gives durations in milliseconds.
+fn duration_secs(value: &serde_json::Value) -> Option<u32> {
+    value
+        .get("duration")
+        .and_then(serde_json::Value::as_u64)
+        .and_then(|millis| u32::try_from(millis / 1000).ok())
+}
+
+fn isrc(value: &serde_json::Value) -> Option<String> {
+    value
+        .get("isrc")
+        .or_else(|| value.pointer("/external_ids/isrc"))
+        .or_else(|| value.pointer("/externalIds/isrc"))
+        .and_then(serde_json::Value::as_str)
+        .filter(|isrc| !isrc.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed_page(entity: serde_json::Value) -> String {
+        let data = serde_json::json!({
+            "props": {"pageProps": {"state": {"data": {"entity": entity}}}}
+        });
+        format!(
+            r#"<html><body><script id="__NEXT_DATA__" type="application/json">{data}</script></body></html>"#
+        )
+    }
+
+    fn parse(url: &str) -> Option<(SpotifyResource, String)> {
+        parse_spotify_resource(&url::Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parses_spotify_links() {
+        assert_eq!(
+            parse("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC?si=abc"),
+            Some((SpotifyResource::Track, "4uLU6hMCjMI75M1A2tKUQC".to_string()))
+        );
+        assert_eq!(
+            parse("https://open.spotify.com/intl-de/album/1DFixLWuPkv3KT3TnV35m3"),
+            Some((SpotifyResource::Album, "1DFixLWuPkv3KT3TnV35m3".to_string()))
+        );
+        assert_eq!(
+            parse("https://open.spotify.com/embed/playlist/37i9dQZF1DXcBWIGoYBM5M"),
+            Some((
+                SpotifyResource::Playlist,
+                "37i9dQZF1DXcBWIGoYBM5M".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("https://open.spotify.com/artist/0OdUWJ0sBjDrqHygGUXeCF"),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_a_single_track_embed() {
+        let html = embed_page(serde_json::json!({
+            "type": "track",
+            "name": "Song Title",
+            "artists": [{"name": "Main Artist"}, {"name": "Guest Artist"}],
+            "isrc": "USABC1234567",
+            "duration": 215_000,
+        }));
+
+        assert_eq!(
+            parse_embed_page(&html).unwrap(),
+            [ExternalTrack::new(
+                "Song Title".to_string(),
+                "Main Artist".to_string(),
+                String::new(),
+                Some("USABC1234567".to_string()),
+                Some(215),
+            )
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn reads_album_and_playlist_track_lists() {
+        let album = embed_page(serde_json::json!({
+            "type": "album",
+            "name": "Album Title",
+            "trackList": [
+                {"title": "First", "subtitle": "Main Artist"},
+                {"title": "Second", "subtitle": "Main Artist, Guest Artist"},
+            ],
+        }));
+        let tracks = parse_embed_page(&album).unwrap();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[1].title, "Second");
+        assert_eq!(tracks[1].artist, "Main Artist");
+        assert_eq!(tracks[1].album, "Album Title");
+
+        let playlist = embed_page(serde_json::json!({
+            "type": "playlist",
+            "name": "Road Trip",
+            "trackList": [
+                {"title": "Anthem", "subtitle": "Band"},
+                {"title": "", "subtitle": "Nobody"},
+            ],
+        }));
+        let tracks = parse_embed_page(&playlist).unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].album, "");
+    }
+
+    #[test]
+    fn reads_a_saved_album_embed() {
+        let tracks =
+            parse_embed_page(include_str!("../tests/fixtures/spotify_embed_album.html")).unwrap();
+
+        assert_eq!(
+            tracks
+                .iter()
+                .map(|track| (track.title.as_str(), track.artist.as_str(), track.duration))
+                .collect::<Vec<_>>(),
+            [
+                ("First", "Main Artist", Some(181)),
+                ("Second", "Main Artist", Some(202)),
+            ]
+        );
+        assert!(tracks.iter().all(|track| track.album == "Album Title"));
+    }
+
+    #[test]
+    fn reads_open_graph_track_tags() {
+        let html = r#"<html><head>
+            <meta property="og:title" content="Song &amp; Title">
+            <meta property="og:description" content="Main Artist · Album Title · Song · 2020">
+        </head></html>"#;
+
+        assert_eq!(
+            parse_open_graph_track(html).unwrap(),
+            ExternalTrack::new(
+                "Song & Title".to_string(),
+                "Main Artist".to_string(),
+                "Album Title".to_string(),
+                None,
+                None,
+            )
+        );
+        assert_eq!(parse_open_graph_track("<html></html>").unwrap(), None);
+    }
+}
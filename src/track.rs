@@ -1,4 +1,12 @@
 use crate::commands::Error;
+use base64::Engine;
+use html_escape::decode_html_entities;
+use lol_html::{HtmlRewriter, Settings, element, text};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const BTS_MANIFEST_MIME_TYPE: &str = "application/vnd.tidal.bts";
+const DASH_MANIFEST_MIME_TYPE: &str = "application/dash+xml";
 
 #[derive(Debug, Clone, serde::Deserialize)]
 #[serde(untagged)]
@@ -22,6 +30,7 @@ pub struct TidalArtist {
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TidalTrackResponse {
     id: TidalTrackId,
     title: String,
@@ -29,6 +38,14 @@ pub struct TidalTrackResponse {
     duration: u32,
     #[serde(default, rename = "type")]
     item_type: Option<String>,
+    /// False when the track cannot be streamed from the account's country.
+    #[serde(default)]
+    allow_streaming: Option<bool>,
+    /// False for tracks Tidal lists but has not made playable yet.
+    #[serde(default)]
+    stream_ready: Option<bool>,
+    #[serde(default)]
+    isrc: Option<String>,
 }
 
 impl TidalTrackResponse {
@@ -42,6 +59,24 @@ impl TidalTrackResponse {
             .is_some_and(|item_type| item_type.eq_ignore_ascii_case("video"))
     }
 
+    /// Why Tidal will not stream this item, if its flags say so. Missing flags count as
+    /// streamable, since not every endpoint sends them.
+    pub fn unavailable_reason(&self) -> Option<SkipReason> {
+        if self.is_video() {
+            Some(SkipReason::Video)
+        } else if self.allow_streaming == Some(false) {
+            Some(SkipReason::RegionLocked)
+        } else if self.stream_ready == Some(false) {
+            Some(SkipReason::NotStreamReady)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_streamable(&self) -> bool {
+        self.unavailable_reason().is_none()
+    }
+
     pub fn summary(&self) -> Result<TrackSummary, Error> {
         let artist = self
             .artists
@@ -67,18 +102,553 @@ impl TidalTrackResponse {
     }
 }
 
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TidalArtistResponse {
+    id: TidalTrackId,
+    name: String,
+}
+
+impl TidalArtistResponse {
+    pub fn summary(&self) -> ArtistSummary {
+        ArtistSummary {
+            id: self.id.to_string(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TidalAlbumResponse {
+    id: TidalTrackId,
+    title: String,
+    #[serde(default)]
+    artists: Vec<TidalArtist>,
+    #[serde(default)]
+    number_of_tracks: u32,
+    #[serde(default)]
+    release_date: Option<String>,
+}
+
+impl TidalAlbumResponse {
+    pub fn summary(&self) -> AlbumSummary {
+        AlbumSummary {
+            id: self.id.to_string(),
+            title: self.title.clone(),
+            artist: self
+                .artists
+                .first()
+                .map(|artist| artist.name.clone())
+                .unwrap_or_else(|| "Various Artists".to_string()),
+            track_count: self.number_of_tracks,
+            release_year: self
+                .release_date
+                .as_deref()
+                .and_then(|date| date.get(..4))
+                .map(str::to_string),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TidalPlaylistResponse {
+    uuid: String,
+    title: String,
+    #[serde(default)]
+    number_of_tracks: u32,
+}
+
+impl TidalPlaylistResponse {
+    pub fn summary(&self) -> PlaylistSummary {
+        PlaylistSummary {
+            id: self.uuid.clone(),
+            title: self.title.clone(),
+            track_count: self.number_of_tracks,
+        }
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, poise::ChoiceParameter, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AudioQuality {
+    #[name = "low"]
+    Low,
+    #[name = "high"]
+    High,
+    #[name = "lossless"]
+    Lossless,
+    #[name = "hi_res"]
+    HiRes,
+}
+
+impl AudioQuality {
+    const DESCENDING: [Self; 4] = [Self::HiRes, Self::Lossless, Self::High, Self::Low];
+
+    pub fn api_name(self) -> &'static str {
+        match self {
+            Self::Low => "LOW",
+            Self::High => "HIGH",
+            Self::Lossless => "LOSSLESS",
+            Self::HiRes => "HI_RES",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Low => "Low",
+            Self::High => "High",
+            Self::Lossless => "Lossless",
+            Self::HiRes => "Hi-Res",
+        }
+    }
+
+    /// The requested tier followed by every lower tier, best first.
+    pub fn fallback_chain(self) -> impl Iterator<Item = Self> {
+        Self::DESCENDING
+            .into_iter()
+            .skip_while(move |quality| *quality != self)
+    }
+}
+
+impl std::str::FromStr for AudioQuality {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::DESCENDING
+            .into_iter()
+            .find(|quality| quality.api_name().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| format!("expected one of LOW, HIGH, LOSSLESS, or HI_RES, got {value}"))
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct StreamUrlResponse {
     urls: Vec<String>,
+    #[serde(default)]
+    audio_quality: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaybackInfoResponse {
+    #[serde(default)]
+    audio_quality: Option<String>,
+    manifest_mime_type: String,
+    manifest: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BtsManifest {
+    #[serde(default)]
+    codecs: Option<String>,
+    #[serde(default)]
+    encryption_type: Option<String>,
+    urls: Vec<String>,
+}
+
+/// The playable parts of a Tidal stream manifest. Segmented DASH streams list the
+/// initialization segment first, followed by every media segment in order.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StreamManifest {
+    pub codecs: Option<String>,
+    pub urls: Vec<String>,
+}
+
+impl StreamManifest {
+    pub fn parse(manifest_mime_type: &str, encoded_manifest: &str) -> Result<Self, Error> {
+        let manifest = base64::engine::general_purpose::STANDARD
+            .decode(encoded_manifest.trim())
+            .map_err(|error| format!("Failed to decode stream manifest: {error}"))?;
+
+        match manifest_mime_type {
+            BTS_MANIFEST_MIME_TYPE => Self::from_bts(&manifest),
+            DASH_MANIFEST_MIME_TYPE => Self::from_dash(&String::from_utf8_lossy(&manifest)),
+            other => Err(format!("Unsupported stream manifest type: {other}").into()),
+        }
+    }
+
+    fn from_bts(manifest: &[u8]) -> Result<Self, Error> {
+        let manifest: BtsManifest = serde_json::from_slice(manifest)?;
+
+        if let Some(encryption_type) = manifest.encryption_type.as_deref()
+            && !encryption_type.eq_ignore_ascii_case("NONE")
+        {
+            return Err(format!("Encrypted streams are not supported: {encryption_type}").into());
+        }
+
+        if manifest.urls.is_empty() {
+            return Err("Expected stream URL in manifest".into());
+        }
+
+        Ok(Self {
+            codecs: manifest.codecs,
+            urls: manifest.urls,
+        })
+    }
+
+    fn from_dash(manifest: &str) -> Result<Self, Error> {
+        let mpd = DashManifest::parse(manifest)?;
+        let template = mpd
+            .template
+            .as_ref()
+            .ok_or("DASH manifest has no SegmentTemplate")?;
+        let media = template
+            .media
+            .as_deref()
+            .ok_or("DASH manifest has no media template")?;
+
+        let segment_count = if mpd.timeline.is_empty() {
+            let segment_duration = template.duration.ok_or("DASH manifest has no segments")?;
+            let total_duration = mpd
+                .presentation_duration
+                .ok_or("DASH manifest has no presentation duration")?;
+            (total_duration * template.timescale as f64 / segment_duration as f64).ceil() as u64
+        } else {
+            let mut count = 0;
+            let mut elapsed = 0;
+            for &(duration, repeat) in &mpd.timeline {
+                // A negative repeat count runs the segment until the end of the presentation
+                let repeats = match u64::try_from(repeat) {
+                    Ok(repeat) => repeat + 1,
+                    Err(_) => {
+                        let total_duration = mpd
+                            .presentation_duration
+                            .ok_or("DASH manifest has no presentation duration")?
+                            * template.timescale as f64;
+                        ((total_duration - elapsed as f64) / duration as f64)
+                            .ceil()
+                            .max(0.0) as u64
+                    }
+                };
+                count += repeats;
+                elapsed += repeats * duration;
+            }
+            count
+        };
+
+        let mut urls = Vec::with_capacity(segment_count as usize + 1);
+        if let Some(initialization) = template.initialization.as_deref() {
+            urls.push(mpd.segment_url(initialization, None)?);
+        }
+        for number in template.start_number..template.start_number + segment_count {
+            urls.push(mpd.segment_url(media, Some(number))?);
+        }
+
+        Ok(Self {
+            codecs: mpd.codecs,
+            urls,
+        })
+    }
+
+    /// Input for ffmpeg. Segments are joined in a `concat:` list, read back to back as one
+    /// fragmented MP4 file. The spool writes that list to a file before starting ffmpeg.
+    pub fn ffmpeg_input(&self) -> String {
+        match self.urls.as_slice() {
+            [url] => url.clone(),
+            urls => format!(
+                "concat:{}",
+                urls.iter()
+                    .map(|url| url.replace('|', "%7C"))
+                    .collect::<Vec<_>>()
+                    .join("|")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DashSegmentTemplate {
+    initialization: Option<String>,
+    media: Option<String>,
+    start_number: u64,
+    timescale: u64,
+    duration: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct DashManifest {
+    presentation_duration: Option<f64>,
+    base_url: Option<String>,
+    representation_id: String,
+    bandwidth: String,
+    codecs: Option<String>,
+    template: Option<DashSegmentTemplate>,
+    template_count: usize,
+    timeline: Vec<(u64, i64)>, // (duration, repeat count)
+}
+
+impl DashManifest {
+    /// Reads the first representation of an MPD. lol_html is an HTML parser, but the
+    /// handful of elements and attributes needed here parse the same way in XML.
+    fn parse(manifest: &str) -> Result<Self, Error> {
+        let mpd = Rc::new(RefCell::new(DashManifest::default()));
+        let base_url_text = Rc::new(RefCell::new(String::new()));
+
+        let mpd_handle = Rc::clone(&mpd);
+        let representation_handle = Rc::clone(&mpd);
+        let template_handle = Rc::clone(&mpd);
+        let timeline_handle = Rc::clone(&mpd);
+        let base_url_handle = Rc::clone(&base_url_text);
+
+        let mut rewriter = HtmlRewriter::new(
+            Settings {
+                element_content_handlers: vec![
+                    element!("mpd", move |el| {
+                        mpd_handle.borrow_mut().presentation_duration = el
+                            .get_attribute("mediaPresentationDuration")
+                            .and_then(|duration| parse_iso8601_duration(&duration));
+                        Ok(())
+                    }),
+                    element!("representation", move |el| {
+                        let mut mpd = representation_handle.borrow_mut();
+                        if mpd.template.is_none() {
+                            mpd.representation_id = el.get_attribute("id").unwrap_or_default();
+                            mpd.bandwidth = el.get_attribute("bandwidth").unwrap_or_default();
+                            mpd.codecs = el.get_attribute("codecs");
+                        }
+                        Ok(())
+                    }),
+                    element!("segmenttemplate", move |el| {
+                        let mut mpd = template_handle.borrow_mut();
+                        mpd.template_count += 1;
+                        if mpd.template.is_none() {
+                            let number = |name: &str| {
+                                el.get_attribute(name)
+                                    .and_then(|value| value.parse::<u64>().ok())
+                            };
+                            mpd.template = Some(DashSegmentTemplate {
+                                initialization: el
+                                    .get_attribute("initialization")
+                                    .map(|value| decode_html_entities(&value).into_owned()),
+                                media: el
+                                    .get_attribute("media")
+                                    .map(|value| decode_html_entities(&value).into_owned()),
+                                start_number: number("startNumber").unwrap_or(1),
+                                timescale: number("timescale").unwrap_or(1).max(1),
+                                duration: number("duration").filter(|duration| *duration > 0),
+                            });
+                        }
+                        Ok(())
+                    }),
+                    element!("s", move |el| {
+                        let mut mpd = timeline_handle.borrow_mut();
+                        // Only the timeline of the first SegmentTemplate is used
+                        if mpd.template_count == 1 {
+                            let duration = el
+                                .get_attribute("d")
+                                .and_then(|value| value.parse::<u64>().ok())
+                                .ok_or("DASH segment is missing a duration")?;
+                            let repeat = el
+                                .get_attribute("r")
+                                .and_then(|value| value.parse::<i64>().ok())
+                                .unwrap_or(0);
+                            mpd.timeline.push((duration, repeat));
+                        }
+                        Ok(())
+                    }),
+                    text!("baseurl", move |t| {
+                        base_url_handle.borrow_mut().push_str(t.as_str());
+                        Ok(())
+                    }),
+                ],
+                ..Settings::default()
+            },
+            |_: &[u8]| {},
+        );
+
+        rewriter.write(manifest.as_bytes())?;
+        rewriter.end()?;
+
+        let mut mpd = mpd.take();
+        let base_url = decode_html_entities(base_url_text.take().trim()).into_owned();
+        mpd.base_url = (!base_url.is_empty()).then_some(base_url);
+
+        Ok(mpd)
+    }
+
+    fn segment_url(&self, template: &str, number: Option<u64>) -> Result<String, Error> {
+        let mut segment = template
+            .replace("$RepresentationID$", &self.representation_id)
+            .replace("$Bandwidth$", &self.bandwidth);
+        if let Some(number) = number {
+            segment = segment.replace("$Number$", &number.to_string());
+        }
+        let segment = segment.replace("$$", "$");
+
+        match self.base_url.as_deref() {
+            Some(base_url) => Ok(url::Url::parse(base_url)?.join(&segment)?.to_string()),
+            None => Ok(segment),
+        }
+    }
+}
+
+/// Parses the `PT#H#M#S` form of ISO 8601 durations used by DASH manifests, in seconds.
+fn parse_iso8601_duration(duration: &str) -> Option<f64> {
+    let mut rest = duration.strip_prefix("PT")?;
+    let mut seconds = 0.0;
+
+    while !rest.is_empty() {
+        let unit_index = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let value = rest[..unit_index].parse::<f64>().ok()?;
+        seconds += match &rest[unit_index..unit_index + 1] {
+            "H" => value * 3600.0,
+            "M" => value * 60.0,
+            "S" => value,
+            _ => return None,
+        };
+        rest = &rest[unit_index + 1..];
+    }
+
+    Some(seconds)
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Track {
+    pub id: String,
     pub title: String,
     pub artist: String,
     pub featured_artists: Vec<String>, // Can be empty
     pub duration: u32,
-    pub stream_url: String,
+    pub audio_quality: AudioQuality, // Requested quality
+    /// International Standard Recording Code, when Tidal lists one.
+    #[serde(default)]
+    pub isrc: Option<String>,
+    /// Quality Tidal actually served, known once the stream has been resolved for playback.
+    #[serde(skip)]
+    pub served_quality: std::sync::Arc<std::sync::Mutex<Option<AudioQuality>>>,
+}
+
+/// Why a track was left out when queuing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    RegionLocked,
+    NotStreamReady,
+    Video,
+    StreamError,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::RegionLocked => "region-locked",
+            Self::NotStreamReady => "not stream-ready",
+            Self::Video => "video",
+            Self::StreamError => "stream error",
+        })
+    }
+}
+
+/// How many tracks of an album or playlist were left out, by reason.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SkippedTracks {
+    pub region_locked: usize,
+    pub not_stream_ready: usize,
+    pub videos: usize,
+    pub stream_errors: usize,
+    /// Tracks from another service with no match on Tidal.
+    pub not_found: Vec<String>,
+}
+
+impl SkippedTracks {
+    pub fn record(&mut self, reason: SkipReason) {
+        match reason {
+            SkipReason::RegionLocked => self.region_locked += 1,
+            SkipReason::NotStreamReady => self.not_stream_ready += 1,
+            SkipReason::Video => self.videos += 1,
+            SkipReason::StreamError => self.stream_errors += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.region_locked
+            + self.not_stream_ready
+            + self.videos
+            + self.stream_errors
+            + self.not_found.len()
+    }
+
+    /// A sentence for the user, such as `Skipped 3 tracks: 2 region-locked, 1 video.` Tracks
+    /// that were not found are named, up to a few.
+    pub fn report(&self) -> Option<String> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+
+        let reasons = [
+            (self.region_locked, SkipReason::RegionLocked),
+            (self.not_stream_ready, SkipReason::NotStreamReady),
+            (self.videos, SkipReason::Video),
+            (self.stream_errors, SkipReason::StreamError),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| match (count, reason) {
+            (1, _) | (_, SkipReason::RegionLocked | SkipReason::NotStreamReady) => {
+                format!("{count} {reason}")
+            }
+            _ => format!("{count} {reason}s"),
+        })
+        .chain((!self.not_found.is_empty()).then(|| self.not_found_report()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+        Some(format!(
+            "Skipped {total} {}: {reasons}.",
+            if total == 1 { "track" } else { "tracks" }
+        ))
+    }
+
+    fn not_found_report(&self) -> String {
+        const NAMED_LIMIT: usize = 5;
+
+        let mut names = self
+            .not_found
+            .iter()
+            .take(NAMED_LIMIT)
+            .cloned()
+            .collect::<Vec<_>>();
+        if self.not_found.len() > NAMED_LIMIT {
+            names.push(format!("{} more", self.not_found.len() - NAMED_LIMIT));
+        }
+
+        format!(
+            "{} not found on Tidal ({})",
+            self.not_found.len(),
+            names.join("; ")
+        )
+    }
+}
+
+/// Tracks found for a query, along with any the lookup had to leave out.
+#[derive(Debug, Default)]
+pub struct FoundTracks {
+    pub tracks: Vec<Track>,
+    pub skipped: SkippedTracks,
+    /// Linked tracks without a confident match, for the user to choose from.
+    pub suggestions: Vec<MatchSuggestion>,
+}
+
+impl From<Vec<Track>> for FoundTracks {
+    fn from(tracks: Vec<Track>) -> Self {
+        Self {
+            tracks,
+            ..Default::default()
+        }
+    }
+}
+
+/// The closest Tidal tracks for a linked track that had no confident match.
+#[derive(Debug)]
+pub struct MatchSuggestion {
+    /// The linked track, as `Artist - Title`.
+    pub source: String,
+    pub candidates: Vec<Track>,
 }
 
 #[derive(Debug, Clone)]
@@ -90,52 +660,39 @@ pub struct TrackSummary {
     pub duration: u32,
 }
 
-impl Track {
-    pub async fn from_track_id(
-        session: &crate::session::Session,
-        track_response: &TidalTrackResponse,
-    ) -> Result<Self, Error> {
-        Self::from_track_response(
-            &session.client,
-            &session.session_id,
-            &session.country_code,
-            track_response,
-        )
-        .await
-    }
-
-    pub async fn from_track_response(
-        client: &reqwest::Client,
-        session_id: &str,
-        country_code: &str,
-        track_response: &TidalTrackResponse,
-    ) -> Result<Self, Error> {
-        let track_id = track_response.id();
-
-        let url = format!(
-            "https://api.tidal.com/v1/tracks/{}/urlpostpaywall",
-            track_id
-        );
-
-        let params = [
-            ("sessionId", session_id),
-            ("countryCode", country_code),
-            ("urlusagemode", "STREAM"),
-            ("audioquality", "HIGH"),
-            ("assetpresentation", "FULL"),
-        ];
+#[derive(Debug, Clone)]
+pub struct ArtistSummary {
+    pub id: String,
+    pub name: String,
+}
 
-        let response = client
-            .get(&url)
-            .query(&params)
-            .send()
-            .await?
-            .error_for_status()?;
+#[derive(Debug, Clone)]
+pub struct PlaylistSummary {
+    pub id: String,
+    pub title: String,
+    pub track_count: u32,
+}
 
-        let stream_response = response.json::<StreamUrlResponse>().await?;
+#[derive(Debug, Clone)]
+pub struct AlbumSummary {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub track_count: u32,
+    pub release_year: Option<String>,
+}
 
+impl Track {
+    pub fn from_track_response(
+        track_response: &TidalTrackResponse,
+        audio_quality: AudioQuality,
+    ) -> Result<Self, Error> {
         let title = track_response.title.clone();
 
+        if let Some(reason) = track_response.unavailable_reason() {
+            return Err(format!("Tidal cannot stream {title}: {reason}").into());
+        }
+
         let artist = track_response
             .artists
             .first()
@@ -152,20 +709,216 @@ impl Track {
 
         let duration = track_response.duration;
 
-        let stream_url = stream_response
-            .urls
-            .into_iter()
-            .next()
-            .ok_or("Expected stream URL")?;
-
         Ok(Track {
+            id: track_response.id(),
             title,
             artist,
             featured_artists,
             duration,
-            stream_url,
+            audio_quality,
+            isrc: track_response.isrc.clone().filter(|isrc| !isrc.is_empty()),
+            served_quality: Default::default(),
         })
     }
+
+    pub fn summary(&self) -> TrackSummary {
+        TrackSummary {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            featured_artists: self.featured_artists.clone(),
+            duration: self.duration,
+        }
+    }
+
+    /// The quality that was actually played, or the requested one before playback starts.
+    pub fn playback_quality(&self) -> AudioQuality {
+        self.served_quality
+            .lock()
+            .unwrap()
+            .unwrap_or(self.audio_quality)
+    }
+}
+
+/// Resolves a track's signed stream URL right before playback, since Tidal's URLs expire.
+/// The account is picked at that point too, so a queued track survives its account failing.
+pub struct TidalStreamSource {
+    tidal: std::sync::Arc<crate::session_pool::SessionPool>,
+    guild_id: poise::serenity_prelude::GuildId,
+    track: Track,
+}
+
+impl TidalStreamSource {
+    pub fn new(
+        tidal: std::sync::Arc<crate::session_pool::SessionPool>,
+        guild_id: poise::serenity_prelude::GuildId,
+        track: Track,
+    ) -> Self {
+        Self {
+            tidal,
+            guild_id,
+            track,
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl crate::ffmpeg_spool::StreamSource for TidalStreamSource {
+    async fn stream_url(&self, force_refresh: bool) -> Result<String, Error> {
+        let (stream_url, served_quality) = self
+            .tidal
+            .session(Some(self.guild_id))
+            .find_stream_url(&self.track.id, self.track.audio_quality, force_refresh)
+            .await?;
+
+        *self.track.served_quality.lock().unwrap() = Some(served_quality);
+        Ok(stream_url)
+    }
+}
+
+/// Requests a stream for the track, stepping down through lower qualities until one is available.
+pub async fn resolve_stream_url(
+    http: &crate::http::TidalHttp,
+    api_base_url: &str,
+    authorization: &str,
+    session_id: &str,
+    country_code: &str,
+    track_id: &str,
+    audio_quality: AudioQuality,
+) -> Result<(String, AudioQuality), Error> {
+    let mut last_error = None;
+    for quality in audio_quality.fallback_chain() {
+        let result = match request_playback_info(
+            http,
+            api_base_url,
+            authorization,
+            session_id,
+            country_code,
+            track_id,
+            quality,
+        )
+        .await
+        {
+            Ok(stream) => Ok(stream),
+            // Lower tiers will be rejected too; let the caller refresh the token instead. A
+            // 401 or 403 for any other reason falls through to the next tier.
+            Err(error) if crate::http::is_auth_error(&error) => return Err(error),
+            Err(error) => {
+                tracing::debug!(
+                    track_id,
+                    quality = quality.api_name(),
+                    %error,
+                    "Playback info unavailable; trying legacy stream URL"
+                );
+                request_stream_url(
+                    http,
+                    api_base_url,
+                    session_id,
+                    country_code,
+                    track_id,
+                    quality,
+                )
+                .await
+            }
+        };
+
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(error) if crate::http::is_auth_error(&error) => return Err(error),
+            Err(error) => {
+                tracing::debug!(
+                    track_id,
+                    quality = quality.api_name(),
+                    %error,
+                    "Stream quality unavailable; stepping down"
+                );
+                last_error = Some(error);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| "Expected stream URL".into()))
+}
+
+async fn request_playback_info(
+    http: &crate::http::TidalHttp,
+    api_base_url: &str,
+    authorization: &str,
+    session_id: &str,
+    country_code: &str,
+    track_id: &str,
+    quality: AudioQuality,
+) -> Result<(String, AudioQuality), Error> {
+    let url = crate::http::endpoint(
+        api_base_url,
+        &format!("tracks/{}/playbackinfopostpaywall", track_id),
+    );
+
+    let params = [
+        ("sessionId", session_id),
+        ("countryCode", country_code),
+        ("playbackmode", "STREAM"),
+        ("audioquality", quality.api_name()),
+        ("assetpresentation", "FULL"),
+    ];
+
+    let response = http
+        .send(
+            http.get(&url)
+                .header("Authorization", authorization)
+                .query(&params),
+        )
+        .await?;
+
+    let playback_info = response.json::<PlaybackInfoResponse>().await?;
+    let manifest =
+        StreamManifest::parse(&playback_info.manifest_mime_type, &playback_info.manifest)?;
+
+    let served_quality = playback_info
+        .audio_quality
+        .as_deref()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(quality);
+
+    Ok((manifest.ffmpeg_input(), served_quality))
+}
+
+async fn request_stream_url(
+    http: &crate::http::TidalHttp,
+    api_base_url: &str,
+    session_id: &str,
+    country_code: &str,
+    track_id: &str,
+    quality: AudioQuality,
+) -> Result<(String, AudioQuality), Error> {
+    let url = crate::http::endpoint(api_base_url, &format!("tracks/{}/urlpostpaywall", track_id));
+
+    let params = [
+        ("sessionId", session_id),
+        ("countryCode", country_code),
+        ("urlusagemode", "STREAM"),
+        ("audioquality", quality.api_name()),
+        ("assetpresentation", "FULL"),
+    ];
+
+    let response = http.send(http.get(&url).query(&params)).await?;
+
+    let stream_response = response.json::<StreamUrlResponse>().await?;
+
+    // Tidal may quietly serve a lower tier than requested, so prefer what it reports
+    let served_quality = stream_response
+        .audio_quality
+        .as_deref()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(quality);
+
+    let stream_url = stream_response
+        .urls
+        .into_iter()
+        .next()
+        .ok_or("Expected stream URL")?;
+
+    Ok((stream_url, served_quality))
 }
 
 #[cfg(test)]
@@ -209,6 +962,120 @@ mod tests {
         assert!(track.is_video());
     }
 
+    #[test]
+    fn reads_availability_flags() {
+        let parse = |flags: &str| -> TidalTrackResponse {
+            serde_json::from_str(&format!(
+                r#"{{"id": 1, "title": "Song", "artists": [{{"name": "Artist"}}], "duration": 1{flags}}}"#
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(parse("").unavailable_reason(), None);
+        assert_eq!(
+            parse(r#", "allowStreaming": true, "streamReady": true"#).unavailable_reason(),
+            None
+        );
+        assert_eq!(
+            parse(r#", "allowStreaming": false, "streamReady": true"#).unavailable_reason(),
+            Some(SkipReason::RegionLocked)
+        );
+        assert_eq!(
+            parse(r#", "allowStreaming": true, "streamReady": false"#).unavailable_reason(),
+            Some(SkipReason::NotStreamReady)
+        );
+        assert!(
+            Track::from_track_response(&parse(r#", "streamReady": false"#), AudioQuality::High)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn reports_skipped_tracks_by_reason() {
+        let mut skipped = SkippedTracks::default();
+        assert_eq!(skipped.report(), None);
+
+        skipped.record(SkipReason::Video);
+        assert_eq!(
+            skipped.report().as_deref(),
+            Some("Skipped 1 track: 1 video.")
+        );
+
+        for reason in [
+            SkipReason::RegionLocked,
+            SkipReason::RegionLocked,
+            SkipReason::NotStreamReady,
+            SkipReason::Video,
+            SkipReason::StreamError,
+        ] {
+            skipped.record(reason);
+        }
+        assert_eq!(
+            skipped.report().as_deref(),
+            Some(
+                "Skipped 6 tracks: 2 region-locked, 1 not stream-ready, 2 videos, 1 stream error."
+            )
+        );
+
+        let not_found = SkippedTracks {
+            not_found: (1..=7)
+                .map(|index| format!("Artist - Song {index}"))
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            not_found.report().as_deref(),
+            Some(
+                "Skipped 7 tracks: 7 not found on Tidal (Artist - Song 1; Artist - Song 2; \
+                 Artist - Song 3; Artist - Song 4; Artist - Song 5; 2 more)."
+            )
+        );
+    }
+
+    #[test]
+    fn builds_track_metadata_without_resolving_stream() {
+        let track_response: TidalTrackResponse = serde_json::from_str(
+            r#"{
+                "id": 123456789,
+                "title": "Song Title",
+                "artists": [{"name": "Main Artist"}, {"name": "Guest Artist"}],
+                "duration": 185,
+                "isrc": "USABC1234567"
+            }"#,
+        )
+        .unwrap();
+
+        let track = Track::from_track_response(&track_response, AudioQuality::Lossless).unwrap();
+
+        assert_eq!(track.id, "123456789");
+        assert_eq!(track.isrc.as_deref(), Some("USABC1234567"));
+        assert_eq!(track.featured_artists, vec!["Guest Artist".to_string()]);
+        assert_eq!(track.playback_quality(), AudioQuality::Lossless);
+
+        *track.served_quality.lock().unwrap() = Some(AudioQuality::High);
+        assert_eq!(track.playback_quality(), AudioQuality::High);
+    }
+
+    #[test]
+    fn summarizes_artist_albums() {
+        let album: TidalAlbumResponse = serde_json::from_str(
+            r#"{
+                "id": 55555,
+                "title": "Album Title",
+                "artists": [{"name": "Main Artist"}],
+                "numberOfTracks": 12,
+                "releaseDate": "2013-05-17"
+            }"#,
+        )
+        .unwrap();
+        let summary = album.summary();
+
+        assert_eq!(summary.id, "55555");
+        assert_eq!(summary.artist, "Main Artist");
+        assert_eq!(summary.track_count, 12);
+        assert_eq!(summary.release_year.as_deref(), Some("2013"));
+    }
+
     #[test]
     fn deserializes_stream_url_response() {
         let stream_response: StreamUrlResponse = serde_json::from_str(
@@ -225,5 +1092,191 @@ mod tests {
                 "https://example.com/stream-two".to_string()
             ]
         );
+        assert_eq!(stream_response.audio_quality, None);
+    }
+
+    #[test]
+    fn deserializes_served_stream_quality() {
+        let stream_response: StreamUrlResponse = serde_json::from_str(
+            r#"{
+                "urls": ["https://example.com/stream"],
+                "audioQuality": "LOSSLESS"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            stream_response.audio_quality.as_deref().map(str::parse),
+            Some(Ok(AudioQuality::Lossless))
+        );
+    }
+
+    #[test]
+    fn audio_quality_falls_back_to_lower_tiers() {
+        assert_eq!(
+            AudioQuality::HiRes.fallback_chain().collect::<Vec<_>>(),
+            vec![
+                AudioQuality::HiRes,
+                AudioQuality::Lossless,
+                AudioQuality::High,
+                AudioQuality::Low
+            ]
+        );
+        assert_eq!(
+            AudioQuality::High.fallback_chain().collect::<Vec<_>>(),
+            vec![AudioQuality::High, AudioQuality::Low]
+        );
+    }
+
+    #[test]
+    fn parses_audio_quality_names() {
+        assert_eq!("hi_res".parse(), Ok(AudioQuality::HiRes));
+        assert_eq!(" LOSSLESS ".parse(), Ok(AudioQuality::Lossless));
+        assert!("ultra".parse::<AudioQuality>().is_err());
+    }
+
+    const DASH_FLAC_MANIFEST: &str = r#"<?xml version='1.0' encoding='UTF-8'?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:isoff-main:2011" type="static" mediaPresentationDuration="PT3M5.000S" minBufferTime="PT3.993S">
+  <Period id="0">
+    <AdaptationSet id="0" contentType="audio" mimeType="audio/mp4" segmentAlignment="true">
+      <Representation id="FLAC,44100,16" codecs="flac" bandwidth="895231" audioSamplingRate="44100">
+        <SegmentTemplate timescale="44100" initialization="https://sp-ad-cf.audio.tidal.com/mediatracks/abc/0.mp4?token=a&amp;sig=b" media="https://sp-ad-cf.audio.tidal.com/mediatracks/abc/$Number$.mp4?token=a&amp;sig=b" startNumber="1">
+          <SegmentTimeline>
+            <S d="176128" r="2"/>
+            <S d="32000"/>
+          </SegmentTimeline>
+        </SegmentTemplate>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    const DASH_DURATION_MANIFEST: &str = r#"<MPD mediaPresentationDuration="PT1M0.5S">
+  <Period>
+    <BaseURL>https://example.com/tracks/42/</BaseURL>
+    <AdaptationSet mimeType="audio/mp4">
+      <Representation id="mp4a" codecs="mp4a.40.2" bandwidth="320000">
+        <SegmentTemplate timescale="1000" duration="20000" startNumber="0" initialization="$RepresentationID$/init.mp4" media="$RepresentationID$/$Bandwidth$-$Number$.m4s"/>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    fn encode(manifest: &str) -> String {
+        base64::engine::general_purpose::STANDARD.encode(manifest)
+    }
+
+    #[test]
+    fn parses_bts_manifest() {
+        let manifest = StreamManifest::parse(
+            BTS_MANIFEST_MIME_TYPE,
+            &encode(
+                r#"{
+                    "mimeType": "audio/flac",
+                    "codecs": "flac",
+                    "encryptionType": "NONE",
+                    "urls": ["https://example.com/track.flac"]
+                }"#,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(manifest.codecs.as_deref(), Some("flac"));
+        assert_eq!(manifest.ffmpeg_input(), "https://example.com/track.flac");
+    }
+
+    #[test]
+    fn rejects_encrypted_bts_manifest() {
+        let result = StreamManifest::parse(
+            BTS_MANIFEST_MIME_TYPE,
+            &encode(r#"{"encryptionType": "OLD_AES", "urls": ["https://example.com/track.flac"]}"#),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parses_segmented_flac_dash_manifest() {
+        let manifest =
+            StreamManifest::parse(DASH_MANIFEST_MIME_TYPE, &encode(DASH_FLAC_MANIFEST)).unwrap();
+
+        assert_eq!(manifest.codecs.as_deref(), Some("flac"));
+        assert_eq!(manifest.urls.len(), 5);
+        assert_eq!(
+            manifest.urls[0],
+            "https://sp-ad-cf.audio.tidal.com/mediatracks/abc/0.mp4?token=a&sig=b"
+        );
+        assert_eq!(
+            manifest.urls[4],
+            "https://sp-ad-cf.audio.tidal.com/mediatracks/abc/4.mp4?token=a&sig=b"
+        );
+        assert!(manifest.ffmpeg_input().starts_with(
+            "concat:https://sp-ad-cf.audio.tidal.com/mediatracks/abc/0.mp4?token=a&sig=b|"
+        ));
+    }
+
+    #[test]
+    fn counts_dash_segments_from_duration() {
+        let manifest =
+            StreamManifest::parse(DASH_MANIFEST_MIME_TYPE, &encode(DASH_DURATION_MANIFEST))
+                .unwrap();
+
+        assert_eq!(
+            manifest.urls,
+            vec![
+                "https://example.com/tracks/42/mp4a/init.mp4".to_string(),
+                "https://example.com/tracks/42/mp4a/320000-0.m4s".to_string(),
+                "https://example.com/tracks/42/mp4a/320000-1.m4s".to_string(),
+                "https://example.com/tracks/42/mp4a/320000-2.m4s".to_string(),
+                "https://example.com/tracks/42/mp4a/320000-3.m4s".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_open_ended_dash_segments_until_the_end() {
+        let manifest = StreamManifest::parse(
+            DASH_MANIFEST_MIME_TYPE,
+            &encode(
+                r#"<MPD mediaPresentationDuration="PT10S">
+  <Period>
+    <AdaptationSet mimeType="audio/mp4">
+      <Representation id="FLAC" codecs="flac" bandwidth="900000">
+        <SegmentTemplate timescale="1000" initialization="https://example.com/0.mp4" media="https://example.com/$Number$.mp4" startNumber="1">
+          <SegmentTimeline>
+            <S d="4000" r="-1"/>
+          </SegmentTimeline>
+        </SegmentTemplate>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.urls,
+            vec![
+                "https://example.com/0.mp4".to_string(),
+                "https://example.com/1.mp4".to_string(),
+                "https://example.com/2.mp4".to_string(),
+                "https://example.com/3.mp4".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_manifest_types() {
+        assert!(
+            StreamManifest::parse("application/vnd.apple.mpegurl", &encode("#EXTM3U")).is_err()
+        );
+    }
+
+    #[test]
+    fn parses_iso8601_durations() {
+        assert_eq!(parse_iso8601_duration("PT3M5.000S"), Some(185.0));
+        assert_eq!(parse_iso8601_duration("PT1H0.5S"), Some(3600.5));
+        assert_eq!(parse_iso8601_duration("P1D"), None);
     }
 }
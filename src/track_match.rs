@@ -0,0 +1,271 @@
+//! Scores Tidal search results against a track from another service, so links are not matched
+//! to whatever the search happens to rank first, such as a karaoke or cover version.
+
+use crate::track::Track;
+use crate::url_handler::ExternalTrack;
+use std::collections::HashSet;
+
+/// Matches at or above this score are queued without asking.
+pub const CONFIDENT_SCORE: f64 = 0.7;
+/// Matches below this score are not offered at all.
+pub const SUGGESTION_SCORE: f64 = 0.4;
+/// Versions that should only match a source of the same kind.
+const VERSION_KEYWORDS: &[&str] = &[
+    "live",
+    "remix",
+    "karaoke",
+    "instrumental",
+    "acoustic",
+    "cover",
+];
+/// Durations this close count as the same recording.
+const DURATION_TOLERANCE_SECS: u32 = 3;
+/// Durations this far apart get no credit. Music videos often run longer than the track.
+const DURATION_LIMIT_SECS: u32 = 30;
+
+#[derive(Debug)]
+pub enum TrackMatch {
+    Confident(Track),
+    /// The plausible candidates, best first, none of them a confident match.
+    Uncertain(Vec<Track>),
+    NotFound,
+}
+
+/// Picks the best scoring candidate. Ties keep the search order.
+pub fn best_match(source: &ExternalTrack, candidates: Vec<Track>) -> TrackMatch {
+    let mut scored = candidates
+        .into_iter()
+        .map(|candidate| (score(source, &candidate), candidate))
+        .filter(|(score, _)| *score >= SUGGESTION_SCORE)
+        .collect::<Vec<_>>();
+    scored.sort_by(|(left, _), (right, _)| right.total_cmp(left));
+
+    match scored.first() {
+        None => TrackMatch::NotFound,
+        Some((score, _)) if *score >= CONFIDENT_SCORE => {
+            TrackMatch::Confident(scored.swap_remove(0).1)
+        }
+        Some(_) => TrackMatch::Uncertain(scored.into_iter().map(|(_, track)| track).collect()),
+    }
+}
+
+/// How well `candidate` matches `source`, from 0 to 1. The title has to match for any score;
+/// the artist, duration, and version keywords decide how far to trust it.
+pub fn score(source: &ExternalTrack, candidate: &Track) -> f64 {
+    let title = similarity(&title_words(&source.title), &title_words(&candidate.title));
+
+    let source_artist = artist_words(&source.artist);
+    let artist = if source_artist.is_empty() {
+        0.5
+    } else {
+        let main_artist = artist_words(&candidate.artist);
+        let all_artists = std::iter::once(&candidate.artist)
+            .chain(&candidate.featured_artists)
+            .flat_map(|artist| artist_words(artist))
+            .collect();
+        similarity(&source_artist, &main_artist).max(similarity(&source_artist, &all_artists))
+    };
+
+    let text = title * (0.5 + 0.5 * artist);
+    let score = match source.duration {
+        Some(duration) => 0.85 * text + 0.15 * duration_closeness(duration, candidate.duration),
+        None => text,
+    };
+
+    let candidate_versions = version_keywords(&format!("{} {}", candidate.title, candidate.artist));
+    let version_mismatches = VERSION_KEYWORDS
+        .iter()
+        .filter(|keyword| source.versions.contains(keyword) != candidate_versions.contains(keyword))
+        .count();
+
+    score * 0.5_f64.powi(version_mismatches as i32)
+}
+
+/// The version keywords, such as `live`, that appear as words in `text`.
+pub fn version_keywords(text: &str) -> Vec<&'static str> {
+    VERSION_KEYWORDS
+        .iter()
+        .copied()
+        .filter(|keyword| has_word(text, keyword))
+        .collect()
+}
+
+/// Dice coefficient of two word sets.
+fn similarity(left: &HashSet<String>, right: &HashSet<String>) -> f64 {
+    if left.is_empty() || right.is_empty() {
+        return 0.0;
+    }
+
+    let shared = left.intersection(right).count();
+    2.0 * shared as f64 / (left.len() + right.len()) as f64
+}
+
+fn duration_closeness(source: u32, candidate: u32) -> f64 {
+    let delta = source.abs_diff(candidate);
+    if delta <= DURATION_TOLERANCE_SECS {
+        1.0
+    } else if delta >= DURATION_LIMIT_SECS {
+        0.0
+    } else {
+        f64::from(DURATION_LIMIT_SECS - delta)
+            / f64::from(DURATION_LIMIT_SECS - DURATION_TOLERANCE_SECS)
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Title words without bracketed versions or featured artists, which are scored separately.
+fn title_words(title: &str) -> HashSet<String> {
+    let mut core = String::new();
+    let mut depth = 0_usize;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => core.push(c),
+            _ => {}
+        }
+    }
+
+    words(&core)
+        .take_while(|word| !matches!(word.as_str(), "feat" | "ft" | "featuring"))
+        .collect()
+}
+
+/// Artist words, without the `VEVO` and ` - Topic` markers of YouTube channel names.
+fn artist_words(artist: &str) -> HashSet<String> {
+    words(artist)
+        .filter(|word| word != "topic" && word != "vevo")
+        .map(|word| match word.strip_suffix("vevo") {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => word,
+        })
+        .collect()
+}
+
+fn has_word(text: &str, keyword: &str) -> bool {
+    words(text).any(|word| word == keyword)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(title: &str, artist: &str, duration: Option<u32>) -> ExternalTrack {
+        ExternalTrack::new(
+            title.to_string(),
+            artist.to_string(),
+            String::new(),
+            None,
+            duration,
+        )
+        .unwrap()
+    }
+
+    fn candidate(id: &str, title: &str, artist: &str, duration: u32) -> Track {
+        Track {
+            id: id.to_string(),
+            title: title.to_string(),
+            artist: artist.to_string(),
+            featured_artists: Vec::new(),
+            duration,
+            audio_quality: crate::track::AudioQuality::High,
+            isrc: None,
+            served_quality: Default::default(),
+        }
+    }
+
+    #[test]
+    fn scores_exact_matches_as_confident() {
+        let source = source("anthem", "bandvevo", Some(215));
+
+        assert!((score(&source, &candidate("1", "Anthem", "Band", 213)) - 1.0).abs() < 1e-9);
+        assert!(
+            score(
+                &source,
+                &candidate("2", "Anthem (feat. Guest)", "Band", 230)
+            ) >= CONFIDENT_SCORE
+        );
+    }
+
+    #[test]
+    fn penalizes_other_versions_artists_and_durations() {
+        let source = source("anthem", "band", Some(215));
+        let original = score(&source, &candidate("1", "Anthem", "Band", 215));
+
+        for other in [
+            candidate("2", "Anthem (Karaoke Version)", "Band", 215),
+            candidate("3", "Anthem (Live)", "Band", 215),
+            candidate("4", "Anthem", "Tribute Players", 215),
+            candidate("5", "Anthem", "Band", 320),
+            candidate("6", "Another Song", "Band", 215),
+        ] {
+            assert!(
+                score(&source, &other) < original,
+                "{} by {} should score lower",
+                other.title,
+                other.artist
+            );
+        }
+
+        assert_eq!(
+            score(&source, &candidate("6", "Another Song", "Band", 215)),
+            0.15
+        );
+    }
+
+    #[test]
+    fn reads_versions_from_the_raw_title() {
+        let live = candidate("1", "Anthem (Live)", "Band", 215);
+        let studio = candidate("2", "Anthem", "Band", 215);
+        // YouTube titles arrive cleaned of their brackets
+        let source = source("anthem", "band", None).with_raw_title("Band - Anthem (Live)");
+
+        assert!(score(&source, &live) > score(&source, &studio));
+    }
+
+    #[test]
+    fn picks_the_best_candidate_or_offers_the_plausible_ones() {
+        let source = source("anthem", "band", None);
+
+        let found = best_match(
+            &source,
+            vec![
+                candidate("1", "Anthem (Karaoke Version)", "Karaoke Stars", 215),
+                candidate("2", "Anthem", "Band", 215),
+            ],
+        );
+        assert!(matches!(found, TrackMatch::Confident(track) if track.id == "2"));
+
+        let found = best_match(
+            &source,
+            vec![
+                candidate("1", "Anthem (Live)", "Band", 215),
+                candidate("2", "Anthem", "Tribute Players", 215),
+                candidate("3", "Something Else", "Nobody", 215),
+            ],
+        );
+        let TrackMatch::Uncertain(candidates) = found else {
+            panic!("expected an uncertain match, got {found:?}");
+        };
+        assert_eq!(
+            candidates
+                .iter()
+                .map(|track| track.id.as_str())
+                .collect::<Vec<_>>(),
+            ["1", "2"]
+        );
+
+        assert!(matches!(
+            best_match(
+                &source,
+                vec![candidate("3", "Something Else", "Nobody", 215)]
+            ),
+            TrackMatch::NotFound
+        ));
+    }
+}
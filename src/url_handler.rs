@@ -1,89 +1,348 @@
 use crate::commands::Error;
-use crate::track::Track;
-use html_escape::decode_html_entities;
-use lol_html::{HtmlRewriter, Settings, element, text};
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::track::{AudioQuality, FoundTracks, MatchSuggestion};
+use crate::track_match::TrackMatch;
 use std::sync::OnceLock;
-static RE: OnceLock<regex::Regex> = OnceLock::new();
-static FEAT_RE: OnceLock<regex::Regex> = OnceLock::new();
-
-#[derive(Debug)]
-pub struct YouTubeMetadata {
-    title: String,
-    artist: String,
-    album: String,
+static RESOLVERS: OnceLock<UrlResolvers> = OnceLock::new();
+
+/// A track from another service, to be matched on Tidal by its details.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalTrack {
+    pub title: String,
+    pub artist: String,
+    /// Empty when the service does not say.
+    pub album: String,
+    pub isrc: Option<String>,
+    /// Length in seconds, when the service says.
+    pub duration: Option<u32>,
+    /// Version keywords such as `live` or `remix`, kept apart from the title since some
+    /// services' titles are cleaned of the brackets that name the version.
+    pub versions: Vec<&'static str>,
 }
 
+impl ExternalTrack {
+    /// `None` for entries without a title, which cannot be searched for. An ISRC that is not
+    /// well formed is dropped; hyphens and lowercase letters are accepted.
+    pub fn new(
+        title: String,
+        artist: String,
+        album: String,
+        isrc: Option<String>,
+        duration: Option<u32>,
+    ) -> Option<Self> {
+        (!title.trim().is_empty()).then(|| Self {
+            versions: crate::track_match::version_keywords(&format!("{title} {artist}")),
+            title,
+            artist,
+            album,
+            isrc: isrc.as_deref().and_then(normalize_isrc),
+            duration,
+        })
+    }
+
+    /// Also reads version keywords from the title as the service showed it, before cleaning.
+    pub fn with_raw_title(mut self, raw_title: &str) -> Self {
+        for keyword in crate::track_match::version_keywords(raw_title) {
+            if !self.versions.contains(&keyword) {
+                self.versions.push(keyword);
+            }
+        }
+        self
+    }
+
+    pub fn label(&self) -> String {
+        if self.artist.is_empty() {
+            self.title.clone()
+        } else {
+            format!("{} - {}", self.artist, self.title)
+        }
+    }
+}
+
+/// Finds the tracks behind a link to a supported service. Anything else, including text that is
+/// not a URL, resolves to nothing so the caller can search for it instead.
 pub async fn handle_url(
-    session: &mut crate::session::Session,
+    session: &std::sync::Arc<crate::session::Session>,
     input: &str,
     collection_track_fetch_concurrency: usize,
-) -> Result<Vec<Track>, Error> {
-    let parsed_url = url::Url::parse(input);
-
-    match parsed_url {
-        Ok(url) => {
-            let domain = url.domain().unwrap_or("");
-            let youtube_domains = ["youtube.com", "youtu.be"];
-
-            if youtube_domains.iter().any(|&d| domain.contains(d)) {
-                tracing::info!(url = input, "Detected YouTube URL; extracting metadata");
-                let metadata = extract_youtube_metadata(input).await?;
-                Ok(session
-                    .find_track_by_details(&metadata.title, &metadata.artist, &metadata.album)
-                    .await?
-                    .into_iter()
-                    .collect())
-            } else if domain.contains("tidal.com") {
-                tracing::info!(url = input, "Detected Tidal URL; resolving");
-                match parse_tidal_resource(&url) {
-                    Some((TidalResource::Track, id)) => {
-                        Ok(vec![session.find_track_by_id(&id).await?])
-                    }
-                    Some((TidalResource::Playlist, id)) => {
-                        session
-                            .find_collection_tracks(
-                                "playlists",
-                                &id,
-                                collection_track_fetch_concurrency,
-                            )
-                            .await
-                    }
-                    Some((TidalResource::Album, id)) => {
-                        session
-                            .find_collection_tracks(
-                                "albums",
-                                &id,
-                                collection_track_fetch_concurrency,
-                            )
-                            .await
-                    }
-                    None => match extract_tidal_info(input).await? {
-                        Some(search) => session.find_tracks(&search, 1).await,
-                        _ => Ok(Vec::new()),
-                    },
-                }
-            } else {
-                tracing::warn!(url = %url, "Unsupported URL host");
-                Ok(Vec::new())
+    audio_quality: AudioQuality,
+) -> Result<FoundTracks, Error> {
+    let Ok(url) = url::Url::parse(input) else {
+        return Ok(FoundTracks::default());
+    };
+
+    let Some(resolver) = builtin_resolvers().find(&url) else {
+        tracing::warn!(url = %url, "Unsupported URL host");
+        return Ok(FoundTracks::default());
+    };
+
+    tracing::debug!(url = input, resolver = resolver.name(), "Resolving URL");
+    resolver
+        .resolve(
+            &url,
+            &ResolveContext {
+                session,
+                collection_track_fetch_concurrency,
+                audio_quality,
+            },
+        )
+        .await
+}
+
+/// Turns links to one service into Tidal tracks. Implement this and add the resolver to
+/// [`builtin_resolvers`] to support another service.
+#[serenity::async_trait]
+pub trait UrlResolver: Send + Sync {
+    /// Names the service in logs.
+    fn name(&self) -> &'static str;
+
+    fn matches(&self, url: &url::Url) -> bool;
+
+    async fn resolve(
+        &self,
+        url: &url::Url,
+        context: &ResolveContext<'_>,
+    ) -> Result<FoundTracks, Error>;
+}
+
+/// What a resolver needs to look tracks up on Tidal.
+pub struct ResolveContext<'a> {
+    pub session: &'a std::sync::Arc<crate::session::Session>,
+    pub collection_track_fetch_concurrency: usize,
+    pub audio_quality: AudioQuality,
+}
+
+impl ResolveContext<'_> {
+    pub async fn find_external_tracks(&self, tracks: Vec<ExternalTrack>) -> FoundTracks {
+        find_external_tracks(
+            self.session,
+            tracks,
+            self.collection_track_fetch_concurrency,
+            self.audio_quality,
+        )
+        .await
+    }
+}
+
+/// Resolvers tried in order; the first that matches a link handles it.
+pub struct UrlResolvers {
+    resolvers: Vec<Box<dyn UrlResolver>>,
+}
+
+impl UrlResolvers {
+    pub fn new(resolvers: Vec<Box<dyn UrlResolver>>) -> Self {
+        Self { resolvers }
+    }
+
+    pub fn find(&self, url: &url::Url) -> Option<&dyn UrlResolver> {
+        self.resolvers
+            .iter()
+            .map(Box::as_ref)
+            .find(|resolver| resolver.matches(url))
+    }
+}
+
+pub fn builtin_resolvers() -> &'static UrlResolvers {
+    RESOLVERS.get_or_init(|| {
+        UrlResolvers::new(vec![
+            Box::new(TidalResolver),
+            Box::new(crate::youtube::YouTubeResolver),
+            Box::new(crate::spotify::SpotifyResolver),
+            Box::new(crate::open_graph::OpenGraphResolver),
+        ])
+    })
+}
+
+/// Whether the URL's host is one of `domains` or a subdomain of one.
+pub fn host_matches(url: &url::Url, domains: &[&str]) -> bool {
+    let Some(host) = url.domain() else {
+        return false;
+    };
+
+    domains.iter().any(|domain| {
+        host == *domain
+            || host
+                .strip_suffix(domain)
+                .is_some_and(|subdomain| subdomain.ends_with('.'))
+    })
+}
+
+/// Plays Tidal tracks, albums, playlists, and artists' top tracks directly. Other Tidal pages
+/// are searched for by their title.
+pub struct TidalResolver;
+
+#[serenity::async_trait]
+impl UrlResolver for TidalResolver {
+    fn name(&self) -> &'static str {
+        "Tidal"
+    }
+
+    fn matches(&self, url: &url::Url) -> bool {
+        host_matches(url, &["tidal.com"])
+    }
+
+    async fn resolve(
+        &self,
+        url: &url::Url,
+        context: &ResolveContext<'_>,
+    ) -> Result<FoundTracks, Error> {
+        let ResolveContext {
+            session,
+            collection_track_fetch_concurrency,
+            audio_quality,
+        } = *context;
+
+        match parse_tidal_resource(url) {
+            Some((TidalResource::Track, id)) => {
+                Ok(vec![session.find_track_by_id(&id, audio_quality).await?].into())
+            }
+            Some((TidalResource::Playlist, id)) => {
+                session
+                    .find_collection_tracks(
+                        "playlists",
+                        &id,
+                        collection_track_fetch_concurrency,
+                        audio_quality,
+                    )
+                    .await
+            }
+            Some((TidalResource::Album, id)) => {
+                session
+                    .find_collection_tracks(
+                        "albums",
+                        &id,
+                        collection_track_fetch_concurrency,
+                        audio_quality,
+                    )
+                    .await
+            }
+            Some((TidalResource::Artist, id)) => session
+                .find_artist_top_tracks(&id, crate::session::ARTIST_TOP_TRACK_LIMIT, audio_quality)
+                .await
+                .map(FoundTracks::from),
+            None => match extract_tidal_info(url.as_str()).await? {
+                Some(search) => session.find_first_track(&search, audio_quality).await,
+                _ => Ok(FoundTracks::default()),
+            },
+        }
+    }
+}
+
+/// Matches tracks from another service on Tidal, a few at a time, keeping their order. Tracks
+/// without a match are reported as not found, and uncertain matches are kept as suggestions.
+pub async fn find_external_tracks(
+    session: &std::sync::Arc<crate::session::Session>,
+    tracks: Vec<ExternalTrack>,
+    concurrency: usize,
+    audio_quality: AudioQuality,
+) -> FoundTracks {
+    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(concurrency));
+    let mut lookups = tokio::task::JoinSet::new();
+
+    for (index, track) in tracks.into_iter().enumerate() {
+        let permit = semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Track lookup semaphore should not close");
+        let session = session.clone();
+
+        lookups.spawn(async move {
+            let _permit = permit;
+            let result = find_external_track(&session, &track, audio_quality).await;
+            (index, track, result)
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(result) = lookups.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(error) => tracing::error!(%error, "Track lookup task failed"),
+        }
+    }
+    results.sort_by_key(|(index, _, _)| *index);
+
+    let mut found = FoundTracks::default();
+    for (_, track, result) in results {
+        match result {
+            Ok(TrackMatch::Confident(tidal_track)) => found.tracks.push(tidal_track),
+            Ok(TrackMatch::Uncertain(candidates)) => {
+                tracing::debug!(
+                    title = %track.title,
+                    artist = %track.artist,
+                    candidates = candidates.len(),
+                    "No confident Tidal match for track"
+                );
+                found.suggestions.push(MatchSuggestion {
+                    source: track.label(),
+                    candidates,
+                });
+            }
+            Ok(TrackMatch::NotFound) => {
+                tracing::debug!(
+                    title = %track.title,
+                    artist = %track.artist,
+                    isrc = ?track.isrc,
+                    "No Tidal match for track"
+                );
+                found.skipped.not_found.push(track.label());
+            }
+            Err(error) => {
+                tracing::warn!(title = %track.title, artist = %track.artist, %error, "Track lookup failed");
+                found.skipped.not_found.push(track.label());
+            }
+        }
+    }
+
+    found
+}
+
+/// Matches a track by its ISRC when it has one, and by searching its details otherwise or when
+/// Tidal has no streamable recording with that ISRC.
+async fn find_external_track(
+    session: &crate::session::Session,
+    track: &ExternalTrack,
+    audio_quality: AudioQuality,
+) -> Result<TrackMatch, Error> {
+    if let Some(isrc) = &track.isrc {
+        match session.find_track_by_isrc(isrc, audio_quality).await {
+            Ok(Some(tidal_track)) => return Ok(TrackMatch::Confident(tidal_track)),
+            Ok(None) => {
+                tracing::debug!(isrc, title = %track.title, "No Tidal track for ISRC; searching");
+            }
+            Err(error) => {
+                tracing::warn!(isrc, title = %track.title, %error, "ISRC lookup failed; searching");
             }
         }
-        Err(_) => Ok(Vec::new()),
     }
+
+    session.find_track_by_details(track, audio_quality).await
+}
+
+/// An ISRC as `CCXXXYYNNNNN`: country, registrant, year and designation code.
+pub fn normalize_isrc(isrc: &str) -> Option<String> {
+    let isrc = isrc.replace('-', "").trim().to_ascii_uppercase();
+    let bytes = isrc.as_bytes();
+    let well_formed = bytes.len() == 12
+        && bytes[..2].iter().all(u8::is_ascii_alphabetic)
+        && bytes[2..5].iter().all(u8::is_ascii_alphanumeric)
+        && bytes[5..].iter().all(u8::is_ascii_digit);
+
+    well_formed.then_some(isrc)
 }
 
 #[derive(Debug, PartialEq, Eq)]
-enum TidalResource {
+pub enum TidalResource {
     Track,
     Playlist,
     Album,
+    Artist,
 }
 
-fn parse_tidal_resource(url: &url::Url) -> Option<(TidalResource, String)> {
+pub fn parse_tidal_resource(url: &url::Url) -> Option<(TidalResource, String)> {
     let segments = url.path_segments()?.collect::<Vec<_>>();
 
-    for resource_name in ["track", "playlist", "album"] {
+    for resource_name in ["track", "playlist", "album", "artist"] {
         if let Some(index) = segments
             .iter()
             .position(|segment| *segment == resource_name)
@@ -92,6 +351,7 @@ fn parse_tidal_resource(url: &url::Url) -> Option<(TidalResource, String)> {
                 "track" => TidalResource::Track,
                 "playlist" => TidalResource::Playlist,
                 "album" => TidalResource::Album,
+                "artist" => TidalResource::Artist,
                 _ => unreachable!(),
             };
 
@@ -105,153 +365,6 @@ fn parse_tidal_resource(url: &url::Url) -> Option<(TidalResource, String)> {
     None
 }
 
-fn clean_youtube_title(title: &str) -> String {
-    let re = RE.get_or_init(|| regex::Regex::new(r"\s*[\[\(].*?[\]\)]").unwrap());
-    let feat_re =
-        FEAT_RE.get_or_init(|| regex::Regex::new(r"(?i)\b(feat|ft|featuring)\b.*").unwrap());
-
-    let no_brackets = re.replace_all(title, "");
-    let no_feat = feat_re.replace_all(&no_brackets, "");
-    no_feat.trim().to_lowercase()
-}
-
-async fn extract_youtube_metadata(url: &str) -> Result<YouTubeMetadata, Error> {
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:150.0) Gecko/20100101 Firefox/150.0")
-        .build()
-        .map_err(|e| Error::from(e.to_string()))?;
-
-    let resp = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| Error::from(e.to_string()))?
-        .text()
-        .await
-        .map_err(|e| Error::from(e.to_string()))?;
-
-    let script_buffer = Rc::new(RefCell::new(String::new()));
-    let json_string = Rc::new(RefCell::new(None));
-
-    let script_buf_handle = Rc::clone(&script_buffer);
-    let json_handle = Rc::clone(&json_string);
-
-    let mut rewriter = HtmlRewriter::new(
-        Settings {
-            element_content_handlers: vec![text!("script", move |t| {
-                let mut buf = script_buf_handle.borrow_mut();
-
-                buf.push_str(t.as_str());
-
-                if t.last_in_text_node() {
-                    if buf.contains("var ytInitialData =")
-                        && let Some(start) = buf.find("var ytInitialData =")
-                    {
-                        let json_part = &buf[start + "var ytInitialData =".len()..];
-                        let trimmed = json_part.trim().trim_end_matches(';');
-
-                        *json_handle.borrow_mut() = Some(trimmed.to_string());
-                    }
-                    buf.clear();
-                }
-                Ok(())
-            })],
-            ..Settings::default()
-        },
-        |_: &[u8]| {},
-    );
-
-    rewriter
-        .write(resp.as_bytes())
-        .map_err(|e| Error::from(e.to_string()))?;
-    rewriter.end().map_err(|e| Error::from(e.to_string()))?;
-
-    let raw_json = json_string
-        .take()
-        .ok_or_else(|| Error::from("Could not find ytInitialData"))?;
-    let data: serde_json::Value =
-        serde_json::from_str(&raw_json).map_err(|e| Error::from(e.to_string()))?;
-
-    // 1. Attempt to get track details if in "Artist - Song" format
-    let video_details = data.pointer(
-        "/playerOverlays/playerOverlayRenderer/videoDetails/playerOverlayVideoDetailsRenderer",
-    );
-
-    if let Some(v) = video_details {
-        let raw_video_title = v
-            .pointer("/title/simpleText")
-            .and_then(|s| s.as_str())
-            .unwrap_or("");
-
-        let clean_video_title = clean_youtube_title(raw_video_title);
-
-        let separators = [" - ", " – ", " — ", " : "];
-        for sep in separators {
-            if clean_video_title.contains(sep) {
-                let parts: Vec<&str> = clean_video_title.splitn(2, sep).collect();
-                if parts.len() == 2 {
-                    return Ok(YouTubeMetadata {
-                        artist: parts[0].trim().to_string(),
-                        title: parts[1].trim().to_string(),
-                        album: "".to_string(),
-                    });
-                }
-            }
-        }
-    }
-
-    // 2. Attempt to get track details inside description
-    if let Some(panels) = data.get("engagementPanels").and_then(|v| v.as_array()) {
-        for panel in panels {
-            let model = panel.pointer("/engagementPanelSectionListRenderer/content/structuredDescriptionContentRenderer/items/2/horizontalCardListRenderer/cards/0/videoAttributeViewModel");
-
-            if let Some(m) = model {
-                let raw_title = m.get("title").and_then(|v| v.as_str()).unwrap_or("");
-                let artist = m
-                    .get("subtitle")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-
-                let title = clean_youtube_title(raw_title);
-
-                if !title.is_empty() && !artist.is_empty() {
-                    return Ok(YouTubeMetadata {
-                        title,
-                        artist,
-                        album: m
-                            .pointer("/secondarySubtitle/content")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_lowercase(),
-                    });
-                }
-            }
-        }
-    }
-
-    // 3. Fallback to uploader and video title
-    if let Some(v) = video_details {
-        let raw_title = v
-            .pointer("/title/simpleText")
-            .and_then(|s| s.as_str())
-            .unwrap_or("");
-        let uploader = v
-            .pointer("/subtitle/runs/0/text")
-            .and_then(|t| t.as_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        return Ok(YouTubeMetadata {
-            title: clean_youtube_title(raw_title),
-            artist: uploader,
-            album: "".to_string(),
-        });
-    }
-
-    Err(Error::from("Could not extract valid metadata"))
-}
-
 async fn extract_tidal_info(url: &str) -> Result<Option<String>, Error> {
     let client = reqwest::Client::builder()
         .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:150.0) Gecko/20100101 Firefox/150.0")
@@ -259,27 +372,13 @@ async fn extract_tidal_info(url: &str) -> Result<Option<String>, Error> {
 
     let resp = client.get(url).send().await?.text().await?;
 
-    let extracted_title = Rc::new(RefCell::new(None));
-    let title_handle = Rc::clone(&extracted_title);
-
-    let mut rewriter = HtmlRewriter::new(
-        Settings {
-            element_content_handlers: vec![element!("meta[property='og:title']", move |el| {
-                if let Some(content) = el.get_attribute("content") {
-                    let decoded = decode_html_entities(&content).into_owned();
-                    *title_handle.borrow_mut() = Some(decoded);
-                }
-                Ok(())
-            })],
-            ..Settings::default()
-        },
-        |_: &[u8]| {},
-    );
-
-    rewriter.write(resp.as_bytes())?;
-    rewriter.end()?;
-
-    Ok(extracted_title.take())
+    parse_tidal_page_title(&resp)
+}
+
+fn parse_tidal_page_title(html: &str) -> Result<Option<String>, Error> {
+    Ok(crate::open_graph::read_page(html)?
+        .meta("og:title")
+        .map(str::to_string))
 }
 
 #[cfg(test)]
@@ -313,22 +412,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_tidal_artist_urls() {
+        assert_eq!(
+            parse("https://tidal.com/browse/artist/123456789"),
+            Some((TidalResource::Artist, "123456789".to_string()))
+        );
+    }
+
     #[test]
     fn ignores_tidal_urls_without_supported_resource() {
-        assert_eq!(parse("https://tidal.com/browse/artist/123456789"), None);
+        assert_eq!(parse("https://tidal.com/browse/mix/0123456789abcdef"), None);
         assert_eq!(parse("https://tidal.com/browse/track/"), None);
     }
 
     #[test]
-    fn cleans_youtube_title_noise() {
+    fn routes_links_to_their_resolver() {
+        let resolver = |url: &str| {
+            builtin_resolvers()
+                .find(&url::Url::parse(url).unwrap())
+                .map(|resolver| resolver.name())
+        };
+
+        assert_eq!(resolver("https://listen.tidal.com/track/1"), Some("Tidal"));
+        assert_eq!(resolver("https://youtu.be/dQw4w9WgXcQ"), Some("YouTube"));
+        assert_eq!(
+            resolver("https://music.youtube.com/watch?v=dQw4w9WgXcQ"),
+            Some("YouTube")
+        );
+        assert_eq!(
+            resolver("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC"),
+            Some("Spotify")
+        );
+        assert_eq!(
+            resolver("https://www.deezer.com/track/3135556"),
+            Some("Open Graph")
+        );
+        assert_eq!(resolver("https://example.com/tidal.com"), None);
+        assert_eq!(resolver("https://nottidal.com/track/1"), None);
+    }
+
+    #[test]
+    fn reads_tidal_page_titles() {
+        let html = include_str!("../tests/fixtures/tidal_mix.html");
+
+        assert_eq!(
+            parse_tidal_page_title(html).unwrap().as_deref(),
+            Some("Song Title by Main Artist")
+        );
+    }
+
+    #[test]
+    fn normalizes_isrcs() {
         assert_eq!(
-            clean_youtube_title("Song Title (Official Video) [HD] feat. Guest Artist"),
-            "song title"
+            normalize_isrc("us-abc-12-34567").as_deref(),
+            Some("USABC1234567")
         );
-        assert_eq!(clean_youtube_title("Another Song ft Guest"), "another song");
         assert_eq!(
-            clean_youtube_title("Third Song FEATURING Someone"),
-            "third song"
+            normalize_isrc("USABC1234567").as_deref(),
+            Some("USABC1234567")
         );
+        assert_eq!(normalize_isrc("USABC123456"), None);
+        assert_eq!(normalize_isrc("1SABC1234567"), None);
+
+        let track = ExternalTrack::new(
+            "Song".to_string(),
+            "Artist".to_string(),
+            String::new(),
+            Some("not an isrc".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(track.isrc, None);
     }
 }
@@ -0,0 +1,595 @@
+use crate::commands::Error;
+use crate::track::FoundTracks;
+use crate::url_handler::{ExternalTrack, ResolveContext, UrlResolver, host_matches};
+use lol_html::{HtmlRewriter, Settings, text};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::OnceLock;
+static RE: OnceLock<regex::Regex> = OnceLock::new();
+static FEAT_RE: OnceLock<regex::Regex> = OnceLock::new();
+static CLIENT_VERSION_RE: OnceLock<regex::Regex> = OnceLock::new();
+static ISRC_RE: OnceLock<regex::Regex> = OnceLock::new();
+static LENGTH_RE: OnceLock<regex::Regex> = OnceLock::new();
+
+const YOUTUBE_BASE_URL: &str = "https://www.youtube.com";
+/// Used for continuation requests when the playlist page does not state its client version.
+const YOUTUBE_CLIENT_VERSION: &str = "2.20250101.00.00";
+/// The most items read from one YouTube playlist.
+const YOUTUBE_PLAYLIST_LIMIT: usize = 500;
+
+#[derive(Debug)]
+pub struct YouTubeMetadata {
+    /// The title as shown on YouTube, which still names versions such as `(Live)`.
+    raw_title: String,
+    title: String,
+    artist: String,
+    album: String,
+    isrc: Option<String>,
+    duration: Option<u32>,
+}
+
+/// Matches YouTube and YouTube Music videos and playlists on Tidal by their titles.
+pub struct YouTubeResolver;
+
+#[serenity::async_trait]
+impl UrlResolver for YouTubeResolver {
+    fn name(&self) -> &'static str {
+        "YouTube"
+    }
+
+    fn matches(&self, url: &url::Url) -> bool {
+        host_matches(url, &["youtube.com", "youtu.be"])
+    }
+
+    async fn resolve(
+        &self,
+        url: &url::Url,
+        context: &ResolveContext<'_>,
+    ) -> Result<FoundTracks, Error> {
+        if let Some(list_id) = youtube_playlist_id(url) {
+            tracing::info!(%url, list_id, "Detected YouTube playlist; reading items");
+            let tracks = extract_youtube_playlist(&list_id).await?;
+            let total = tracks.len();
+            let found = context.find_external_tracks(tracks).await;
+            tracing::info!(
+                list_id,
+                matched = found.tracks.len(),
+                total,
+                "Matched YouTube playlist on Tidal"
+            );
+            return Ok(found);
+        }
+
+        tracing::info!(%url, "Detected YouTube URL; extracting metadata");
+        let metadata = extract_youtube_metadata(url.as_str()).await?;
+        let Some(track) = ExternalTrack::new(
+            metadata.title,
+            metadata.artist,
+            metadata.album,
+            metadata.isrc,
+            metadata.duration,
+        )
+        .map(|track| track.with_raw_title(&metadata.raw_title)) else {
+            return Ok(FoundTracks::default());
+        };
+        Ok(context.find_external_tracks(vec![track]).await)
+    }
+}
+
+/// The ISRC a video description lists, as labels do in `ISRC: CC-XXX-YY-NNNNN` lines.
+fn find_description_isrc(data: &serde_json::Value) -> Option<String> {
+    let isrc_re = ISRC_RE.get_or_init(|| {
+        regex::Regex::new(r"(?i)\bISRC\b\W{0,3}([A-Z]{2}-?[A-Z0-9]{3}-?\d{2}-?\d{5})\b").unwrap()
+    });
+
+    match data {
+        serde_json::Value::Object(object) => object.iter().find_map(|(key, value)| {
+            match key.as_str() {
+                "attributedDescriptionBodyText" | "attributedDescription" => value
+                    .get("content")
+                    .and_then(serde_json::Value::as_str)
+                    .and_then(|content| isrc_re.captures(content))
+                    .and_then(|captures| crate::url_handler::normalize_isrc(&captures[1])),
+                _ => None,
+            }
+            .or_else(|| find_description_isrc(value))
+        }),
+        serde_json::Value::Array(values) => values.iter().find_map(find_description_isrc),
+        _ => None,
+    }
+}
+
+fn clean_youtube_title(title: &str) -> String {
+    let re = RE.get_or_init(|| regex::Regex::new(r"\s*[\[\(].*?[\]\)]").unwrap());
+    let feat_re =
+        FEAT_RE.get_or_init(|| regex::Regex::new(r"(?i)\b(feat|ft|featuring)\b.*").unwrap());
+
+    let no_brackets = re.replace_all(title, "");
+    let no_feat = feat_re.replace_all(&no_brackets, "");
+    no_feat.trim().to_lowercase()
+}
+
+/// Splits a cleaned `artist - title` video title.
+fn split_artist_title(clean_title: &str) -> Option<(String, String)> {
+    let separators = [" - ", " – ", " — ", " : "];
+    separators.iter().find_map(|sep| {
+        let (artist, title) = clean_title.split_once(sep)?;
+        Some((artist.trim().to_string(), title.trim().to_string()))
+    })
+}
+
+/// The list id of a playlist link. Mixes (`RD…`) are generated per viewer and cannot be read
+/// as a playlist, so those links keep resolving their single video.
+fn youtube_playlist_id(url: &url::Url) -> Option<String> {
+    let (_, list_id) = url.query_pairs().find(|(key, _)| key == "list")?;
+    let is_mix = list_id.starts_with("RD");
+    let is_single_video = is_mix && url.query_pairs().any(|(key, _)| key == "v");
+
+    (!list_id.is_empty() && !is_single_video).then(|| list_id.into_owned())
+}
+
+/// Reads the items of a YouTube or YouTube Music playlist, following continuations until the
+/// list ends or [`YOUTUBE_PLAYLIST_LIMIT`] items were read.
+async fn extract_youtube_playlist(list_id: &str) -> Result<Vec<ExternalTrack>, Error> {
+    let client = reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:150.0) Gecko/20100101 Firefox/150.0")
+        .build()?;
+
+    let page = client
+        .get(format!("{YOUTUBE_BASE_URL}/playlist"))
+        .query(&[("list", list_id)])
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let (mut playlist, client_version) = parse_playlist_page(&page)?;
+
+    let mut pages = 1;
+    while let Some(token) = playlist.continuation.take() {
+        if playlist.tracks.len() >= YOUTUBE_PLAYLIST_LIMIT {
+            tracing::info!(
+                list_id,
+                limit = YOUTUBE_PLAYLIST_LIMIT,
+                "YouTube playlist is longer than the limit; truncating"
+            );
+            break;
+        }
+
+        let body = serde_json::json!({
+            "context": {
+                "client": {
+                    "clientName": "WEB",
+                    "clientVersion": client_version,
+                    "hl": "en",
+                },
+            },
+            "continuation": token,
+        });
+        let response: serde_json::Value = client
+            .post(format!("{YOUTUBE_BASE_URL}/youtubei/v1/browse"))
+            .query(&[("prettyPrint", "false")])
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let read_before = playlist.tracks.len();
+        read_playlist_items(&response, &mut playlist);
+        pages += 1;
+        if playlist.tracks.len() == read_before {
+            break;
+        }
+    }
+
+    playlist.tracks.truncate(YOUTUBE_PLAYLIST_LIMIT);
+    tracing::debug!(
+        list_id,
+        pages,
+        items = playlist.tracks.len(),
+        "Read YouTube playlist"
+    );
+    Ok(playlist.tracks)
+}
+
+/// Reads the first items of a playlist page, and the client version to ask for the rest with.
+fn parse_playlist_page(html: &str) -> Result<(YouTubePlaylistPage, String), Error> {
+    let client_version = client_version_re()
+        .captures(html)
+        .and_then(|captures| captures.get(1))
+        .map_or(YOUTUBE_CLIENT_VERSION, |version| version.as_str())
+        .to_string();
+
+    let mut playlist = YouTubePlaylistPage::default();
+    read_playlist_items(&parse_yt_initial_data(html)?, &mut playlist);
+    Ok((playlist, client_version))
+}
+
+#[derive(Debug, Default)]
+struct YouTubePlaylistPage {
+    tracks: Vec<ExternalTrack>,
+    continuation: Option<String>,
+}
+
+/// Collects playlist items and the next continuation token from a playlist page or a browse
+/// continuation response. The renderers sit at different depths in each, so the whole tree is
+/// walked.
+fn read_playlist_items(value: &serde_json::Value, playlist: &mut YouTubePlaylistPage) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object {
+                match key.as_str() {
+                    "playlistVideoRenderer" | "playlistPanelVideoRenderer" => {
+                        playlist.tracks.extend(playlist_item(value));
+                    }
+                    "continuationItemRenderer" => {
+                        if let Some(token) = value
+                            .pointer("/continuationEndpoint/continuationCommand/token")
+                            .and_then(serde_json::Value::as_str)
+                        {
+                            playlist.continuation = Some(token.to_string());
+                        }
+                    }
+                    _ => read_playlist_items(value, playlist),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                read_playlist_items(value, playlist);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// An `Artist - Title` video title names its artist; otherwise the uploading channel is used,
+/// without the ` - Topic` suffix of auto-generated music channels. Deleted and private videos
+/// clean to an empty title and are left out.
+fn playlist_item(renderer: &serde_json::Value) -> Option<ExternalTrack> {
+    let raw_title = renderer
+        .pointer("/title/runs/0/text")
+        .or_else(|| renderer.pointer("/title/simpleText"))
+        .and_then(serde_json::Value::as_str)?;
+    let channel = renderer
+        .pointer("/shortBylineText/runs/0/text")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or_default();
+
+    let title = clean_youtube_title(raw_title);
+    let (artist, title) = split_artist_title(&title).unwrap_or_else(|| {
+        let artist = channel.trim_end_matches(" - Topic").trim().to_lowercase();
+        (artist, title)
+    });
+
+    let duration = renderer
+        .get("lengthSeconds")
+        .and_then(serde_json::Value::as_str)
+        .and_then(|seconds| seconds.parse().ok());
+
+    ExternalTrack::new(title, artist, String::new(), None, duration)
+        .map(|track| track.with_raw_title(raw_title))
+}
+
+fn client_version_re() -> &'static regex::Regex {
+    CLIENT_VERSION_RE.get_or_init(|| {
+        regex::Regex::new(r#""INNERTUBE_CONTEXT_CLIENT_VERSION":"([^"]+)""#).unwrap()
+    })
+}
+
+async fn extract_youtube_metadata(url: &str) -> Result<YouTubeMetadata, Error> {
+    let client = reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:150.0) Gecko/20100101 Firefox/150.0")
+        .build()
+        .map_err(|e| Error::from(e.to_string()))?;
+
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| Error::from(e.to_string()))?
+        .text()
+        .await
+        .map_err(|e| Error::from(e.to_string()))?;
+
+    parse_video_page(&resp)
+}
+
+fn parse_video_page(resp: &str) -> Result<YouTubeMetadata, Error> {
+    let data = parse_yt_initial_data(resp)?;
+    let isrc = find_description_isrc(&data);
+    let duration = LENGTH_RE
+        .get_or_init(|| regex::Regex::new(r#""lengthSeconds":"(\d+)""#).unwrap())
+        .captures(resp)
+        .and_then(|captures| captures[1].parse().ok());
+
+    // 1. Attempt to get track details if in "Artist - Song" format
+    let video_details = data.pointer(
+        "/playerOverlays/playerOverlayRenderer/videoDetails/playerOverlayVideoDetailsRenderer",
+    );
+
+    if let Some(v) = video_details {
+        let raw_video_title = v
+            .pointer("/title/simpleText")
+            .and_then(|s| s.as_str())
+            .unwrap_or("");
+
+        let clean_video_title = clean_youtube_title(raw_video_title);
+
+        if let Some((artist, title)) = split_artist_title(&clean_video_title) {
+            return Ok(YouTubeMetadata {
+                raw_title: raw_video_title.to_string(),
+                artist,
+                title,
+                album: "".to_string(),
+                isrc,
+                duration,
+            });
+        }
+    }
+
+    // 2. Attempt to get track details inside description
+    if let Some(panels) = data.get("engagementPanels").and_then(|v| v.as_array()) {
+        for panel in panels {
+            let model = panel.pointer("/engagementPanelSectionListRenderer/content/structuredDescriptionContentRenderer/items/2/horizontalCardListRenderer/cards/0/videoAttributeViewModel");
+
+            if let Some(m) = model {
+                let raw_title = m.get("title").and_then(|v| v.as_str()).unwrap_or("");
+                let artist = m
+                    .get("subtitle")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+
+                let title = clean_youtube_title(raw_title);
+
+                if !title.is_empty() && !artist.is_empty() {
+                    return Ok(YouTubeMetadata {
+                        raw_title: raw_title.to_string(),
+                        title,
+                        artist,
+                        album: m
+                            .pointer("/secondarySubtitle/content")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("")
+                            .to_lowercase(),
+                        isrc,
+                        duration,
+                    });
+                }
+            }
+        }
+    }
+
+    // 3. Fallback to uploader and video title
+    if let Some(v) = video_details {
+        let raw_title = v
+            .pointer("/title/simpleText")
+            .and_then(|s| s.as_str())
+            .unwrap_or("");
+        let uploader = v
+            .pointer("/subtitle/runs/0/text")
+            .and_then(|t| t.as_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        return Ok(YouTubeMetadata {
+            raw_title: raw_title.to_string(),
+            title: clean_youtube_title(raw_title),
+            artist: uploader,
+            album: "".to_string(),
+            isrc,
+            duration,
+        });
+    }
+
+    Err(Error::from("Could not extract valid metadata"))
+}
+
+/// Reads the `ytInitialData` object a YouTube page embeds in a script.
+fn parse_yt_initial_data(html: &str) -> Result<serde_json::Value, Error> {
+    let script_buffer = Rc::new(RefCell::new(String::new()));
+    let json_string = Rc::new(RefCell::new(None));
+
+    let script_buf_handle = Rc::clone(&script_buffer);
+    let json_handle = Rc::clone(&json_string);
+
+    let mut rewriter = HtmlRewriter::new(
+        Settings {
+            element_content_handlers: vec![text!("script", move |t| {
+                let mut buf = script_buf_handle.borrow_mut();
+
+                buf.push_str(t.as_str());
+
+                if t.last_in_text_node() {
+                    if buf.contains("var ytInitialData =")
+                        && let Some(start) = buf.find("var ytInitialData =")
+                    {
+                        let json_part = &buf[start + "var ytInitialData =".len()..];
+                        let trimmed = json_part.trim().trim_end_matches(';');
+
+                        *json_handle.borrow_mut() = Some(trimmed.to_string());
+                    }
+                    buf.clear();
+                }
+                Ok(())
+            })],
+            ..Settings::default()
+        },
+        |_: &[u8]| {},
+    );
+
+    rewriter
+        .write(html.as_bytes())
+        .map_err(|e| Error::from(e.to_string()))?;
+    rewriter.end().map_err(|e| Error::from(e.to_string()))?;
+
+    let raw_json = json_string
+        .take()
+        .ok_or_else(|| Error::from("Could not find ytInitialData"))?;
+    serde_json::from_str(&raw_json).map_err(|e| Error::from(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleans_youtube_title_noise() {
+        assert_eq!(
+            clean_youtube_title("Song Title (Official Video) [HD] feat. Guest Artist"),
+            "song title"
+        );
+        assert_eq!(clean_youtube_title("Another Song ft Guest"), "another song");
+        assert_eq!(
+            clean_youtube_title("Third Song FEATURING Someone"),
+            "third song"
+        );
+    }
+
+    #[test]
+    fn reads_isrc_from_video_description() {
+        let data = serde_json::json!({
+            "engagementPanels": [{"engagementPanelSectionListRenderer": {"content": {
+                "structuredDescriptionContentRenderer": {"items": [
+                    {"videoDescriptionHeaderRenderer": {"title": {"runs": [{"text": "Song"}]}}},
+                    {"expandableVideoDescriptionBodyRenderer": {"attributedDescriptionBodyText": {
+                        "content": "Provided to YouTube by Label\n\nSong · Artist\n\nISRC: GB-AAA-24-00012",
+                    }}},
+                ]},
+            }}}],
+        });
+        assert_eq!(
+            find_description_isrc(&data).as_deref(),
+            Some("GBAAA2400012")
+        );
+
+        let without_isrc = serde_json::json!({
+            "attributedDescription": {"content": "Official video for Song by Artist"},
+        });
+        assert_eq!(find_description_isrc(&without_isrc), None);
+    }
+
+    #[test]
+    fn reads_youtube_playlist_ids() {
+        let list_id = |url: &str| youtube_playlist_id(&url::Url::parse(url).unwrap());
+
+        assert_eq!(
+            list_id("https://www.youtube.com/playlist?list=PLabc123"),
+            Some("PLabc123".to_string())
+        );
+        assert_eq!(
+            list_id("https://music.youtube.com/playlist?list=OLAK5uy_xyz"),
+            Some("OLAK5uy_xyz".to_string())
+        );
+        assert_eq!(
+            list_id("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLabc123"),
+            Some("PLabc123".to_string())
+        );
+        assert_eq!(
+            list_id("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=RDdQw4w9WgXcQ"),
+            None
+        );
+        assert_eq!(list_id("https://www.youtube.com/watch?v=dQw4w9WgXcQ"), None);
+    }
+
+    #[test]
+    fn reads_youtube_playlist_items_and_continuation() {
+        let page = serde_json::json!({
+            "contents": {"twoColumnBrowseResultsRenderer": {"tabs": [{"tabRenderer": {"content": {
+                "sectionListRenderer": {"contents": [{"itemSectionRenderer": {"contents": [{
+                    "playlistVideoListRenderer": {"contents": [
+                        {"playlistVideoRenderer": {
+                            "title": {"runs": [{"text": "Band - Anthem (Official Video)"}]},
+                            "shortBylineText": {"runs": [{"text": "BandVEVO"}]},
+                        }},
+                        {"playlistVideoRenderer": {
+                            "title": {"runs": [{"text": "Quiet Song"}]},
+                            "shortBylineText": {"runs": [{"text": "Singer - Topic"}]},
+                        }},
+                        {"playlistVideoRenderer": {
+                            "title": {"runs": [{"text": "[Private video]"}]},
+                        }},
+                        {"continuationItemRenderer": {"continuationEndpoint": {
+                            "continuationCommand": {"token": "next-page"},
+                        }}},
+                    ]},
+                }]}}]},
+            }}}]}},
+        });
+
+        let mut playlist = YouTubePlaylistPage::default();
+        read_playlist_items(&page, &mut playlist);
+
+        assert_eq!(
+            playlist.tracks,
+            [
+                ExternalTrack::new(
+                    "anthem".to_string(),
+                    "band".to_string(),
+                    String::new(),
+                    None,
+                    None
+                )
+                .unwrap(),
+                ExternalTrack::new(
+                    "quiet song".to_string(),
+                    "singer".to_string(),
+                    String::new(),
+                    None,
+                    None
+                )
+                .unwrap(),
+            ]
+        );
+        assert_eq!(playlist.continuation.take().as_deref(), Some("next-page"));
+
+        let continuation = serde_json::json!({
+            "onResponseReceivedActions": [{"appendContinuationItemsAction": {"continuationItems": [
+                {"playlistVideoRenderer": {
+                    "title": {"runs": [{"text": "Last Song ft. Guest"}]},
+                    "shortBylineText": {"runs": [{"text": "Closer"}]},
+                }},
+            ]}}],
+        });
+        read_playlist_items(&continuation, &mut playlist);
+
+        assert_eq!(playlist.tracks.len(), 3);
+        assert_eq!(playlist.tracks[2].title, "last song");
+        assert_eq!(playlist.tracks[2].artist, "closer");
+        assert_eq!(playlist.continuation, None);
+    }
+
+    #[test]
+    fn reads_a_saved_video_page() {
+        let metadata =
+            parse_video_page(include_str!("../tests/fixtures/youtube_watch.html")).unwrap();
+
+        assert_eq!(metadata.title, "anthem");
+        assert_eq!(metadata.artist, "band");
+        assert_eq!(metadata.isrc.as_deref(), Some("GBAAA2400012"));
+        assert_eq!(metadata.duration, Some(228));
+    }
+
+    #[test]
+    fn reads_a_saved_playlist_page() {
+        let (playlist, client_version) =
+            parse_playlist_page(include_str!("../tests/fixtures/youtube_playlist.html")).unwrap();
+
+        assert_eq!(client_version, "2.20261001.01.00");
+        assert_eq!(
+            playlist
+                .tracks
+                .iter()
+                .map(|track| (track.artist.as_str(), track.title.as_str(), track.duration))
+                .collect::<Vec<_>>(),
+            [
+                ("band", "anthem", Some(228)),
+                ("singer", "quiet song", Some(201))
+            ]
+        );
+        assert_eq!(playlist.continuation.as_deref(), Some("mock-continuation"));
+    }
+}